@@ -0,0 +1,63 @@
+//! 解析命令名的开销
+//!
+//! 已知命令直接用 frame 中的字节查找命令表，不分配 `String`；
+//! 只有未知命令才需要分配，保存小写的命令名
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use mini_redis::{Command, Frame};
+
+/// 记录分配次数的分配器
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// 只有命令名的 frame，`PING` 不带参数时解析不会再分配
+fn frame(name: &'static str) -> Frame {
+    Frame::Array(vec![Frame::Bulk(name.into())])
+}
+
+/// 解析一次命令期间的分配次数
+fn allocations(name: &'static str) -> usize {
+    let frame = frame(name);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let command = Command::from_frame(frame).unwrap();
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    drop(command);
+    after - before
+}
+
+fn from_frame(c: &mut Criterion) {
+    // 小写及大小写混合的已知命令都不分配，未知命令分配一次
+    assert_eq!(0, allocations("ping"));
+    assert_eq!(0, allocations("PiNg"));
+    assert_eq!(1, allocations("pong"));
+
+    let mut group = c.benchmark_group("from_frame");
+    for name in ["ping", "PiNg", "pong"] {
+        group.bench_function(name, |b| {
+            b.iter_batched(|| frame(name), Command::from_frame, BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, from_frame);
+criterion_main!(benches);
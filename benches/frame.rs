@@ -0,0 +1,26 @@
+//! 解析一行很长的 frame 的开销，`get_line` 使用 `memchr` 查找行尾
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use mini_redis::Frame;
+
+/// 内容为 `len` 个字节的 Simple frame，`with_cr` 时每 64 个字节有一个单独的 b'\r'
+fn long_line(len: usize, with_cr: bool) -> Vec<u8> {
+    let mut src = vec![b'+'];
+    src.extend((0..len).map(|i| if with_cr && i % 64 == 63 { b'\r' } else { b'x' }));
+    src.extend_from_slice(b"\r\n");
+    src
+}
+
+fn parse_long_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_long_line");
+    for (name, with_cr) in [("plain", false), ("lone_cr", true)] {
+        let src = long_line(64 * 1024, with_cr);
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_function(name, |b| b.iter(|| Frame::parse(&mut Cursor::new(&src[..])).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_long_line);
+criterion_main!(benches);
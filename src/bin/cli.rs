@@ -5,7 +5,7 @@ use std::{
 };
 
 use bytes::Bytes;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
 use mini_redis::{client, DEFAULT_PORT};
 
@@ -16,6 +16,20 @@ async fn main() -> mini_redis::Result<()> {
     // 解析命令行参数
     let cli = Cli::parse();
 
+    // 列出客户端支持的命令，无需连接服务端
+    if cli.list_commands {
+        for command in Cli::command().get_subcommands() {
+            println!("{}", command.get_name());
+        }
+
+        return Ok(())
+    }
+
+    let command = match cli.command {
+        Some(command) => command,
+        None => return Err("a command must be provided, see --help or --list-commands".into()),
+    };
+
     // 确定远程服务地址
     let addr = format!("{}:{}", cli.host, cli.port);
 
@@ -23,7 +37,7 @@ async fn main() -> mini_redis::Result<()> {
     let mut client = client::connect(&addr).await?;
 
     // 处理请求的命令
-    match cli.command {
+    match command {
         Command::Ping { msg } => {
             let value = client.ping(msg).await?;
             if let Ok(string) = str::from_utf8(&value) {
@@ -52,7 +66,7 @@ async fn main() -> mini_redis::Result<()> {
             println!("OK");
         },
         Command::Publish { channel, message } => {
-            client.publish(&channel, message.into()).await?;
+            client.publish(&channel, message).await?;
             println!("Publish OK");
         },
         // 进入订阅者客户端模式
@@ -82,7 +96,11 @@ async fn main() -> mini_redis::Result<()> {
 )]
 struct Cli {
     #[clap(subcommand)]
-    command: Command,
+    command: Option<Command>,
+
+    /// 列出客户端支持的所有命令
+    #[clap(long)]
+    list_commands: bool,
 
     #[clap(name = "hostname", long, default_value = "127.0.0.1")]
     host: String,
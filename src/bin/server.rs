@@ -1,26 +1,53 @@
-use clap::Parser;
+use clap::{ArgEnum, Parser};
 use tokio::{
     net::TcpListener,
     signal,
+    time::Duration,
 };
-use tracing_subscriber;
 
 use mini_redis::{server, DEFAULT_PORT};
 
 #[tokio::main]
 pub async fn main() -> mini_redis::Result<()> {
-    // 初始化默认日志收集？
-    set_up_logging()?;
+    let cli =Cli::parse();
+
+    set_up_logging(cli.log_format, cli.log_level)?;
     println!("Hello Redis Server...");
 
-    let cli =Cli::parse();
     let port = cli.port.unwrap_or(DEFAULT_PORT);
 
     // 绑定一个 TCP 监听器
     let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
 
+    let unknown_command = if cli.ignore_unknown_commands {
+        server::UnknownCommandMode::Lenient
+    } else {
+        server::UnknownCommandMode::Strict
+    };
+
+    let accept_error = if cli.retry_accept_errors {
+        server::AcceptErrorPolicy::Retry
+    } else {
+        server::AcceptErrorPolicy::Fail
+    };
+
+    let config = server::Config {
+        enable_debug_command: cli.enable_debug_command,
+        unknown_command,
+        save_path: cli.save_path,
+        notify_keyspace_events: cli.notify_keyspace_events,
+        accept_error,
+        rate_limit: cli.rate_limit,
+        idle_timeout: match cli.timeout {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => Some(server::DEFAULT_IDLE_TIMEOUT),
+        },
+        ..server::Config::default()
+    };
+
     // 接收 ctrl_c 作为关闭信号
-    server::run(listener, signal::ctrl_c()).await;
+    server::run_with_config(listener, signal::ctrl_c(), config).await;
 
     Ok(())
 }
@@ -31,9 +58,59 @@ struct Cli {
     // 长命令格式：--port NUM
     #[clap(long)]
     port: Option<u16>,
+
+    /// 允许执行 DEBUG 命令
+    #[clap(long)]
+    enable_debug_command: bool,
+
+    /// 忽略未知命令，不返回错误
+    #[clap(long)]
+    ignore_unknown_commands: bool,
+
+    /// SAVE 写入的快照文件，启动时从中恢复数据
+    #[clap(long)]
+    save_path: Option<std::path::PathBuf>,
+
+    /// 键被修改时发布键空间通知
+    #[clap(long)]
+    notify_keyspace_events: bool,
+
+    /// 接受连接出错时一直重试，不停止服务
+    #[clap(long)]
+    retry_accept_errors: bool,
+
+    /// 每个连接每秒最多执行的命令数量，默认不限制
+    #[clap(long)]
+    rate_limit: Option<u64>,
+
+    /// 客户端空闲多少秒后关闭连接，0 表示不关闭，默认 300 秒
+    #[clap(long)]
+    timeout: Option<u64>,
+
+    /// 日志的格式
+    #[clap(long, arg_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// 输出的最低日志级别：trace、debug、info、warn、error
+    #[clap(long, default_value = "info")]
+    log_level: tracing::Level,
 }
 
-fn set_up_logging() -> mini_redis::Result<()> {
+/// 日志的输出格式
+#[derive(ArgEnum, Clone, Copy, Debug)]
+enum LogFormat {
+    /// 默认的多字段文本格式
+    Text,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+fn set_up_logging(format: LogFormat, level: tracing::Level) -> mini_redis::Result<()> {
     // https://docs.rs/tracing-subscriber/0.3.16/tracing_subscriber/fmt/index.html
-    tracing_subscriber::fmt::try_init()
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+
+    match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    }
 }
@@ -19,13 +19,16 @@
 //!     assert_eq!(val, "bar");
 //! }
 //! ```
-use std::time::Duration;
+use std::{pin::Pin, time::Duration};
 
 use bytes::Bytes;
 use tokio::{
     net::ToSocketAddrs,
     runtime::Runtime,
 };
+use tokio_stream::{Stream, StreamExt};
+
+use crate::cmd::SetOptions;
 
 pub use crate::client::Message;
 
@@ -52,12 +55,22 @@ pub struct BlockingSubscriber {
 
     // 一个 `current_thread` 运行时，用于在订阅客户端上以阻塞的方式运行异步操作
     rt: Runtime,
+
+    // 连接断开后是否自动重连并重新订阅
+    reconnect: bool,
 }
 
 /// 由 `Subscriber::into_iter()` 返回的迭代器 
 pub struct SubscriberIterator {
     inner: crate::client::Subscriber,
     rt: Runtime,
+    reconnect: bool,
+}
+
+/// 由 `BlockingClient::scan_iter()` 返回的迭代器，逐个产生匹配的键
+pub struct ScanIterator<'a> {
+    inner: Pin<Box<dyn Stream<Item = crate::Result<String>> + 'a>>,
+    rt: &'a Runtime,
 }
 
 /// 与 Redis 服务建立连接并返回一个 `BlockingClient`
@@ -99,27 +112,55 @@ impl BlockingClient {
         self.rt.block_on(self.inner.set_expires(key, value, expiration))
     }
 
+    pub fn set_options(&mut self, key: &str, value: Bytes, options: SetOptions) -> crate::Result<Option<Bytes>> {
+        self.rt.block_on(self.inner.set_options(key, value, options))
+    }
+
+    pub fn scan(&mut self, cursor: u64, pattern: Option<String>, count: Option<u64>) -> crate::Result<(u64, Vec<String>)> {
+        self.rt.block_on(self.inner.scan(cursor, pattern, count))
+    }
+
+    pub fn scan_iter(&mut self, pattern: Option<String>) -> ScanIterator<'_> {
+        ScanIterator {
+            inner: Box::pin(self.inner.scan_iter(pattern)),
+            rt: &self.rt,
+        }
+    }
+
     pub fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
         self.rt.block_on(self.inner.publish(channel, message))
     }
 
+    pub fn close(self) -> crate::Result<()> {
+        self.rt.block_on(self.inner.close())
+    }
+
     pub fn subscribe(self, channels: Vec<String>) -> crate::Result<BlockingSubscriber> {
         let subscriber = self.rt.block_on(self.inner.subscribe(channels))?;
 
         Ok(BlockingSubscriber {
             inner: subscriber,
             rt: self.rt,
+            reconnect: false,
         })
     }
 }
 
 impl BlockingSubscriber {
     pub fn get_subscribed(&self) -> &[String] {
-        &self.inner.get_subscribed()
+        self.inner.get_subscribed()
     }
 
     pub fn next_message(&mut self) -> crate::Result<Option<Message>> {
-        self.rt.block_on(self.inner.next_message())
+        next_message(&mut self.inner, &self.rt, self.reconnect)
+    }
+
+    /// 设置断线重连模式
+    ///
+    /// 开启后，若读取消息时连接断开，会重新连接服务端并订阅相同的频道，然后继续读取。
+    /// 注意：断线期间发布的消息将会丢失。
+    pub fn set_reconnect(&mut self, reconnect: bool) {
+        self.reconnect = reconnect;
     }
 
     pub fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
@@ -130,11 +171,18 @@ impl BlockingSubscriber {
         self.rt.block_on(self.inner.unsubscribe(channels))
     }
 
+}
+
+impl IntoIterator for BlockingSubscriber {
+    type Item = crate::Result<Message>;
+    type IntoIter = SubscriberIterator;
+
     /// 将自身转化为 `SubscriberIterator`
-    pub fn into_iter(self) -> impl Iterator<Item = crate::Result<Message>> {
+    fn into_iter(self) -> SubscriberIterator {
         SubscriberIterator {
             inner: self.inner,
-            rt: self.rt
+            rt: self.rt,
+            reconnect: self.reconnect,
         }
     }
 }
@@ -144,6 +192,32 @@ impl Iterator for SubscriberIterator {
 
     fn next(&mut self) -> Option<Self::Item> {
         // transpose 将 Result 和 Option 互相转换
-        self.rt.block_on(self.inner.next_message()).transpose()
+        next_message(&mut self.inner, &self.rt, self.reconnect).transpose()
+    }
+}
+
+impl Iterator for ScanIterator<'_> {
+    type Item = crate::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rt.block_on(self.inner.next())
     }
 }
+
+/// 读取订阅的频道发送的消息
+/// 若开启了断线重连，连接断开（或读取出错）时重新连接并订阅，之后继续读取
+fn next_message(
+    subscriber: &mut crate::client::Subscriber,
+    rt: &Runtime,
+    reconnect: bool,
+) -> crate::Result<Option<Message>> {
+    rt.block_on(async {
+        loop {
+            match subscriber.next_message().await {
+                Ok(Some(message)) => return Ok(Some(message)),
+                res if !reconnect => return res,
+                _ => subscriber.reconnect().await?,
+            }
+        }
+    })
+}
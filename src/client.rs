@@ -1,16 +1,28 @@
 use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
     io::{Error, ErrorKind},
-    time::Duration,
+    net::SocketAddr,
+    ops::Bound,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
 };
 
 use async_stream::try_stream;
 use bytes::Bytes;
-use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    time,
+};
 use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
 use crate::{
-    cmd::{Get, Set, Publish, Subscribe, Unsubscribe, Ping},
+    cmd::{Append, Del, Get, Getrange, Hello, Incr, Keys, Save, Set, SetOptions, Publish, Subscribe, Unsubscribe, Ping, Sadd, Smembers, SetAlgebra, Zadd, Zrangebyscore, Zrem, Zrank, Zincrby, Pfadd, Pfcount, Geoadd, Geodist, GeoUnit, Lastsave, Copy, Ttl, Mset, Scan, Setnx, Getset, Quit, Delifeq},
+    db::SetOperation,
     Connection, Frame,
 };
 
@@ -19,6 +31,24 @@ use crate::{
 #[derive(Debug)]
 pub struct Client {
     connection: Connection,
+    /// `is_alive` 超时后连接中可能残留未发送的 `PING` 或未读取的 `PONG`，
+    /// 此后的命令都返回错误，不再使用该连接
+    poisoned: bool,
+}
+
+/// `HELLO` 返回的服务端信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// 服务名称，如 `mini-redis`
+    pub server: String,
+    /// 服务版本
+    pub version: String,
+    /// 协商后连接使用的 RESP 协议版本
+    pub proto: u8,
+    /// 运行模式，如 `standalone`
+    pub mode: String,
+    /// 角色，如 `master`
+    pub role: String,
 }
 
 /// 一个实现了订阅/取消模式的客户端
@@ -26,6 +56,8 @@ pub struct Client {
 pub struct Subscriber {
     client: Client,
     subscribed_channels: Vec<String>,
+    // 服务端地址，断线重连时使用
+    addr: SocketAddr,
 }
 
 /// 订阅频道发送的消息
@@ -33,8 +65,45 @@ pub struct Subscriber {
 pub struct Message {
     pub channel: String,
     pub content: Bytes,
+    /// 消息在频道中的序号，服务端开启了消息序号时才有
+    /// 序号不连续说明中间的消息已丢失，如断线重连期间发布的消息
+    pub seq: Option<u64>,
+}
+
+/// `subscribe_resilient` 返回的消息流中的事件
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    /// 订阅频道发送的消息
+    Message(Message),
+    /// 连接断开后已重新连接并订阅了所有频道
+    Reconnected,
+}
+
+/// 有界的订阅消息流，由 `Subscriber::into_bounded_stream` 返回
+/// 已接收但未被消费的消息数量达到上限后，不再从连接中读取数据，
+/// 由 TCP 的流量控制让服务端放慢发送
+#[derive(Debug)]
+pub struct BoundedStream {
+    rx: mpsc::Receiver<crate::Result<Message>>,
+}
+
+/// `Client::acquire_lock` 获得的锁，保存着写入锁的键中的随机 token
+///
+/// 需要调用 `release` 释放锁，只有 token 仍与键的值相同时才会删除键，
+/// 因此锁过期后被其它客户端获得时，不会误删别人的锁；
+/// 直接 drop 时不会访问服务端，锁在有效期过后自动释放
+#[derive(Debug, Clone)]
+pub struct LockGuard {
+    key: String,
+    token: Bytes,
 }
 
+/// 断线重连时，两次重试之间的最大间隔
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(2);
+
+/// `scan_iter` 每次 `SCAN` 使用的 `COUNT`
+const SCAN_COUNT: u64 = 100;
+
 /// 通过给定的地址来和服务端建立起连接
 ///
 /// # 示例
@@ -55,7 +124,66 @@ pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
     // 尝试和服务建立连接
     let socket = TcpStream::connect(addr).await?;
 
-    Ok(Client { connection: Connection::new(socket) })
+    Ok(Client { connection: Connection::new(socket), poisoned: false })
+}
+
+/// 连接服务端并订阅 `channels`，返回自动断线重连的消息流
+///
+/// 读取出错或连接断开时，重新连接服务端并订阅所有频道，随后产生一个
+/// `SubscriptionEvent::Reconnected` 事件，再继续产生消息。
+/// 断开到重新订阅成功之间发布的消息会丢失。
+/// 首次连接失败，或重连的重试间隔超过上限后，消息流产生错误并结束。
+pub fn subscribe_resilient<T: ToSocketAddrs>(
+    addr: T,
+    channels: Vec<String>,
+) -> impl Stream<Item = crate::Result<SubscriptionEvent>> {
+    try_stream! {
+        let mut subscriber = connect(addr).await?.subscribe(channels).await?;
+
+        loop {
+            match subscriber.next_message().await {
+                Ok(Some(message)) => yield SubscriptionEvent::Message(message),
+                Ok(None) | Err(_) => {
+                    subscriber.reconnect().await?;
+                    yield SubscriptionEvent::Reconnected;
+                },
+            }
+        }
+    }
+}
+
+/// 将由 `Simple` 或 `Bulk` 组成的数组解码为字符串列表，用于解析 `execute` 的回复
+pub fn frame_to_string_vec(frame: Frame) -> crate::Result<Vec<String>> {
+    match frame {
+        Frame::Array(parts) => parts.into_iter().map(frame_to_string).collect(),
+        frame => Err(frame.to_error()),
+    }
+}
+
+/// 将 [名称, 值, 名称, 值, ..] 形式的数组解码为 (名称, 值) 的列表，值保持原样
+/// 如 `COMMAND DOCS` 的回复，值可以是嵌套的数组
+pub fn frame_to_pairs(frame: Frame) -> crate::Result<Vec<(String, Frame)>> {
+    let parts = match frame {
+        Frame::Array(parts) if parts.len() % 2 == 0 => parts,
+        frame => return Err(frame.to_error()),
+    };
+
+    let mut pairs = Vec::with_capacity(parts.len() / 2);
+    let mut parts = parts.into_iter();
+    while let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+        pairs.push((frame_to_string(name)?, value));
+    }
+
+    Ok(pairs)
+}
+
+/// 将 `Simple` 或 UTF-8 编码的 `Bulk` 解码为字符串
+fn frame_to_string(frame: Frame) -> crate::Result<String> {
+    match frame {
+        Frame::Simple(value) => Ok(value),
+        Frame::Bulk(value) => String::from_utf8(value.to_vec()).map_err(|_| Frame::Bulk(value).to_error()),
+        frame => Err(frame.to_error()),
+    }
 }
 
 impl Client {
@@ -84,7 +212,7 @@ impl Client {
         let frame = Get::new(key).into_frame();
         debug!(?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.connection()?.write_frame(&frame).await?;
 
         match self.read_response().await? {
             Frame::Simple(value) => Ok(Some(value.into())),
@@ -94,6 +222,144 @@ impl Client {
         }
     }
 
+    /// 返回键对应的值在 `start` 与 `end` 之间（包含两端）的部分，负数表示从末尾倒数
+    /// 键不存在时返回空的 `Bytes`
+    #[instrument(skip(self))]
+    pub async fn getrange(&mut self, key: &str, start: i64, end: i64) -> crate::Result<Bytes> {
+        let frame = Getrange::new(key, start, end).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 键的值等于 `value` 时删除它，返回是否删除，比较与删除在服务端一次完成
+    #[instrument(skip(self))]
+    pub async fn delifeq(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        let frame = Delifeq::new(key, value).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(deleted) => Ok(deleted == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 获取以 `key` 为名的锁，`ttl` 后锁自动过期，锁已被持有时返回 `None`
+    /// 相当于 `SET key <token> NX PX <ttl>`，token 随机生成，释放锁时用来确认持有者
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     if let Some(lock) = client.acquire_lock("lock", Duration::from_secs(10)).await.unwrap() {
+    ///         // 持有锁时的操作
+    ///         lock.release(&mut client).await.unwrap();
+    ///     }
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn acquire_lock(&mut self, key: &str, ttl: Duration) -> crate::Result<Option<LockGuard>> {
+        let token = lock_token();
+        let options = SetOptions::new().nx().expire(ttl);
+        let frame = Set::with_options(key, token.clone(), options).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(Some(LockGuard { key: key.to_string(), token })),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 删除键，无论其存储的是何种类型，返回实际删除的键数量
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[String]) -> crate::Result<u64> {
+        let frame = Del::new(keys).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 返回所有匹配 `pattern` 的键，支持 `*`、`?`、`[...]` 及 `\` 转义
+    #[instrument(skip(self))]
+    pub async fn keys(&mut self, pattern: &str) -> crate::Result<Vec<String>> {
+        let frame = Keys::new(pattern).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(keys) => keys
+                .into_iter()
+                .map(|key| match key {
+                    Frame::Bulk(key) => Ok(String::from_utf8(key.to_vec())?),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 执行一次 `SCAN`，返回下一次使用的游标及本次遍历到的键，游标为 0 时遍历结束
+    /// `count` 是服务端每次检查的键数量的提示，返回的键可能更多或更少
+    #[instrument(skip(self))]
+    pub async fn scan(&mut self, cursor: u64, pattern: Option<String>, count: Option<u64>) -> crate::Result<(u64, Vec<String>)> {
+        let frame = Scan::new(cursor, pattern, count).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(mut parts) if parts.len() == 2 => {
+                let keys = frame_to_string_vec(parts.pop().unwrap())?;
+                let cursor = frame_to_string(parts.pop().unwrap())?;
+                let cursor = cursor.parse().map_err(|_| format!("invalid SCAN cursor {:?}", cursor))?;
+                Ok((cursor, keys))
+            },
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 遍历所有匹配 `pattern` 的键，自动使用返回的游标继续 `SCAN`，直到游标为 0
+    /// 整个遍历期间一直存在的键恰好产生一次，遍历期间新增或删除的键可能产生也可能不产生
+    pub fn scan_iter(&mut self, pattern: Option<String>) -> impl Stream<Item = crate::Result<String>> + '_ {
+        try_stream! {
+            let mut cursor = 0;
+            loop {
+                let (next, keys) = self.scan(cursor, pattern.clone(), Some(SCAN_COUNT)).await?;
+                for key in keys {
+                    yield key;
+                }
+
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+    }
+
     /// 将一个值保存到一个键上
     /// 此键上的值可以被重写覆盖，若值被重写，则其有效期也将重置
     ///
@@ -154,10 +420,94 @@ impl Client {
         self.set_cmd(Set::new(key, value, Some(expiration))).await
     }
 
+    /// 将 `value` 序列化为 JSON 后保存到键上，需开启 `json` feature
+    #[cfg(feature = "json")]
+    #[instrument(skip(self, value))]
+    pub async fn set_json<T: serde::Serialize + ?Sized>(&mut self, key: &str, value: &T) -> crate::Result<()> {
+        let value = serde_json::to_vec(value)?;
+        self.set(key, value.into()).await
+    }
+
+    /// 读取键的值并按 JSON 解析，键不存在时返回 `None`，需开启 `json` feature
+    /// 值不是合法的 JSON 或与 `T` 不符时返回 `serde_json::Error`，
+    /// 可用 `downcast_ref` 与连接错误区分
+    #[cfg(feature = "json")]
+    #[instrument(skip(self))]
+    pub async fn get_json<T: serde::de::DeserializeOwned>(&mut self, key: &str) -> crate::Result<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按 `options` 设置键的值，与 `SET key value [NX | XX] [GET] [EX | PX | EXAT | PXAT | KEEPTTL]` 相同
+    ///
+    /// 指定 `GET` 时返回键原先的值，键不存在时返回 `None`，无论是否写入；
+    /// 未指定 `GET` 时写入成功返回 `None`，不满足 `NX`/`XX` 条件时返回错误
+    #[instrument(skip(self))]
+    pub async fn set_options(&mut self, key: &str, value: Bytes, options: SetOptions) -> crate::Result<Option<Bytes>> {
+        let frame = Set::with_options(key, value, options).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(previous) if options.is_get() => Ok(Some(previous)),
+            Frame::Null if options.is_get() => Ok(None),
+            Frame::Simple(response) if response == "OK" => Ok(None),
+            Frame::Null => Err("SET condition not met, value not written".into()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 设置键的值并返回原先的值，键不存在时返回 `None`，写入后键不再有有效期
+    #[instrument(skip(self))]
+    pub async fn getset(&mut self, key: &str, value: Bytes) -> crate::Result<Option<Bytes>> {
+        let frame = Getset::new(key, value).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(previous) => Ok(Some(previous)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 键不存在时设置它的值，返回是否写入，键已存在时不修改原有的值
+    #[instrument(skip(self))]
+    pub async fn set_nx(&mut self, key: &str, value: Bytes) -> crate::Result<bool> {
+        let frame = Setnx::new(key, value).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(written) => Ok(written == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 一次设置多个键的值，所有键值在服务端一次写入，其它客户端不会只看到其中一部分
+    /// 与 `set` 相同，已有的值及有效期都会被覆盖
+    #[instrument(skip(self))]
+    pub async fn mset(&mut self, pairs: Vec<(String, Bytes)>) -> crate::Result<()> {
+        let frame = Mset::new(pairs).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
         let frame = cmd.into_frame();
 
-        self.connection.write_frame(&frame).await?;
+        self.connection()?.write_frame(&frame).await?;
 
         match self.read_response().await? {
             Frame::Simple(response) if response == "OK" => Ok(()),
@@ -186,56 +536,29 @@ impl Client {
     /// ```
     #[instrument(skip(self))]
     pub async fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
-        let frame = Publish::new(channel, message).into_frame();
-        debug!(request = ?frame);
-
-        self.connection.write_frame(&frame).await?;
-
-        match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
-            frame => Err(frame.to_error()),
-        }
+        self.publish_cmd(Publish::new(channel, message)).await
     }
 
-    /// 客户端订阅指定的频道
-    /// 一旦客户端执行了订阅的命令，它将消耗掉自身并返回一个 `Subscriber`
-    /// 新的 `Subscriber` 客户端会保持连接，接收订阅的频道的消息，它仅可执行订阅相关的命令
+    /// 与 `publish` 相同，但频道缓存已满时服务端会等待订阅者取走消息，而不是丢弃最早的消息
+    /// 订阅者读取得慢时，此调用也会变慢
     #[instrument(skip(self))]
-    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
-        self.subscribe_cmd(&channels).await?;
-
-        Ok(Subscriber {
-            client: self,
-            subscribed_channels: channels,
-        })
+    pub async fn publish_wait(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> {
+        self.publish_cmd(Publish::new(channel, message).wait()).await
     }
 
-    //// subscribe 命令的核心逻辑
-    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
-        let frame = Subscribe::new(channels).into_frame();
+    async fn publish_cmd(&mut self, cmd: Publish) -> crate::Result<u64> {
+        let frame = cmd.into_frame();
         debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.connection()?.write_frame(&frame).await?;
 
-        // 在 `Subscribe` 命令的实现中，使用 `drain` 消费 channels 
-        // 每订阅一个频道，就会返回一条消息，故消息顺序是与 channels 一致的
-        // 格式为 ["subscrib", channel, sub_nums]
-        for channel in channels {
-            match self.read_response().await? {
-                Frame::Array(frame) => match frame.as_slice() {
-                    [subscribe, schannel, ..]
-                        if *subscribe == "subscribe" && *schannel == channel => {},
-                    _ => return Err(Frame::Array(frame).to_error()),
-                },
-                frame => return Err(frame.to_error()),
-            }
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
         }
-
-        Ok(())
     }
 
-    /// Ping 服务端
-    /// 未指定消息时返回 `PONG`，否则返回 `Ping` 相同的消息
+    /// 向集合中添加成员，返回新添加的成员数量
     ///
     /// # 示例
     ///
@@ -249,81 +572,711 @@ impl Client {
     ///         Err(_) => panic!("failed to establish connnect"),
     ///     };
     ///
-    ///     let pong = client.ping(None).await.unwrap();
-    ///     assert_eq!(b"PONG", &pong[..]);
+    ///     let added = client.sadd("fruits", vec!["apple".into(), "pear".into()]).await.unwrap();
+    ///     assert_eq!(2, added);
     /// }
     /// ```
     #[instrument(skip(self))]
-    pub async fn ping(&mut self, msg: Option<String>) -> crate::Result<Bytes> {
-        let frame = Ping::new(msg).into_frame();
+    pub async fn sadd(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let frame = Sadd::new(key, members).into_frame();
+        debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.connection()?.write_frame(&frame).await?;
 
-        // Simple 或 Bulk 是正常返回值
         match self.read_response().await? {
-            Frame::Simple(value) => Ok(value.into()),
-            Frame::Bulk(value) => Ok(value),
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
             frame => Err(frame.to_error()),
         }
     }
 
-    /// 从当前连接中读取返回消息
-    async fn read_response(&mut self) -> crate::Result<Frame> {
-        let response = self.connection.read_frame().await?;
-        debug!(?response);
+    /// 返回集合的所有成员，键不存在时返回空列表
+    #[instrument(skip(self))]
+    pub async fn smembers(&mut self, key: &str) -> crate::Result<Vec<Bytes>> {
+        let frame = Smembers::new(key).into_frame();
+        debug!(request = ?frame);
 
-        match response {
-            Some(Frame::Error(msg)) => Err(msg.into()),
-            Some(frame) => Ok(frame),
-            None => {
-                // 正常来讲，一定会有返回消息，若为 None 服务端断开了连接
-                let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
-                Err(err.into())
-            }
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(members) => members
+                .into_iter()
+                .map(|member| match member {
+                    Frame::Bulk(member) => Ok(member),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
         }
     }
-}
 
-impl Subscriber {
-    /// 返回已订阅的频道列表
-    pub fn get_subscribed(&self) -> &[String] {
-        &self.subscribed_channels
+    /// 计算 `keys` 对应集合的交集并保存至 `destination`，返回结果的成员数量
+    /// 结果为空集时 `destination` 会被删除
+    #[instrument(skip(self))]
+    pub async fn sinterstore(&mut self, destination: &str, keys: &[String]) -> crate::Result<u64> {
+        self.set_operation_store(SetOperation::Inter, destination, keys).await
     }
 
-    pub fn into_stream(mut self) -> impl Stream<Item = crate::Result<Message>> {
-        try_stream! {
-            while let Some(message) = self.next_message().await? {
-                yield message;
-            }
-        }
+    /// 计算 `keys` 对应集合的并集并保存至 `destination`，返回结果的成员数量
+    #[instrument(skip(self))]
+    pub async fn sunionstore(&mut self, destination: &str, keys: &[String]) -> crate::Result<u64> {
+        self.set_operation_store(SetOperation::Union, destination, keys).await
     }
 
-    /// 接收订阅的频道发送的消息
-    /// 正确的消息格式为 ["message", channel, msg]
+    /// 计算第一个集合与其余集合的差集并保存至 `destination`，返回结果的成员数量
     #[instrument(skip(self))]
-    pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
-        match self.client.connection.read_frame().await? {
-            Some(frame) => {
-                debug!(?frame);
-
-                match frame {
-                    Frame::Array(frame) => match frame.as_slice() {
-                        [message, channel, content] if *message == "message" => Ok(Some(Message {
-                            channel: channel.to_string(),
-                            content: Bytes::from(content.to_string()),
-                        })),
-                        _ => Err(Frame::Array(frame).to_error()),
-                    },
-                    frame => Err(frame.to_error()),
-                }
-            }
-            // 连接断开
-            None => Ok(None),
-        }
+    pub async fn sdiffstore(&mut self, destination: &str, keys: &[String]) -> crate::Result<u64> {
+        self.set_operation_store(SetOperation::Diff, destination, keys).await
     }
 
-    /// 添加新的订阅列表
-    #[instrument(skip(self))]
+    async fn set_operation_store(&mut self, op: SetOperation, destination: &str, keys: &[String]) -> crate::Result<u64> {
+        let frame = SetAlgebra::new(op, Some(destination.to_string()), keys).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 向有序集合中添加成员，已存在的成员会更新分值，返回新添加的成员数量
+    #[instrument(skip(self))]
+    pub async fn zadd(&mut self, key: &str, members: Vec<(f64, Bytes)>) -> crate::Result<u64> {
+        let frame = Zadd::new(key, members).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 按分值从小到大返回分值在 `min` 与 `max` 之间的成员
+    /// `limit` 为 (偏移量, 数量)，数量为负数时返回偏移量之后的所有成员
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::ops::Bound;
+    ///
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     // 相当于 `ZRANGEBYSCORE rank (1 +inf LIMIT 0 10`
+    ///     let members = client
+    ///         .zrangebyscore("rank", Bound::Excluded(1.0), Bound::Unbounded, Some((0, 10)))
+    ///         .await
+    ///         .unwrap();
+    ///     println!("got = {:?}", members);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn zrangebyscore(
+        &mut self,
+        key: &str,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        limit: Option<(u64, i64)>,
+    ) -> crate::Result<Vec<Bytes>> {
+        let frame = Zrangebyscore::new(key, min, max, false, limit).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(members) => members
+                .into_iter()
+                .map(|member| match member {
+                    Frame::Bulk(member) => Ok(member),
+                    frame => Err(frame.to_error()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 从有序集合中删除成员，返回实际删除的成员数量
+    #[instrument(skip(self))]
+    pub async fn zrem(&mut self, key: &str, members: Vec<Bytes>) -> crate::Result<u64> {
+        let frame = Zrem::new(key, members).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 返回成员按分值从小到大的排名（从 0 开始），键或成员不存在时返回 `None`
+    #[instrument(skip(self))]
+    pub async fn zrank(&mut self, key: &str, member: Bytes) -> crate::Result<Option<u64>> {
+        let frame = Zrank::new(key, member).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(rank) => Ok(Some(u64::try_from(rank)?)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 为有序集合中成员的分值加上 `increment`，返回新的分值
+    #[instrument(skip(self))]
+    pub async fn zincrby(&mut self, key: &str, increment: f64, member: Bytes) -> crate::Result<f64> {
+        let frame = Zincrby::new(key, increment, member).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(score) => std::str::from_utf8(&score)?
+                .parse()
+                .map_err(|_| "protocol error; invalid score".into()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 将 `source` 的值及有效期复制到 `destination`，返回是否复制
+    /// `destination` 已存在且 `replace` 为 `false` 时不复制
+    #[instrument(skip(self))]
+    pub async fn copy(&mut self, source: &str, destination: &str, replace: bool) -> crate::Result<bool> {
+        let frame = Copy::new(source, destination, replace).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 向 HyperLogLog 中添加元素，估算的基数改变时返回 `true`
+    #[instrument(skip(self))]
+    pub async fn pfadd(&mut self, key: &str, elements: Vec<Bytes>) -> crate::Result<bool> {
+        let frame = Pfadd::new(key, elements).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 合并 `keys` 对应的 HyperLogLog，返回估算的基数
+    #[instrument(skip(self))]
+    pub async fn pfcount(&mut self, keys: &[String]) -> crate::Result<u64> {
+        let frame = Pfcount::new(keys).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 添加地理位置，`members` 为 (经度, 纬度, 成员)，返回新添加的成员数量
+    #[instrument(skip(self))]
+    pub async fn geoadd(&mut self, key: &str, members: Vec<(f64, f64, Bytes)>) -> crate::Result<u64> {
+        let frame = Geoadd::new(key, members).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(u64::try_from(response)?),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 返回两个地理位置之间以 `unit` 为单位的距离，任意一个成员不存在时返回 `None`
+    #[instrument(skip(self))]
+    pub async fn geodist(&mut self, key: &str, member1: Bytes, member2: Bytes, unit: GeoUnit) -> crate::Result<Option<f64>> {
+        let frame = Geodist::new(key, member1, member2, unit).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(distance) => std::str::from_utf8(&distance)?
+                .parse()
+                .map(Some)
+                .map_err(|_| "protocol error; invalid distance".into()),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 将键存储的整数加一，返回新的值，键不存在时视其值为 0
+    #[instrument(skip(self))]
+    pub async fn incr(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = Incr::new(key).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 将键存储的整数减一，返回新的值，键不存在时视其值为 0
+    #[instrument(skip(self))]
+    pub async fn decr(&mut self, key: &str) -> crate::Result<i64> {
+        let frame = Incr::decr(key).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 返回键剩余的有效期（秒），键不存在时为 -2，键没有有效期时为 -1
+    #[instrument(skip(self))]
+    pub async fn ttl(&mut self, key: &str) -> crate::Result<i64> {
+        self.ttl_cmd(Ttl::new(key)).await
+    }
+
+    /// 返回键剩余的有效期（毫秒），键不存在时为 -2，键没有有效期时为 -1
+    #[instrument(skip(self))]
+    pub async fn pttl(&mut self, key: &str) -> crate::Result<i64> {
+        self.ttl_cmd(Ttl::pttl(key)).await
+    }
+
+    /// 发送 `TTL` 或 `PTTL` 命令，返回回复的整数
+    async fn ttl_cmd(&mut self, cmd: Ttl) -> crate::Result<i64> {
+        let frame = cmd.into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 在键存储的字符串末尾追加 `value`，返回追加后的长度
+    #[instrument(skip(self))]
+    pub async fn append(&mut self, key: &str, value: Bytes) -> crate::Result<u64> {
+        let frame = Append::new(key, value).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(len) => Ok(len as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 与服务端协商 RESP 协议版本，返回服务端信息
+    /// 不指定版本时保持当前协议不变，协商的版本会保存在连接上，之后的回复按此版本解析
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protover: Option<u8>) -> crate::Result<ServerInfo> {
+        let frame = Hello::new(protover.map(u64::from)).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        let fields = match self.read_response().await? {
+            Frame::Array(fields) => fields,
+            frame => return Err(frame.to_error()),
+        };
+
+        // 回复为 [名称, 值, ..]
+        let field = |name: &str| {
+            fields
+                .chunks(2)
+                .find(|pair| pair[0] == name)
+                .and_then(|pair| pair.get(1))
+                .ok_or_else(|| crate::Error::from(format!("protocol error; missing `{}` in HELLO reply", name)))
+        };
+
+        let proto = match field("proto")? {
+            Frame::Integer(proto) => u8::try_from(*proto)?,
+            frame => return Err(frame.to_error()),
+        };
+
+        let info = ServerInfo {
+            server: field("server")?.to_string(),
+            version: field("version")?.to_string(),
+            proto,
+            mode: field("mode")?.to_string(),
+            role: field("role")?.to_string(),
+        };
+
+        self.connection.set_protocol(info.proto);
+
+        Ok(info)
+    }
+
+    /// 当前连接使用的 RESP 协议版本
+    pub fn protocol(&self) -> u8 {
+        self.connection.protocol()
+    }
+
+    /// 设置读取回复时单个 bulk 的字节数及数组的元素个数的上限
+    /// 默认为 `DEFAULT_MAX_FRAME_SIZE`，读取更大的值前需调大
+    pub fn set_max_read_len(&mut self, max_read_len: usize) {
+        self.connection.set_max_read_len(max_read_len);
+    }
+
+    /// 让服务端将数据库保存到快照文件，服务端需配置 `save_path`
+    #[instrument(skip(self))]
+    pub async fn save(&mut self) -> crate::Result<()> {
+        let frame = Save::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 返回服务端最近一次成功保存快照的 Unix 时间（秒）
+    #[instrument(skip(self))]
+    pub async fn lastsave(&mut self) -> crate::Result<i64> {
+        let frame = Lastsave::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(response) => Ok(response),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 客户端订阅指定的频道
+    /// 一旦客户端执行了订阅的命令，它将消耗掉自身并返回一个 `Subscriber`
+    /// 新的 `Subscriber` 客户端会保持连接，接收订阅的频道的消息，它仅可执行订阅相关的命令
+    #[instrument(skip(self))]
+    pub async fn subscribe(mut self, channels: Vec<String>) -> crate::Result<Subscriber> {
+        let addr = self.connection.peer_addr()?;
+        self.subscribe_cmd(&channels).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: channels,
+            addr,
+        })
+    }
+
+    //// subscribe 命令的核心逻辑
+    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
+        let frame = Subscribe::new(channels).into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        // 在 `Subscribe` 命令的实现中，使用 `drain` 消费 channels 
+        // 每订阅一个频道，就会返回一条消息，故消息顺序是与 channels 一致的
+        // 格式为 ["subscrib", channel, sub_nums]
+        for channel in channels {
+            match self.read_response().await? {
+                Frame::Array(frame) => match frame.as_slice() {
+                    [subscribe, schannel, ..]
+                        if *subscribe == "subscribe" && *schannel == channel.as_str() => {},
+                    _ => return Err(Frame::Array(frame).to_error()),
+                },
+                frame => return Err(frame.to_error()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ping 服务端
+    /// 未指定消息时返回 `PONG`，否则返回 `Ping` 相同的消息
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = match client::connect("localhost:6379").await {
+    ///         Ok(client) => client,
+    ///         Err(_) => panic!("failed to establish connnect"),
+    ///     };
+    ///
+    ///     let pong = client.ping(None).await.unwrap();
+    ///     assert_eq!(b"PONG", &pong[..]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn ping(&mut self, msg: Option<String>) -> crate::Result<Bytes> {
+        let frame = Ping::new(msg).into_frame();
+
+        self.connection()?.write_frame(&frame).await?;
+
+        // Simple 或 Bulk 是正常返回值
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(value.into()),
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// 检查服务端是否可达
+    /// 在 `timeout` 内发送 `PING` 并收到 `PONG` 则返回 `true`，超时或任何错误均返回 `false`
+    ///
+    /// 超时后请求与回复可能已错位，该客户端此后的命令都会返回错误，需重新连接
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = match client::connect("localhost:6379").await {
+    ///         Ok(client) => client,
+    ///         Err(_) => panic!("failed to establish connnect"),
+    ///     };
+    ///
+    ///     assert!(client.is_alive(Duration::from_secs(1)).await);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn is_alive(&mut self, timeout: Duration) -> bool {
+        match time::timeout(timeout, self.ping(None)).await {
+            Ok(Ok(pong)) => &pong[..] == b"PONG",
+            Ok(Err(_)) => false,
+            Err(_) => {
+                self.poisoned = true;
+                false
+            },
+        }
+    }
+
+    /// 关闭连接：发送 `QUIT` 并等待服务端回复，然后关闭写端
+    /// 与直接 drop 相比，服务端读到的是正常的 EOF，发送或关闭时的错误也会返回给调用方
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.close().await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn close(mut self) -> crate::Result<()> {
+        let frame = Quit::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => {},
+            frame => return Err(frame.to_error()),
+        }
+
+        self.connection.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// 发送任意命令，返回完整的回复，嵌套的数组保持原样
+    /// 用于客户端没有单独封装的命令，可以用 `frame_to_string_vec`、`frame_to_pairs` 解码回复
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let docs = client.execute(&["COMMAND", "DOCS", "get"]).await.unwrap();
+    ///     println!("{:?}", client::frame_to_pairs(docs).unwrap());
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn execute(&mut self, args: &[&str]) -> crate::Result<Frame> {
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes()))).collect());
+        debug!(request = ?frame);
+
+        self.connection()?.write_frame(&frame).await?;
+
+        self.read_response().await
+    }
+
+    /// 先发送所有命令，再按顺序读取同样数量的回复
+    /// 所有命令写入缓冲后只 flush 一次，整批命令只需一次往返
+    /// 与 `read_response` 不同，`Frame::Error` 会原样返回，由调用方逐条处理
+    pub(crate) async fn send_batch(&mut self, frames: &[Frame]) -> crate::Result<Vec<Frame>> {
+        for frame in frames {
+            self.connection()?.write_frame_buffered(frame).await?;
+        }
+
+        self.connection.flush().await?;
+        debug!(commands = frames.len(), "flushed batch");
+
+        let mut responses = Vec::with_capacity(frames.len());
+        for _ in frames {
+            match self.connection.read_frame().await? {
+                Some(frame) => responses.push(frame),
+                None => {
+                    let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+                    return Err(err.into());
+                },
+            }
+        }
+        debug!(?responses);
+
+        Ok(responses)
+    }
+
+    /// 发送命令使用的连接，`is_alive` 超时后返回错误
+    fn connection(&mut self) -> crate::Result<&mut Connection> {
+        if self.poisoned {
+            return Err("connection is out of sync after `is_alive` timed out".into());
+        }
+
+        Ok(&mut self.connection)
+    }
+
+    /// 从当前连接中读取返回消息
+    async fn read_response(&mut self) -> crate::Result<Frame> {
+        let response = self.connection.read_frame().await?;
+        debug!(?response);
+
+        match response {
+            Some(Frame::Error(msg)) => Err(msg.into()),
+            // RESP3 连接的订阅回复为 push 类型，内容与数组相同
+            Some(Frame::Push(parts)) => Ok(Frame::Array(parts)),
+            Some(frame) => Ok(frame),
+            None => {
+                // 正常来讲，一定会有返回消息，若为 None 服务端断开了连接
+                let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+                Err(err.into())
+            }
+        }
+    }
+}
+
+impl LockGuard {
+    /// 锁的键
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 写入锁的随机 token
+    pub fn token(&self) -> &Bytes {
+        &self.token
+    }
+
+    /// 释放锁，返回是否删除了键
+    /// 锁已过期或已被其它客户端获得时返回 `false`，不会删除别人的锁
+    pub async fn release(self, client: &mut Client) -> crate::Result<bool> {
+        client.delifeq(&self.key, self.token).await
+    }
+}
+
+impl Subscriber {
+    /// 返回已订阅的频道列表
+    pub fn get_subscribed(&self) -> &[String] {
+        &self.subscribed_channels
+    }
+
+    pub fn into_stream(mut self) -> impl Stream<Item = crate::Result<Message>> {
+        try_stream! {
+            while let Some(message) = self.next_message().await? {
+                yield message;
+            }
+        }
+    }
+
+    /// 与 `into_stream` 相同，但最多只缓存 `max_in_flight` 条未被消费的消息
+    /// 消费者处理不过来时，后台任务停止读取连接，避免客户端的内存无限增长
+    ///
+    /// 需在 tokio 运行时中调用，`max_in_flight` 不能为 0
+    pub fn into_bounded_stream(mut self, max_in_flight: usize) -> BoundedStream {
+        let (tx, rx) = mpsc::channel(max_in_flight);
+
+        tokio::spawn(async move {
+            // 先申请到位置再读取下一条消息，保证缓存的消息不超过上限
+            // 消息流被 drop 后 `reserve` 返回错误，任务退出
+            while let Ok(permit) = tx.reserve().await {
+                match self.next_message().await {
+                    Ok(Some(message)) => permit.send(Ok(message)),
+                    Ok(None) => return,
+                    Err(err) => {
+                        permit.send(Err(err));
+                        return
+                    },
+                }
+            }
+        });
+
+        BoundedStream { rx }
+    }
+
+    /// 接收订阅的频道发送的消息
+    /// 正确的消息格式为 ["message", channel, msg]
+    #[instrument(skip(self))]
+    pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
+        match self.client.connection.read_frame().await? {
+            Some(frame) => {
+                debug!(?frame);
+
+                match frame {
+                    // RESP3 连接以 push 类型发送消息
+                    Frame::Array(frame) | Frame::Push(frame) => match frame.as_slice() {
+                        [message, channel, content] if *message == "message" => Ok(Some(Message {
+                            channel: channel.to_string(),
+                            content: Bytes::from(content.to_string()),
+                            seq: None,
+                        })),
+                        [message, channel, content, Frame::Integer(seq)] if *message == "message" => Ok(Some(Message {
+                            channel: channel.to_string(),
+                            content: Bytes::from(content.to_string()),
+                            seq: Some(*seq as u64),
+                        })),
+                        _ => Err(Frame::Array(frame).to_error()),
+                    },
+                    // 如服务关闭时发送的 `ERR server shutting down`
+                    Frame::Error(msg) => Err(msg.into()),
+                    frame => Err(frame.to_error()),
+                }
+            }
+            // 连接断开
+            None => Ok(None),
+        }
+    }
+
+    /// 添加新的订阅列表
+    #[instrument(skip(self))]
     pub async fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         // 执行订阅
         self.client.subscribe_cmd(channels).await?;
@@ -335,13 +1288,56 @@ impl Subscriber {
         Ok(())
     }
 
+    /// 重新连接服务端，并订阅当前已订阅的所有频道
+    /// 失败后等待并重试，重试的间隔逐次翻倍，超过最大间隔后返回错误
+    pub(crate) async fn reconnect(&mut self) -> crate::Result<()> {
+        let mut backoff = Duration::from_millis(10);
+
+        loop {
+            // 连接可能被一个正在关闭的服务端接受，因此订阅失败时同样重试
+            let res = match connect(self.addr).await {
+                Ok(mut client) => client
+                    .subscribe_cmd(&self.subscribed_channels)
+                    .await
+                    .map(|_| client),
+                Err(err) => Err(err),
+            };
+
+            match res {
+                Ok(client) => {
+                    self.client = client;
+                    return Ok(())
+                },
+                Err(err) => {
+                    if backoff > MAX_RECONNECT_BACKOFF {
+                        return Err(err)
+                    }
+                },
+            }
+
+            time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
     /// 取消对某些频道的订阅
     #[instrument(skip(self))]
     pub async fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         let frame = Unsubscribe::new(channels).into_frame();
         debug!(request = ? frame);
 
-        self.client.connection.write_frame(&frame).await?;
+        self.client.connection()?.write_frame(&frame).await?;
+
+        // 没有任何订阅时取消全部订阅，服务端只回复 ["unsubscribe", nil, 0]
+        if channels.is_empty() && self.subscribed_channels.is_empty() {
+            return match self.client.read_response().await? {
+                Frame::Array(frame) => match frame.as_slice() {
+                    [unsubscribe, Frame::Null, Frame::Integer(0)] if *unsubscribe == "unsubscribe" => Ok(()),
+                    _ => Err(Frame::Array(frame).to_error()),
+                },
+                frame => Err(frame.to_error()),
+            };
+        }
 
         // 若不指定取消订阅的频道则取消所有订阅
         let len = if channels.is_empty() {
@@ -378,3 +1374,36 @@ impl Subscriber {
         Ok(())
     }
 }
+
+impl BoundedStream {
+    /// 已接收但未被消费的消息数量
+    pub fn buffered(&self) -> usize {
+        self.rx.len()
+    }
+}
+
+impl Stream for BoundedStream {
+    type Item = crate::Result<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// 生成锁的 token，由每次随机取种子的 `RandomState`、当前时刻及进程内的计数器混合而成
+fn lock_token() -> Bytes {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos());
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut token = String::with_capacity(32);
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(count);
+        token.push_str(&format!("{:016x}", hasher.finish()));
+    }
+
+    Bytes::from(token)
+}
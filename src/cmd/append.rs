@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 在键存储的字符串末尾追加数据，返回追加后的长度
+/// `APPEND key value`，键不存在时等同于 `SET key value`
+#[derive(Debug)]
+pub struct Append {
+    key: String,
+    value: Bytes,
+}
+
+/// `APPEND key value` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("value")];
+
+impl Append {
+    /// 新建一条 `Append` 命令
+    pub fn new(key: impl ToString, value: Bytes) -> Append {
+        Append {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Append` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Append> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Append { key, value })
+    }
+
+    /// 追加数据，并将追加后的长度写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.append(&self.key, self.value) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"append"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+
+        frame
+    }
+}
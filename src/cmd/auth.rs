@@ -0,0 +1,43 @@
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Parse, ParseError, cmd::ArgSpec};
+
+/// 连接认证，`AUTH [username] password`
+/// 服务端目前不支持设置密码，所有连接都无需认证，因此总是返回错误
+#[derive(Debug)]
+pub struct Auth {
+    username: Option<String>,
+    password: String,
+}
+
+/// `AUTH [username] password` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::string("username").optional(), ArgSpec::string("password")];
+
+impl Auth {
+    /// 从 `Parse` 中解析出 `Auth` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let first = parse.next_string()?;
+
+        match parse.next_string() {
+            Ok(password) => Ok(Auth { username: Some(first), password }),
+            Err(ParseError::EndOfStream) => Ok(Auth { username: None, password: first }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 与未配置密码的 Redis 一样回复错误
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Error(
+            "ERR AUTH <password> called without any password configured for the default user. \
+             Are you sure your configuration is correct?"
+                .to_string(),
+        );
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Parse, ParseError, cmd::{table::COMMAND_TABLE, lookup, ArgSpec, ArgType}};
+
+/// 查询服务端支持的命令
+/// `COMMAND COUNT` 返回命令的数量
+/// `COMMAND DOCS [command ...]` 返回命令的参数描述，不指定命令时返回所有命令
+#[derive(Debug)]
+pub enum CommandMeta {
+    Count,
+    Docs { names: Vec<String> },
+}
+
+/// `COMMAND COUNT | COMMAND DOCS [command-name ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::one_of("subcommand", &[
+    ArgSpec::pure_token("count", "COUNT"),
+    ArgSpec::block("docs", &[ArgSpec::string("command-name").optional().multiple()]).token("DOCS"),
+])];
+
+impl CommandMeta {
+    /// 从 `Parse` 中解析出 `CommandMeta` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandMeta> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "count" => Ok(CommandMeta::Count),
+            "docs" => {
+                let mut names = vec![];
+                loop {
+                    match parse.next_string() {
+                        Ok(name) => names.push(name.to_lowercase()),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                Ok(CommandMeta::Docs { names })
+            },
+            _ => Err(format!("ERR unknown subcommand '{}' for 'command'", subcommand).into()),
+        }
+    }
+
+    /// 查询命令表，并写入客户端的连接
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            CommandMeta::Count => Frame::Integer(COMMAND_TABLE.len() as i64),
+            CommandMeta::Docs { names } => {
+                // 格式为 [命令名, 描述, 命令名, 描述, ..]，不存在的命令被忽略
+                let infos: Vec<_> = if names.is_empty() {
+                    COMMAND_TABLE.iter().collect()
+                } else {
+                    names.iter().filter_map(|name| lookup(name)).collect()
+                };
+
+                let mut response = vec![];
+                for info in infos {
+                    let mut doc = vec![];
+                    if !info.args.is_empty() {
+                        doc.push(Frame::Bulk(Bytes::from_static(b"arguments")));
+                        doc.push(args_doc(info.args));
+                    }
+
+                    response.push(Frame::Bulk(Bytes::from_static(info.name.as_bytes())));
+                    response.push(Frame::Array(doc));
+                }
+                Frame::Array(response)
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// 参数描述的 frame，每个参数为 [名称, 值, ..] 形式的数组
+fn args_doc(args: &[ArgSpec]) -> Frame {
+    let bulk = |value: &'static str| Frame::Bulk(Bytes::from_static(value.as_bytes()));
+
+    let docs = args.iter().map(|arg| {
+        let mut doc = vec![bulk("name"), bulk(arg.name), bulk("type"), bulk(arg.kind.name())];
+
+        if let Some(token) = arg.token {
+            doc.extend([bulk("token"), bulk(token)]);
+        }
+
+        if arg.optional || arg.multiple {
+            let flags = [(arg.optional, "optional"), (arg.multiple, "multiple")]
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, flag)| bulk(flag))
+                .collect();
+            doc.extend([bulk("flags"), Frame::Array(flags)]);
+        }
+
+        if let ArgType::OneOf(children) | ArgType::Block(children) = arg.kind {
+            doc.extend([bulk("arguments"), args_doc(children)]);
+        }
+
+        Frame::Array(doc)
+    });
+
+    Frame::Array(docs.collect())
+}
@@ -0,0 +1,100 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, glob, cmd::ArgSpec};
+
+/// 运行时查看或修改服务端参数
+/// `CONFIG GET pattern` 返回名称匹配 `pattern` 的参数及其值
+/// `CONFIG SET parameter value` 修改参数，对之后执行的命令生效
+///
+/// 目前支持的参数：
+/// - `proto-max-bulk-len`：回复中单个 bulk 的最大字节数
+/// - `rate-limit`：每个连接每秒最多执行的命令数量，0 表示不限制
+#[derive(Debug)]
+pub enum Config {
+    Get { pattern: String },
+    Set { parameter: String, value: String },
+}
+
+/// 可以通过 `CONFIG` 查看或修改的参数
+const PARAMETERS: &[&str] = &["proto-max-bulk-len", "rate-limit"];
+
+/// `CONFIG GET parameter | CONFIG SET parameter value` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::one_of("subcommand", &[
+    ArgSpec::pattern("parameter").token("GET"),
+    ArgSpec::block("set", &[ArgSpec::string("parameter"), ArgSpec::string("value")]).token("SET"),
+])];
+
+impl Config {
+    /// 从 `Parse` 中解析出 `Config` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Config> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => Ok(Config::Get { pattern: parse.next_string()?.to_lowercase() }),
+            "set" => {
+                let parameter = parse.next_string()?.to_lowercase();
+                let value = parse.next_string()?;
+                Ok(Config::Set { parameter, value })
+            },
+            _ => Err(format!("ERR unknown subcommand '{}' for 'config'", subcommand).into()),
+        }
+    }
+
+    /// 查看或修改参数，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Config::Get { pattern } => {
+                let mut response = Frame::array();
+                for parameter in PARAMETERS {
+                    if glob::matches(pattern.as_bytes(), parameter.as_bytes()) {
+                        response.push_bulk(Bytes::from_static(parameter.as_bytes()));
+                        response.push_bulk(Bytes::from(get_parameter(db, parameter)));
+                    }
+                }
+                response
+            },
+            Config::Set { parameter, value } => match set_parameter(db, &parameter, &value) {
+                Ok(()) => Frame::ok(),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// 读取参数的当前值
+fn get_parameter(db: &Db, parameter: &str) -> String {
+    match parameter {
+        "proto-max-bulk-len" => db.proto_max_bulk_len().to_string(),
+        "rate-limit" => db.rate_limit().unwrap_or(0).to_string(),
+        _ => unreachable!("unknown parameter {}", parameter),
+    }
+}
+
+/// 修改参数的值，参数未知或值非法时返回错误
+fn set_parameter(db: &Db, parameter: &str, value: &str) -> crate::Result<()> {
+    match parameter {
+        "proto-max-bulk-len" => {
+            let len = value.parse::<usize>().map_err(|_| {
+                format!("ERR Invalid argument '{}' for CONFIG SET '{}'", value, parameter)
+            })?;
+            db.set_proto_max_bulk_len(len);
+            Ok(())
+        },
+        "rate-limit" => {
+            let limit = value.parse::<u64>().map_err(|_| {
+                format!("ERR Invalid argument '{}' for CONFIG SET '{}'", value, parameter)
+            })?;
+            db.set_rate_limit(Some(limit));
+            Ok(())
+        },
+        _ => Err(format!("ERR Unknown option or number of arguments for CONFIG SET - '{}'", parameter).into()),
+    }
+}
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 将 `source` 的值及有效期复制到 `destination`，复制成功返回 1，否则返回 0
+/// `destination` 已存在时，需指定 `REPLACE` 才会覆盖
+///
+/// mini-redis 只有一个逻辑数据库，`DB` 选项只接受 0
+#[derive(Debug)]
+pub struct Copy {
+    source: String,
+    destination: String,
+    replace: bool,
+}
+
+/// `COPY source destination [DB destination-db] [REPLACE]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("source"),
+    ArgSpec::key("destination"),
+    ArgSpec::integer("destination-db").token("DB").optional(),
+    ArgSpec::pure_token("replace", "REPLACE").optional(),
+];
+
+impl Copy {
+    /// 新建一条 `Copy` 命令
+    pub fn new(source: impl ToString, destination: impl ToString, replace: bool) -> Copy {
+        Copy {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            replace,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Copy` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Copy> {
+        let source = parse.next_string()?;
+        let destination = parse.next_string()?;
+        let mut replace = false;
+
+        loop {
+            match parse.next_string() {
+                Ok(option) => match &option.to_uppercase()[..] {
+                    "REPLACE" => replace = true,
+                    "DB" => {
+                        let db = parse.next_signed_int().map_err(|_| "ERR value is not an integer or out of range")?;
+                        if db != 0 {
+                            return Err("ERR DB index is out of range".into());
+                        }
+                    },
+                    _ => return Err("ERR syntax error".into()),
+                },
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if source == destination {
+            return Err("ERR source and destination objects are the same".into());
+        }
+
+        Ok(Copy { source, destination, replace })
+    }
+
+    /// 在数据库中复制键，并返回是否复制
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let copied = db.copy(&self.source, self.destination, self.replace);
+        let response = Frame::Integer(copied as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"copy"));
+        frame.push_bulk(Bytes::from(self.source.into_bytes()));
+        frame.push_bulk(Bytes::from(self.destination.into_bytes()));
+        if self.replace {
+            frame.push_bulk(Bytes::from_static(b"replace"));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,140 @@
+use std::{thread, time::Duration};
+
+use tracing::{debug, instrument};
+
+use bytes::Bytes;
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 用于诊断的 `DEBUG` 命令，需在服务端配置中开启
+/// `DEBUG SLEEP seconds` 异步地等待，只阻塞当前连接
+/// `DEBUG SLEEP-BLOCKING seconds` 使用 `std::thread::sleep`，会阻塞执行该连接的工作线程，
+/// 可用于观察服务是否运行在多线程的运行时上
+/// `DEBUG OBJECT-STATS` 返回使用每种内部编码的键的数量，用于检查编码优化是否生效
+/// `DEBUG CHANNELS` 返回每个频道的订阅者数量及缓存中的消息数量，用于发现落后的订阅者
+/// `DEBUG EXPIRES` 返回有有效期的键的数量及距最近的过期时刻的毫秒数，用于检查过期清理
+/// `DEBUG INVARIANTS` 检查键的有效期与清理记录是否一致，供测试使用
+/// `DEBUG INTERSECTION key [key ...]` 返回求这些集合的交集时遍历的成员数量
+#[derive(Debug)]
+pub enum Debug {
+    Sleep(Duration),
+    SleepBlocking(Duration),
+    ObjectStats,
+    Channels,
+    Expires,
+    Invariants,
+    Intersection(Vec<String>),
+}
+
+/// `DEBUG SLEEP seconds | DEBUG SLEEP-BLOCKING seconds | DEBUG OBJECT-STATS | DEBUG CHANNELS | DEBUG EXPIRES | DEBUG INVARIANTS | DEBUG INTERSECTION key [key ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::one_of("subcommand", &[
+    ArgSpec::double("seconds").token("SLEEP"),
+    ArgSpec::double("seconds").token("SLEEP-BLOCKING"),
+    ArgSpec::pure_token("object-stats", "OBJECT-STATS"),
+    ArgSpec::pure_token("channels", "CHANNELS"),
+    ArgSpec::pure_token("expires", "EXPIRES"),
+    ArgSpec::pure_token("invariants", "INVARIANTS"),
+    ArgSpec::key("key").multiple().token("INTERSECTION"),
+])];
+
+impl Debug {
+    /// 从 `Parse` 中解析出 `Debug` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Debug> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "sleep" => Ok(Debug::Sleep(next_seconds(parse)?)),
+            "sleep-blocking" => Ok(Debug::SleepBlocking(next_seconds(parse)?)),
+            "object-stats" => Ok(Debug::ObjectStats),
+            "channels" => Ok(Debug::Channels),
+            "expires" => Ok(Debug::Expires),
+            "invariants" => Ok(Debug::Invariants),
+            "intersection" => {
+                let mut keys = vec![parse.next_string()?];
+                while parse.remaining() > 0 {
+                    keys.push(parse.next_string()?);
+                }
+                Ok(Debug::Intersection(keys))
+            },
+            _ => Err(format!("ERR unknown subcommand '{}' for 'debug'", subcommand).into()),
+        }
+    }
+
+    /// 执行诊断命令，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Debug::Sleep(duration) => {
+                tokio::time::sleep(duration).await;
+                Frame::ok()
+            },
+            // 故意阻塞工作线程
+            Debug::SleepBlocking(duration) => {
+                thread::sleep(duration);
+                Frame::ok()
+            },
+            Debug::ObjectStats => {
+                let mut histogram: Vec<_> = db.encoding_histogram().into_iter().collect();
+                histogram.sort_unstable();
+
+                // 格式为 [编码, 数量, 编码, 数量, ..]，按编码名称排序
+                let mut response = Frame::array();
+                for (encoding, count) in histogram {
+                    response.push_bulk(Bytes::from_static(encoding.as_bytes()));
+                    response.push_int(count as i64);
+                }
+                response
+            },
+            Debug::Channels => {
+                // 每个频道一项 [频道, 订阅者数量, 缓存的消息数量]，按频道名称排序
+                let channels = db
+                    .channel_stats()
+                    .into_iter()
+                    .map(|(channel, subscribers, buffered)| Frame::Array(vec![
+                        Frame::Bulk(Bytes::from(channel.into_bytes())),
+                        Frame::Integer(subscribers as i64),
+                        Frame::Integer(buffered as i64),
+                    ]))
+                    .collect();
+                Frame::Array(channels)
+            },
+            Debug::Expires => {
+                // 格式为 [expires, 数量, nearest-ms, 毫秒数]，没有有效期的键时毫秒数为 nil
+                let (count, nearest) = db.expiration_stats();
+                let nearest = nearest.map_or(Frame::Null, |nearest| Frame::Integer(nearest.as_millis() as i64));
+
+                Frame::Array(vec![
+                    Frame::Bulk(Bytes::from_static(b"expires")),
+                    Frame::Integer(count as i64),
+                    Frame::Bulk(Bytes::from_static(b"nearest-ms")),
+                    nearest,
+                ])
+            },
+            Debug::Invariants => match db.check_invariants() {
+                Ok(()) => Frame::ok(),
+                Err(detail) => Frame::Error(format!("ERR invariant violated: {}", detail)),
+            },
+            Debug::Intersection(keys) => match db.intersection_iterations(&keys) {
+                Ok(iterated) => Frame::Integer(iterated as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+}
+
+/// 读取以秒为单位的时长，支持小数
+fn next_seconds(parse: &mut Parse) -> crate::Result<Duration> {
+    parse
+        .next_string()?
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| "ERR value is not a valid float".into())
+}
@@ -0,0 +1,66 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, KvStore, Parse, ParseError, cmd::ArgSpec};
+
+/// 删除一个或多个键，无论其存储的是何种类型，返回实际删除的键数量
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+/// `DEL key [key ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key").multiple()];
+
+impl Del {
+    /// 新建一条 `Del` 命令
+    pub fn new(keys: &[String]) -> Del {
+        Del { keys: keys.to_vec() }
+    }
+
+    /// 从 `Parse` 中解析出 `Del` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Del> {
+        use ParseError::EndOfStream;
+
+        // 至少得删除一个键
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Del { keys })
+    }
+
+    /// 从存储中删除键，返回回复的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        Frame::Integer(store.del(&self.keys) as i64)
+    }
+
+    /// 从数据库中删除键，并返回删除的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"del"));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        frame
+    }
+}
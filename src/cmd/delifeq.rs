@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 键的值等于 `value` 时删除它，`DELIFEQ key value`
+/// 删除时返回 1，键不存在或值不相等时返回 0，用于只由锁的持有者释放锁
+#[derive(Debug)]
+pub struct Delifeq {
+    key: String,
+    value: Bytes,
+}
+
+/// `DELIFEQ key value` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("value")];
+
+impl Delifeq {
+    /// 新建一条 `Delifeq` 命令
+    pub fn new(key: impl ToString, value: Bytes) -> Delifeq {
+        Delifeq {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Delifeq` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Delifeq> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Delifeq { key, value })
+    }
+
+    /// 比较并删除，将是否删除写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.del_if_eq(&self.key, &self.value) {
+            Ok(deleted) => Frame::Integer(deleted as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"delifeq"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+
+        frame
+    }
+}
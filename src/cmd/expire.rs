@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 为已存在的键设置有效期，替换原有的有效期
+/// `EXPIRE key seconds`/`PEXPIRE key milliseconds`，设置成功返回 1，键不存在返回 0
+/// 有效期不为正数时与 Redis 相同，直接删除键
+#[derive(Debug)]
+pub struct Expire {
+    key: String,
+    /// 以毫秒为单位的有效期
+    millis: i64,
+    /// 命令为 `PEXPIRE`
+    pexpire: bool,
+}
+
+/// `EXPIRE key seconds` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::integer("seconds")];
+
+/// `PEXPIRE key milliseconds` 的参数
+pub(crate) const PEXPIRE_ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::integer("milliseconds")];
+
+impl Expire {
+    /// 新建一条 `EXPIRE` 命令
+    pub fn new(key: impl ToString, seconds: i64) -> Expire {
+        Expire {
+            key: key.to_string(),
+            millis: seconds.saturating_mul(1000),
+            pexpire: false,
+        }
+    }
+
+    /// 新建一条 `PEXPIRE` 命令
+    pub fn pexpire(key: impl ToString, millis: i64) -> Expire {
+        Expire {
+            key: key.to_string(),
+            millis,
+            pexpire: true,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Expire` 命令，命令头已被读取
+    /// `pexpire` 由命令名决定，为 `true` 时有效期以毫秒为单位
+    pub(crate) fn parse_frames(pexpire: bool, parse: &mut Parse) -> crate::Result<Expire> {
+        let key = parse.next_string()?;
+        let time = parse.next_signed_int()?;
+
+        let name = if pexpire { "pexpire" } else { "expire" };
+        let invalid = || format!("ERR invalid expire time in '{}' command", name);
+
+        let millis = if pexpire {
+            time
+        } else {
+            time.checked_mul(1000).ok_or_else(invalid)?
+        };
+
+        // 过期时刻无法表示时报错，而不是在设置时溢出
+        if millis > 0 && Instant::now().checked_add(Duration::from_millis(millis as u64)).is_none() {
+            return Err(invalid().into());
+        }
+
+        Ok(Expire { key, millis, pexpire })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.pexpire {
+            "pexpire"
+        } else {
+            "expire"
+        }
+    }
+
+    /// 修改键的有效期，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let done = if self.millis > 0 {
+            db.set_expire(&self.key, Duration::from_millis(self.millis as u64))
+        } else {
+            db.del(&[self.key]) > 0
+        };
+
+        let response = Frame::Integer(done as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    /// `EXPIRE` 的有效期按秒发送，不足一秒的部分被舍去
+    pub(crate) fn into_frame(self) -> Frame {
+        let time = if self.pexpire { self.millis } else { self.millis / 1000 };
+
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(time.to_string()));
+
+        frame
+    }
+}
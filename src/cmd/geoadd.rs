@@ -0,0 +1,96 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, geo, cmd::ArgSpec};
+
+/// 向有序集合中添加地理位置，返回新添加的成员数量
+/// 经纬度编码为 geohash 后作为成员的分值保存
+#[derive(Debug)]
+pub struct Geoadd {
+    key: String,
+    members: Vec<(f64, f64, Bytes)>,
+}
+
+/// `GEOADD key longitude latitude member [longitude latitude member ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("key"),
+    ArgSpec::block("data", &[ArgSpec::double("longitude"), ArgSpec::double("latitude"), ArgSpec::string("member")]).multiple(),
+];
+
+impl Geoadd {
+    /// 新建一条 `Geoadd` 命令，`members` 为 (经度, 纬度, 成员)
+    pub fn new(key: impl ToString, members: Vec<(f64, f64, Bytes)>) -> Geoadd {
+        Geoadd {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Geoadd` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Geoadd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+        let mut members = vec![];
+
+        // 至少得有一组 经度/纬度/成员
+        loop {
+            let longitude = match parse.next_string() {
+                Ok(longitude) => longitude,
+                Err(EndOfStream) if !members.is_empty() => break,
+                Err(err) => return Err(err.into()),
+            };
+            let latitude = parse.next_string()?;
+
+            let (longitude, latitude) = match (longitude.parse::<f64>(), latitude.parse::<f64>()) {
+                (Ok(longitude), Ok(latitude)) => (longitude, latitude),
+                _ => return Err("ERR value is not a valid float".into()),
+            };
+
+            if !geo::is_valid(longitude, latitude) {
+                return Err(format!("ERR invalid longitude,latitude pair {:.6},{:.6}", longitude, latitude).into());
+            }
+
+            let member = parse.next_bytes()?;
+
+            members.push((longitude, latitude, member));
+        }
+
+        Ok(Geoadd { key, members })
+    }
+
+    /// 将位置编码为分值并添加到有序集合中，返回新添加的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let members = self
+            .members
+            .into_iter()
+            .map(|(longitude, latitude, member)| (geo::encode(longitude, latitude) as f64, member))
+            .collect();
+
+        let response = match db.zadd(self.key, members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"geoadd"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for (longitude, latitude, member) in self.members {
+            frame.push_bulk(Bytes::from(longitude.to_string()));
+            frame.push_bulk(Bytes::from(latitude.to_string()));
+            frame.push_bulk(member);
+        }
+
+        frame
+    }
+}
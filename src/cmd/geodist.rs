@@ -0,0 +1,130 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, geo, cmd::ArgSpec};
+
+/// 返回有序集合中两个地理位置之间的距离，保留 4 位小数
+/// 任意一个成员不存在时返回 `Null`
+#[derive(Debug)]
+pub struct Geodist {
+    key: String,
+    member1: Bytes,
+    member2: Bytes,
+    unit: GeoUnit,
+}
+
+/// 距离的单位，默认为米
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoUnit {
+    #[default]
+    M,
+    Km,
+    Mi,
+    Ft,
+}
+
+/// `GEODIST key member1 member2 [M | KM | FT | MI]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("key"),
+    ArgSpec::string("member1"),
+    ArgSpec::string("member2"),
+    ArgSpec::one_of("unit", &[
+        ArgSpec::pure_token("m", "M"),
+        ArgSpec::pure_token("km", "KM"),
+        ArgSpec::pure_token("ft", "FT"),
+        ArgSpec::pure_token("mi", "MI"),
+    ]).optional(),
+];
+
+impl GeoUnit {
+    /// 一个单位对应的米数
+    fn meters(self) -> f64 {
+        match self {
+            GeoUnit::M => 1.0,
+            GeoUnit::Km => 1000.0,
+            GeoUnit::Mi => 1609.34,
+            GeoUnit::Ft => 0.3048,
+        }
+    }
+
+    /// 单位的名称，即命令中的参数
+    fn as_str(self) -> &'static str {
+        match self {
+            GeoUnit::M => "m",
+            GeoUnit::Km => "km",
+            GeoUnit::Mi => "mi",
+            GeoUnit::Ft => "ft",
+        }
+    }
+}
+
+impl Geodist {
+    /// 新建一条 `Geodist` 命令
+    pub fn new(key: impl ToString, member1: Bytes, member2: Bytes, unit: GeoUnit) -> Geodist {
+        Geodist {
+            key: key.to_string(),
+            member1,
+            member2,
+            unit,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Geodist` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Geodist> {
+        let key = parse.next_string()?;
+        let member1 = parse.next_bytes()?;
+        let member2 = parse.next_bytes()?;
+
+        let unit = match parse.next_string() {
+            Ok(unit) => match &unit.to_lowercase()[..] {
+                "m" => GeoUnit::M,
+                "km" => GeoUnit::Km,
+                "mi" => GeoUnit::Mi,
+                "ft" => GeoUnit::Ft,
+                _ => return Err("ERR unsupported unit provided. please use M, KM, FT, MI".into()),
+            },
+            Err(ParseError::EndOfStream) => GeoUnit::M,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Geodist { key, member1, member2, unit })
+    }
+
+    /// 解码两个成员的位置并计算距离
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let scores = db
+            .zscore(&self.key, &self.member1)
+            .and_then(|score1| Ok((score1, db.zscore(&self.key, &self.member2)?)));
+
+        let response = match scores {
+            Ok((Some(score1), Some(score2))) => {
+                let (lon1, lat1) = geo::decode(score1 as u64);
+                let (lon2, lat2) = geo::decode(score2 as u64);
+                let distance = geo::distance(lon1, lat1, lon2, lat2) / self.unit.meters();
+
+                Frame::Bulk(Bytes::from(format!("{:.4}", distance)))
+            },
+            Ok(_) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"geodist"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.member1);
+        frame.push_bulk(self.member2);
+        frame.push_bulk(Bytes::from_static(self.unit.as_str().as_bytes()));
+
+        frame
+    }
+}
@@ -1,13 +1,16 @@
 use bytes::Bytes;
 use tracing::{debug, instrument};
 
-use crate::{Frame, Connection, Parse, Db};
+use crate::{Frame, Connection, Parse, KvStore, cmd::ArgSpec};
 
 #[derive(Debug)]
 pub struct Get {
     key: String,
 }
 
+/// `GET key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
 impl Get {
     /// 从一个实现了 `ToString` 特征的值得到 `Get`
     pub fn new(key: impl ToString) -> Get {
@@ -28,14 +31,19 @@ impl Get {
         Ok(Get { key })
     }
 
+    /// 从存储中查找结果，返回回复的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        match store.get(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
     /// 从数据库中查找结果，并写入客户端的连接
     #[instrument(skip(self, db, dst))]
-    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let response = if let Some(value) = db.get(&self.key) {
-            Frame::Bulk(value)
-        } else {
-            Frame::Null
-        };
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
 
         debug!(?response);
 
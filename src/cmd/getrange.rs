@@ -0,0 +1,90 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回键对应的值在 `start` 与 `end` 之间（包含两端）的部分
+/// `GETRANGE key start end`，负数表示从末尾倒数，如 -1 为最后一个字节
+/// 已废弃的 `SUBSTR key start end` 是它的别名，同样解析为此命令
+#[derive(Debug)]
+pub struct Getrange {
+    key: String,
+    start: i64,
+    end: i64,
+}
+
+/// `GETRANGE key start end` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::integer("start"), ArgSpec::integer("end")];
+
+impl Getrange {
+    /// 新建一条 `Getrange` 命令
+    pub fn new(key: impl ToString, start: i64, end: i64) -> Getrange {
+        Getrange {
+            key: key.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Getrange` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Getrange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let end = parse.next_signed_int()?;
+
+        Ok(Getrange { key, start, end })
+    }
+
+    /// 从数据库中查找值并截取，写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // 两端都不是负数时，只需取出前 `end + 1` 个字节
+        let value = if self.start >= 0 && self.end >= 0 {
+            db.get_prefix(&self.key, (self.end as usize).saturating_add(1))
+        } else {
+            db.get(&self.key)
+        };
+
+        let response = match value {
+            Ok(Some(value)) => Frame::Bulk(range(&value, self.start, self.end)),
+            Ok(None) => Frame::Bulk(Bytes::new()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"getrange"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.start.to_string()));
+        frame.push_bulk(Bytes::from(self.end.to_string()));
+
+        frame
+    }
+}
+
+/// 按 Redis 的规则截取 `value`，返回的切片与 `value` 共享内存
+fn range(value: &Bytes, start: i64, end: i64) -> Bytes {
+    let len = value.len() as i64;
+
+    // 两端都为负数且 start 在 end 之后时，结果必为空
+    if len == 0 || (start < 0 && end < 0 && start > end) {
+        return Bytes::new();
+    }
+
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let end = if end < 0 { (len + end).max(0) } else { end.min(len - 1) };
+
+    if start > end {
+        return Bytes::new();
+    }
+
+    value.slice(start as usize..=end as usize)
+}
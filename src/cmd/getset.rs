@@ -0,0 +1,65 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, KvStore, Parse, cmd::{ArgSpec, SetOptions}};
+
+/// 设置键的值并返回原先的值，键不存在时返回 `nil`
+/// `GETSET key value`，Redis 中已被 `SET key value GET` 取代，这里按后者执行
+/// 与 `SET ... GET` 相同，写入后键不再有有效期
+#[derive(Debug)]
+pub struct Getset {
+    key: String,
+    value: Bytes,
+}
+
+/// `GETSET key value` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("value")];
+
+impl Getset {
+    /// 新建一条 `Getset` 命令
+    pub fn new(key: impl ToString, value: Bytes) -> Getset {
+        Getset {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Getset` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Getset> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Getset { key, value })
+    }
+
+    /// 写入新值，返回包含原先的值的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        // 不指定有效期，原有的有效期被清除
+        match store.set_with_options(self.key, self.value, &SetOptions::new().get()) {
+            Ok(outcome) => outcome.previous.map_or(Frame::Null, Frame::Bulk),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// 写入新值，并将原先的值写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"getset"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+
+        frame
+    }
+}
@@ -0,0 +1,75 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Parse, ParseError, cmd::ArgSpec};
+
+/// 与服务端协商 RESP 协议版本，并返回服务端的基本信息
+/// 不指定版本时保持当前协议不变
+///
+/// 由于尚未支持 RESP3 的 map 类型，无论哪个版本都以 [名称, 值, ..] 数组的形式返回
+#[derive(Debug, Default)]
+pub struct Hello {
+    protover: Option<u64>,
+}
+
+/// `HELLO [protover]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::integer("protover").optional()];
+
+impl Hello {
+    /// 新建一条 `Hello` 命令
+    pub fn new(protover: Option<u64>) -> Hello {
+        Hello { protover }
+    }
+
+    /// 从 `Parse` 中解析出 `Hello` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        match parse.next_int() {
+            Ok(protover) => Ok(Hello { protover: Some(protover) }),
+            Err(ParseError::EndOfStream) => Ok(Hello::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 切换连接的协议版本，并返回服务端信息
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        match self.protover {
+            Some(protover @ (2 | 3)) => dst.set_protocol(protover as u8),
+            Some(_) => {
+                let response = Frame::Error("NOPROTO unsupported protocol version".to_string());
+                dst.write_frame(&response).await?;
+                return Ok(())
+            },
+            None => {},
+        }
+
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from_static(b"server"));
+        response.push_bulk(Bytes::from_static(b"mini-redis"));
+        response.push_bulk(Bytes::from_static(b"version"));
+        response.push_bulk(Bytes::from_static(env!("CARGO_PKG_VERSION").as_bytes()));
+        response.push_bulk(Bytes::from_static(b"proto"));
+        response.push_int(dst.protocol() as i64);
+        response.push_bulk(Bytes::from_static(b"mode"));
+        response.push_bulk(Bytes::from_static(b"standalone"));
+        response.push_bulk(Bytes::from_static(b"role"));
+        response.push_bulk(Bytes::from_static(b"master"));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"hello"));
+        if let Some(protover) = self.protover {
+            frame.push_bulk(Bytes::from(protover.to_string()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回哈希中字段的值，键或字段不存在时返回 `nil`
+#[derive(Debug)]
+pub struct Hget {
+    key: String,
+    field: Bytes,
+}
+
+/// `HGET key field` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("field")];
+
+impl Hget {
+    /// 新建一条 `Hget` 命令
+    pub fn new(key: impl ToString, field: Bytes) -> Hget {
+        Hget {
+            key: key.to_string(),
+            field,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Hget` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hget> {
+        let key = parse.next_string()?;
+        let field = parse.next_bytes()?;
+
+        Ok(Hget { key, field })
+    }
+
+    /// 查询字段的值，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"hget"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.field);
+
+        frame
+    }
+}
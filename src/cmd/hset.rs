@@ -0,0 +1,79 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 设置哈希中一个或多个字段的值，返回新添加的字段数量
+/// 键不存在时创建一个新的哈希
+#[derive(Debug)]
+pub struct Hset {
+    key: String,
+    pairs: Vec<(Bytes, Bytes)>,
+}
+
+/// `HSET key field value [field value ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("key"),
+    ArgSpec::block("data", &[ArgSpec::string("field"), ArgSpec::string("value")]).multiple(),
+];
+
+impl Hset {
+    /// 新建一条 `Hset` 命令
+    pub fn new(key: impl ToString, pairs: Vec<(Bytes, Bytes)>) -> Hset {
+        Hset {
+            key: key.to_string(),
+            pairs,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Hset` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hset> {
+        let key = parse.next_string()?;
+
+        // 字段与值必须成对出现
+        if !parse.remaining().is_multiple_of(2) {
+            return Err("ERR wrong number of arguments for 'hset' command".into());
+        }
+
+        // 至少得设置一个字段
+        let mut pairs = vec![(parse.next_bytes()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(field) => pairs.push((field, parse.next_bytes()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Hset { key, pairs })
+    }
+
+    /// 写入哈希的字段，并返回新添加的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hset(self.key, self.pairs) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"hset"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for (field, value) in self.pairs {
+            frame.push_bulk(field);
+            frame.push_bulk(value);
+        }
+
+        frame
+    }
+}
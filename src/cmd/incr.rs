@@ -0,0 +1,80 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, KvStore, Parse, cmd::ArgSpec};
+
+/// 将键存储的整数加一或减一，返回新的值
+/// `INCR key`/`DECR key`，键不存在时视其值为 0
+#[derive(Debug)]
+pub struct Incr {
+    key: String,
+    /// `INCR` 为 1，`DECR` 为 -1
+    delta: i64,
+}
+
+/// `INCR key`/`DECR key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
+impl Incr {
+    /// 新建一条 `INCR` 命令
+    pub fn new(key: impl ToString) -> Incr {
+        Incr {
+            key: key.to_string(),
+            delta: 1,
+        }
+    }
+
+    /// 新建一条 `DECR` 命令
+    pub fn decr(key: impl ToString) -> Incr {
+        Incr {
+            key: key.to_string(),
+            delta: -1,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Incr` 命令，命令头已被读取
+    /// `delta` 由命令名决定，`INCR` 为 1，`DECR` 为 -1
+    pub(crate) fn parse_frames(delta: i64, parse: &mut Parse) -> crate::Result<Incr> {
+        let key = parse.next_string()?;
+
+        Ok(Incr { key, delta })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.delta < 0 {
+            "decr"
+        } else {
+            "incr"
+        }
+    }
+
+    /// 修改存储中键的值，返回回复的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        match store.incr_by(&self.key, self.delta) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+
+    /// 修改键的值，并将新的值写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame
+    }
+}
@@ -0,0 +1,54 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回所有匹配 `pattern` 的键，支持 `*`、`?`、`[...]` 及 `\` 转义
+/// 需要遍历所有的键，不建议在键很多时使用
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+/// `KEYS pattern` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::pattern("pattern")];
+
+impl Keys {
+    /// 新建一条 `Keys` 命令
+    pub fn new(pattern: impl ToString) -> Keys {
+        Keys {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Keys` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Keys> {
+        let pattern = parse.next_string()?;
+
+        Ok(Keys { pattern })
+    }
+
+    /// 查找匹配的键，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        for key in db.keys(&self.pattern) {
+            response.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"keys"));
+        frame.push_bulk(Bytes::from(self.pattern.into_bytes()));
+
+        frame
+    }
+}
@@ -0,0 +1,36 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db};
+
+/// 返回最近一次成功保存快照的 Unix 时间（秒）
+/// 客户端可以在 `SAVE` 前后比较该值，确认数据已经落盘
+#[derive(Debug, Default)]
+pub struct Lastsave;
+
+impl Lastsave {
+    /// 新建一条 `Lastsave` 命令
+    pub fn new() -> Lastsave {
+        Lastsave
+    }
+
+    /// 将最近一次保存的时间写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.last_save());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"lastsave"));
+
+        frame
+    }
+}
@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 查看内存占用情况
+/// `MEMORY USAGE key` 返回单个键估算占用的字节数
+/// `MEMORY STATS` 返回整个数据库的汇总数据
+#[derive(Debug)]
+pub enum Memory {
+    Usage { key: String },
+    Stats,
+}
+
+/// `MEMORY USAGE key | MEMORY STATS` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::one_of("subcommand", &[
+    ArgSpec::key("key").token("USAGE"),
+    ArgSpec::pure_token("stats", "STATS"),
+])];
+
+impl Memory {
+    /// 从 `Parse` 中解析出 `Memory` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Memory> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "usage" => {
+                let key = parse.next_string()?;
+                Ok(Memory::Usage { key })
+            },
+            "stats" => Ok(Memory::Stats),
+            _ => Err(format!("ERR unknown subcommand '{}' for 'memory'", subcommand).into()),
+        }
+    }
+
+    /// 查询数据库的内存占用，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Memory::Usage { key } => match db.memory_usage(&key) {
+                Some(bytes) => Frame::Integer(bytes as i64),
+                None => Frame::Null,
+            },
+            Memory::Stats => {
+                let stats = db.memory_stats();
+
+                // 格式为 [名称, 数值, 名称, 数值, ..]
+                let mut response = Frame::array();
+                response.push_bulk(Bytes::from_static(b"keys.count"));
+                response.push_int(stats.keys as i64);
+                response.push_bulk(Bytes::from_static(b"dataset.bytes"));
+                response.push_int(stats.bytes as i64);
+                response
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -2,23 +2,167 @@
 /// Redis 对应的命令
 /// 操作数据库键值的 Get/Set
 /// 消息频道订阅的 Publish/Subscribe/Unsubscribe
-use crate::{Frame, Parse, ParseError, Connection, Db, Shutdown};
+use crate::{Frame, Parse, ParseError, Connection, Db, Shutdown, db::SetOperation, server::Config as ServerConfig};
+
+mod table;
+pub use table::{lookup, ArgSpec, ArgType, CommandInfo};
 
 mod get;
 pub use get::Get;
 
 mod set;
-pub use set::Set;
+pub use set::{Expiration, Set, SetCondition, SetOptions, SetOutcome};
+
+mod getset;
+pub use getset::Getset;
+
+mod getrange;
+pub use getrange::Getrange;
+
+mod append;
+pub use append::Append;
+
+mod mset;
+pub use mset::Mset;
+
+mod incr;
+pub use incr::Incr;
+
+mod keys;
+pub use keys::Keys;
+
+mod del;
+pub use del::Del;
 
 mod publish;
 pub use publish::Publish;
 
 mod subscribe;
-pub use subscribe::{Subscribe, Unsubscribe};
+pub use subscribe::{Psubscribe, Punsubscribe, Subscribe, Unsubscribe};
 
 mod ping;
 pub use ping::Ping;
 
+mod quit;
+pub use quit::Quit;
+
+mod reset;
+pub use reset::Reset;
+
+mod object;
+pub use object::Object;
+
+mod command;
+pub use command::CommandMeta;
+
+mod memory;
+pub use memory::Memory;
+
+mod hello;
+pub use hello::Hello;
+
+mod auth;
+pub use auth::Auth;
+
+mod debug;
+pub use debug::Debug;
+
+mod slowlog;
+pub use slowlog::Slowlog;
+
+mod config;
+pub use config::Config;
+
+mod save;
+pub use save::Save;
+
+mod sadd;
+pub use sadd::Sadd;
+
+mod smembers;
+pub use smembers::Smembers;
+
+mod set_algebra;
+pub use set_algebra::SetAlgebra;
+
+mod zadd;
+pub use zadd::Zadd;
+
+mod zrangebyscore;
+pub use zrangebyscore::Zrangebyscore;
+
+mod zrem;
+pub use zrem::Zrem;
+
+mod zrank;
+pub use zrank::Zrank;
+
+mod zincrby;
+pub use zincrby::Zincrby;
+
+mod pfadd;
+pub use pfadd::Pfadd;
+
+mod pfcount;
+pub use pfcount::Pfcount;
+
+mod geoadd;
+pub use geoadd::Geoadd;
+
+mod geodist;
+pub use geodist::{GeoUnit, Geodist};
+
+mod lastsave;
+pub use lastsave::Lastsave;
+
+mod waitaof;
+pub use waitaof::Waitaof;
+
+mod copy;
+pub use copy::Copy;
+
+mod ttl;
+pub use ttl::Ttl;
+
+mod script;
+pub use script::Script;
+
+mod expire;
+pub use expire::Expire;
+
+mod persist;
+pub use persist::Persist;
+
+mod scan;
+pub use scan::Scan;
+
+mod setnx;
+pub use setnx::Setnx;
+
+mod push;
+pub use push::Push;
+
+mod pop;
+pub use pop::Pop;
+
+mod rename;
+pub use rename::Rename;
+
+mod r#type;
+pub use r#type::Type;
+
+mod sintercard;
+pub use sintercard::Sintercard;
+
+mod hset;
+pub use hset::Hset;
+
+mod hget;
+pub use hget::Hget;
+
+mod delifeq;
+pub use delifeq::Delifeq;
+
 mod unknown;
 pub use unknown::Unknown;
 
@@ -26,10 +170,59 @@ pub use unknown::Unknown;
 pub enum Command {
     Get(Get),
     Set(Set),
+    Getset(Getset),
+    Getrange(Getrange),
+    Append(Append),
+    Mset(Mset),
+    Incr(Incr),
+    Keys(Keys),
+    Del(Del),
     Publish(Publish),
     Subscribe(Subscribe),
     Unsubscribe(Unsubscribe),
+    Psubscribe(Psubscribe),
+    Punsubscribe(Punsubscribe),
     Ping(Ping),
+    Quit(Quit),
+    Reset(Reset),
+    Object(Object),
+    CommandMeta(CommandMeta),
+    Memory(Memory),
+    Hello(Hello),
+    Auth(Auth),
+    Debug(Debug),
+    Slowlog(Slowlog),
+    Config(Config),
+    Save(Save),
+    Sadd(Sadd),
+    Smembers(Smembers),
+    SetAlgebra(SetAlgebra),
+    Zadd(Zadd),
+    Zrangebyscore(Zrangebyscore),
+    Zrem(Zrem),
+    Zrank(Zrank),
+    Zincrby(Zincrby),
+    Pfadd(Pfadd),
+    Pfcount(Pfcount),
+    Geoadd(Geoadd),
+    Geodist(Geodist),
+    Lastsave(Lastsave),
+    Waitaof(Waitaof),
+    Copy(Copy),
+    Ttl(Ttl),
+    Script(Script),
+    Expire(Expire),
+    Persist(Persist),
+    Scan(Scan),
+    Setnx(Setnx),
+    Push(Push),
+    Pop(Pop),
+    Rename(Rename),
+    Type(Type),
+    Sintercard(Sintercard),
+    Hset(Hset),
+    Hget(Hget),
+    Delifeq(Delifeq),
     Unknown(Unknown),
 }
 
@@ -38,16 +231,93 @@ impl Command {
     pub fn from_frame(frame: Frame) -> crate::Result<Command> {
         let mut parse = Parse::new(frame)?;
 
-        let command = parse.next_string()?.to_lowercase();
+        // 直接用 frame 中的字节在命令表中查找，已知命令无需分配 `String`
+        let info = match table::lookup_bytes(parse.peek_bytes()?) {
+            Some(info) => info,
+            None => {
+                let mut command = parse.next_string()?;
+                command.make_ascii_lowercase();
+                return Ok(Command::Unknown(Unknown::new(command)));
+            }
+        };
+        parse.skip()?;
+
+        // 先按命令表检查参数个数，与 Redis 返回相同的错误
+        if !info.check_arity(parse.remaining() + 1) {
+            return Err(format!("ERR wrong number of arguments for '{}' command", info.name).into());
+        }
 
-        let command = match &command[..] {
+        let command = match info.name {
             "get" => Command::Get(Get::parse_frames(&mut parse)?),
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
+            "getset" => Command::Getset(Getset::parse_frames(&mut parse)?),
+            "getrange" => Command::Getrange(Getrange::parse_frames(&mut parse)?),
+            // `SUBSTR` 是 `GETRANGE` 已废弃的别名
+            "substr" => Command::Getrange(Getrange::parse_frames(&mut parse)?),
+            "append" => Command::Append(Append::parse_frames(&mut parse)?),
+            "mset" => Command::Mset(Mset::parse_frames(&mut parse)?),
+            "incr" => Command::Incr(Incr::parse_frames(1, &mut parse)?),
+            "decr" => Command::Incr(Incr::parse_frames(-1, &mut parse)?),
+            "keys" => Command::Keys(Keys::parse_frames(&mut parse)?),
+            "del" => Command::Del(Del::parse_frames(&mut parse)?),
             "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
             "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
             "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
+            "psubscribe" => Command::Psubscribe(Psubscribe::parse_frames(&mut parse)?),
+            "punsubscribe" => Command::Punsubscribe(Punsubscribe::parse_frames(&mut parse)?),
             "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
-            _ => return Ok(Command::Unknown(Unknown::new(command))),
+            "quit" => Command::Quit(Quit::new()),
+            "reset" => Command::Reset(Reset::new()),
+            "object" => Command::Object(Object::parse_frames(&mut parse)?),
+            "command" => Command::CommandMeta(CommandMeta::parse_frames(&mut parse)?),
+            "memory" => Command::Memory(Memory::parse_frames(&mut parse)?),
+            "hello" => Command::Hello(Hello::parse_frames(&mut parse)?),
+            "auth" => Command::Auth(Auth::parse_frames(&mut parse)?),
+            "debug" => Command::Debug(Debug::parse_frames(&mut parse)?),
+            "slowlog" => Command::Slowlog(Slowlog::parse_frames(&mut parse)?),
+            "config" => Command::Config(Config::parse_frames(&mut parse)?),
+            "save" => Command::Save(Save::new()),
+            "sadd" => Command::Sadd(Sadd::parse_frames(&mut parse)?),
+            "smembers" => Command::Smembers(Smembers::parse_frames(&mut parse)?),
+            "sinter" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Inter, false, &mut parse)?),
+            "sunion" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Union, false, &mut parse)?),
+            "sdiff" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Diff, false, &mut parse)?),
+            "sinterstore" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Inter, true, &mut parse)?),
+            "sunionstore" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Union, true, &mut parse)?),
+            "sdiffstore" => Command::SetAlgebra(SetAlgebra::parse_frames(SetOperation::Diff, true, &mut parse)?),
+            "zadd" => Command::Zadd(Zadd::parse_frames(&mut parse)?),
+            "zrangebyscore" => Command::Zrangebyscore(Zrangebyscore::parse_frames(&mut parse)?),
+            "zrem" => Command::Zrem(Zrem::parse_frames(&mut parse)?),
+            "zrank" => Command::Zrank(Zrank::parse_frames(&mut parse)?),
+            "zincrby" => Command::Zincrby(Zincrby::parse_frames(&mut parse)?),
+            "pfadd" => Command::Pfadd(Pfadd::parse_frames(&mut parse)?),
+            "pfcount" => Command::Pfcount(Pfcount::parse_frames(&mut parse)?),
+            "geoadd" => Command::Geoadd(Geoadd::parse_frames(&mut parse)?),
+            "geodist" => Command::Geodist(Geodist::parse_frames(&mut parse)?),
+            "lastsave" => Command::Lastsave(Lastsave::new()),
+            "waitaof" => Command::Waitaof(Waitaof::parse_frames(&mut parse)?),
+            "copy" => Command::Copy(Copy::parse_frames(&mut parse)?),
+            "ttl" => Command::Ttl(Ttl::parse_frames(false, &mut parse)?),
+            "pttl" => Command::Ttl(Ttl::parse_frames(true, &mut parse)?),
+            "eval" => Command::Script(Script::parse_frames("eval", &mut parse)?),
+            "evalsha" => Command::Script(Script::parse_frames("evalsha", &mut parse)?),
+            "script" => Command::Script(Script::parse_frames("script", &mut parse)?),
+            "expire" => Command::Expire(Expire::parse_frames(false, &mut parse)?),
+            "pexpire" => Command::Expire(Expire::parse_frames(true, &mut parse)?),
+            "persist" => Command::Persist(Persist::parse_frames(&mut parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(&mut parse)?),
+            "setnx" => Command::Setnx(Setnx::parse_frames(&mut parse)?),
+            "lpush" => Command::Push(Push::parse_frames(true, &mut parse)?),
+            "rpush" => Command::Push(Push::parse_frames(false, &mut parse)?),
+            "lpop" => Command::Pop(Pop::parse_frames(true, &mut parse)?),
+            "rpop" => Command::Pop(Pop::parse_frames(false, &mut parse)?),
+            "rename" => Command::Rename(Rename::parse_frames(&mut parse)?),
+            "type" => Command::Type(Type::parse_frames(&mut parse)?),
+            "sintercard" => Command::Sintercard(Sintercard::parse_frames(&mut parse)?),
+            "hset" => Command::Hset(Hset::parse_frames(&mut parse)?),
+            "hget" => Command::Hget(Hget::parse_frames(&mut parse)?),
+            "delifeq" => Command::Delifeq(Delifeq::parse_frames(&mut parse)?),
+            name => return Ok(Command::Unknown(Unknown::new(name))),
         };
 
         // 不应该有尚未读出的数据
@@ -61,10 +331,59 @@ impl Command {
         match self {
             Command::Get(_) => "get",
             Command::Set(_) => "set",
+            Command::Getset(_) => "getset",
+            Command::Getrange(_) => "getrange",
+            Command::Append(_) => "append",
+            Command::Mset(_) => "mset",
+            Command::Incr(cmd) => cmd.get_name(),
+            Command::Keys(_) => "keys",
+            Command::Del(_) => "del",
             Command::Publish(_) => "publish",
             Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::Psubscribe(_) => "psubscribe",
+            Command::Punsubscribe(_) => "punsubscribe",
             Command::Ping(_) => "ping",
+            Command::Quit(_) => "quit",
+            Command::Reset(_) => "reset",
+            Command::Object(_) => "object",
+            Command::CommandMeta(_) => "command",
+            Command::Memory(_) => "memory",
+            Command::Hello(_) => "hello",
+            Command::Auth(_) => "auth",
+            Command::Debug(_) => "debug",
+            Command::Slowlog(_) => "slowlog",
+            Command::Config(_) => "config",
+            Command::Save(_) => "save",
+            Command::Sadd(_) => "sadd",
+            Command::Smembers(_) => "smembers",
+            Command::SetAlgebra(cmd) => cmd.get_name(),
+            Command::Zadd(_) => "zadd",
+            Command::Zrangebyscore(_) => "zrangebyscore",
+            Command::Zrem(_) => "zrem",
+            Command::Zrank(_) => "zrank",
+            Command::Zincrby(_) => "zincrby",
+            Command::Pfadd(_) => "pfadd",
+            Command::Pfcount(_) => "pfcount",
+            Command::Geoadd(_) => "geoadd",
+            Command::Geodist(_) => "geodist",
+            Command::Lastsave(_) => "lastsave",
+            Command::Waitaof(_) => "waitaof",
+            Command::Copy(_) => "copy",
+            Command::Ttl(cmd) => cmd.get_name(),
+            Command::Script(cmd) => cmd.get_name(),
+            Command::Expire(cmd) => cmd.get_name(),
+            Command::Persist(_) => "persist",
+            Command::Scan(_) => "scan",
+            Command::Setnx(_) => "setnx",
+            Command::Push(cmd) => cmd.get_name(),
+            Command::Pop(cmd) => cmd.get_name(),
+            Command::Rename(_) => "rename",
+            Command::Type(_) => "type",
+            Command::Sintercard(_) => "sintercard",
+            Command::Hset(_) => "hset",
+            Command::Hget(_) => "hget",
+            Command::Delifeq(_) => "delifeq",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
@@ -73,18 +392,68 @@ impl Command {
         self,
         db: &Db,
         dst: &mut Connection,
-        shutdown: &mut Shutdown
+        shutdown: &mut Shutdown,
+        config: &ServerConfig,
     ) -> crate::Result<()> {
         use Command::*;
 
         match self {
             Get(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
+            Getset(cmd) => cmd.apply(db, dst).await,
+            Getrange(cmd) => cmd.apply(db, dst).await,
+            Append(cmd) => cmd.apply(db, dst).await,
+            Mset(cmd) => cmd.apply(db, dst).await,
+            Incr(cmd) => cmd.apply(db, dst).await,
+            Keys(cmd) => cmd.apply(db, dst).await,
+            Del(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
-            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Subscribe(cmd) => cmd.apply(db, dst, shutdown, config).await,
             Ping(cmd) => cmd.apply(dst).await,
-            Unknown(cmd) => cmd.apply(dst).await,
-            Unsubscribe(cmd) => Err("Unsubscribe is not support in this context".into()),
+            Quit(cmd) => cmd.apply(dst).await,
+            Reset(cmd) => cmd.apply(dst).await,
+            Object(cmd) => cmd.apply(db, dst).await,
+            CommandMeta(cmd) => cmd.apply(dst).await,
+            Memory(cmd) => cmd.apply(db, dst).await,
+            Hello(cmd) => cmd.apply(dst).await,
+            Auth(cmd) => cmd.apply(dst).await,
+            Debug(cmd) => cmd.apply(db, dst).await,
+            Slowlog(cmd) => cmd.apply(db, dst).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            Save(cmd) => cmd.apply(db, dst, config).await,
+            Sadd(cmd) => cmd.apply(db, dst).await,
+            Smembers(cmd) => cmd.apply(db, dst).await,
+            SetAlgebra(cmd) => cmd.apply(db, dst).await,
+            Zadd(cmd) => cmd.apply(db, dst).await,
+            Zrangebyscore(cmd) => cmd.apply(db, dst).await,
+            Zrem(cmd) => cmd.apply(db, dst).await,
+            Zrank(cmd) => cmd.apply(db, dst).await,
+            Zincrby(cmd) => cmd.apply(db, dst).await,
+            Pfadd(cmd) => cmd.apply(db, dst).await,
+            Pfcount(cmd) => cmd.apply(db, dst).await,
+            Geoadd(cmd) => cmd.apply(db, dst).await,
+            Geodist(cmd) => cmd.apply(db, dst).await,
+            Lastsave(cmd) => cmd.apply(db, dst).await,
+            Waitaof(cmd) => cmd.apply(dst).await,
+            Copy(cmd) => cmd.apply(db, dst).await,
+            Ttl(cmd) => cmd.apply(db, dst).await,
+            Script(cmd) => cmd.apply(dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            Persist(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
+            Setnx(cmd) => cmd.apply(db, dst).await,
+            Push(cmd) => cmd.apply(db, dst).await,
+            Pop(cmd) => cmd.apply(db, dst).await,
+            Rename(cmd) => cmd.apply(db, dst).await,
+            Type(cmd) => cmd.apply(db, dst).await,
+            Sintercard(cmd) => cmd.apply(db, dst).await,
+            Hset(cmd) => cmd.apply(db, dst).await,
+            Hget(cmd) => cmd.apply(db, dst).await,
+            Delifeq(cmd) => cmd.apply(db, dst).await,
+            Unknown(cmd) => cmd.apply(config.unknown_command, dst).await,
+            Unsubscribe(cmd) => cmd.apply(dst).await,
+            Psubscribe(cmd) => cmd.apply(db, dst, shutdown, config).await,
+            Punsubscribe(cmd) => cmd.apply(dst).await,
         }
     }
 }
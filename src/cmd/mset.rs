@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 一次设置多个键的值，`MSET key value [key value ...]`
+/// 与多次执行 `SET` 相同，已有的值及有效期都会被覆盖
+#[derive(Debug)]
+pub struct Mset {
+    pairs: Vec<(String, Bytes)>,
+}
+
+/// `MSET key value [key value ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::block("data", &[ArgSpec::key("key"), ArgSpec::string("value")]).multiple()];
+
+impl Mset {
+    /// 新建一条 `Mset` 命令
+    pub fn new(pairs: Vec<(String, Bytes)>) -> Mset {
+        Mset { pairs }
+    }
+
+    /// 从 `Parse` 中解析出 `Mset` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Mset> {
+        // 至少得设置一个键
+        let mut pairs = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => pairs.push((key, parse.next_bytes()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Mset { pairs })
+    }
+
+    /// 写入所有键值，并回复 `OK`
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.mset(self.pairs);
+
+        let response = Frame::ok();
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"mset"));
+        for (key, value) in self.pairs {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+            frame.push_bulk(value);
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,46 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 查看键的内部信息
+/// 目前只支持 `OBJECT ENCODING key`，返回值的内部编码，如 `int`、`raw`
+#[derive(Debug)]
+pub enum Object {
+    Encoding { key: String },
+}
+
+/// `OBJECT ENCODING key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key").token("ENCODING")];
+
+impl Object {
+    /// 从 `Parse` 中解析出 `Object` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Object> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "encoding" => {
+                let key = parse.next_string()?;
+                Ok(Object::Encoding { key })
+            },
+            _ => Err(format!("ERR unknown subcommand '{}' for 'object'", subcommand).into()),
+        }
+    }
+
+    /// 查询键的信息，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Object::Encoding { key } => match db.encoding(&key) {
+                Some(encoding) => Frame::Bulk(Bytes::from_static(encoding.as_bytes())),
+                None => Frame::Null,
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, KvStore, Parse, cmd::ArgSpec};
+
+/// 移除键的有效期，使其不再过期
+/// 移除了有效期时返回 1，键不存在或没有有效期时返回 0
+#[derive(Debug)]
+pub struct Persist {
+    key: String,
+}
+
+/// `PERSIST key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
+impl Persist {
+    /// 新建一条 `PERSIST` 命令
+    pub fn new(key: impl ToString) -> Persist {
+        Persist { key: key.to_string() }
+    }
+
+    /// 从 `Parse` 中解析出 `Persist` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Persist> {
+        let key = parse.next_string()?;
+
+        Ok(Persist { key })
+    }
+
+    /// 移除存储中键的有效期，返回回复的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        Frame::Integer(store.persist(&self.key) as i64)
+    }
+
+    /// 移除键的有效期，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("persist".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame
+    }
+}
@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 向 HyperLogLog 中添加元素，估算的基数改变时返回 1，否则返回 0
+/// 键不存在时创建一个新的 HyperLogLog
+#[derive(Debug)]
+pub struct Pfadd {
+    key: String,
+    elements: Vec<Bytes>,
+}
+
+/// `PFADD key [element ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("element").optional().multiple()];
+
+impl Pfadd {
+    /// 新建一条 `Pfadd` 命令
+    pub fn new(key: impl ToString, elements: Vec<Bytes>) -> Pfadd {
+        Pfadd {
+            key: key.to_string(),
+            elements,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Pfadd` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Pfadd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // 可以不添加元素，只创建一个空的 HyperLogLog
+        let mut elements = vec![];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(element) => elements.push(element),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Pfadd { key, elements })
+    }
+
+    /// 向数据库中的 HyperLogLog 添加元素
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.pfadd(self.key, self.elements) {
+            Ok(changed) => Frame::Integer(changed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"pfadd"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for element in self.elements {
+            frame.push_bulk(element);
+        }
+
+        frame
+    }
+}
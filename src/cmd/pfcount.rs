@@ -0,0 +1,65 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 返回 HyperLogLog 估算的基数
+/// 指定多个键时，先合并各个 HyperLogLog 再估算，不存在的键视为空
+#[derive(Debug)]
+pub struct Pfcount {
+    keys: Vec<String>,
+}
+
+/// `PFCOUNT key [key ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key").multiple()];
+
+impl Pfcount {
+    /// 新建一条 `Pfcount` 命令
+    pub fn new(keys: &[String]) -> Pfcount {
+        Pfcount { keys: keys.to_vec() }
+    }
+
+    /// 从 `Parse` 中解析出 `Pfcount` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Pfcount> {
+        use ParseError::EndOfStream;
+
+        // 至少得有一个键
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Pfcount { keys })
+    }
+
+    /// 合并数据库中的 HyperLogLog，并返回估算的基数
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.pfcount(&self.keys) {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"pfcount"));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        frame
+    }
+}
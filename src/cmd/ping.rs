@@ -1,13 +1,16 @@
 use bytes::Bytes;
 use tracing::instrument;
 
-use crate::{Frame, Connection, Parse, ParseError};
+use crate::{Frame, Connection, Parse, ParseError, cmd::ArgSpec};
 
 #[derive(Debug, Default)]
 pub struct Ping {
     msg: Option<String>,
 }
 
+/// `PING [message]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::string("message").optional()];
+
 impl Ping {
     /// 新建一条 `Ping` 命令
     pub fn new(msg: Option<String>) -> Self {
@@ -27,7 +30,7 @@ impl Ping {
     pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
         let response = match self.msg {
             Some(msg) => Frame::Bulk(Bytes::from(msg)),
-            None => Frame::Simple("PONG".to_string()),
+            None => Frame::from_static_simple("PONG"),
         };
 
         dst.write_frame(&response).await?;
@@ -35,6 +38,23 @@ impl Ping {
         Ok(())
     }
 
+    /// 订阅模式下的 `PING`，与 Redis 相同，RESP2 连接回复 `["pong", message]`，
+    /// 没有 message 时为空字符串；RESP3 连接可以区分普通回复与推送，仍按普通方式回复
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply_subscribed(self, dst: &mut Connection) -> crate::Result<()> {
+        if dst.protocol() == 3 {
+            return self.apply(dst).await;
+        }
+
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from_static(b"pong"));
+        response.push_bulk(self.msg.map(Bytes::from).unwrap_or_default());
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
 
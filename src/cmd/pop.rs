@@ -0,0 +1,115 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 弹出列表头部或尾部的元素
+/// `LPOP key [count]`/`RPOP key [count]`，不指定 `count` 时返回单个元素，键不存在时返回 nil；
+/// 指定 `count` 时以数组返回至多 `count` 个元素，键不存在时同样返回 nil
+#[derive(Debug)]
+pub struct Pop {
+    key: String,
+    count: Option<u64>,
+    /// 命令为 `LPOP`，从列表的头部弹出
+    left: bool,
+}
+
+/// `LPOP`/`RPOP key [count]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::integer("count").optional()];
+
+impl Pop {
+    /// 新建一条 `LPOP` 命令
+    pub fn lpop(key: impl ToString, count: Option<u64>) -> Pop {
+        Pop {
+            key: key.to_string(),
+            count,
+            left: true,
+        }
+    }
+
+    /// 新建一条 `RPOP` 命令
+    pub fn rpop(key: impl ToString, count: Option<u64>) -> Pop {
+        Pop {
+            key: key.to_string(),
+            count,
+            left: false,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Pop` 命令，命令头已被读取
+    /// `left` 由命令名决定，为 `true` 时从列表的头部弹出
+    pub(crate) fn parse_frames(left: bool, parse: &mut Parse) -> crate::Result<Pop> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let count = match parse.next_int() {
+            Ok(count) => Some(count),
+            Err(EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Pop { key, count, left })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.left {
+            "lpop"
+        } else {
+            "rpop"
+        }
+    }
+
+    /// 从数据库中的列表弹出元素，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.count {
+            Some(count) => {
+                let count = usize::try_from(count).unwrap_or(usize::MAX);
+                let popped = if self.left {
+                    db.lpop_count(&self.key, count)
+                } else {
+                    db.rpop_count(&self.key, count)
+                };
+
+                match popped {
+                    Ok(Some(elements)) => Frame::Array(elements.into_iter().map(Frame::Bulk).collect()),
+                    Ok(None) => Frame::Null,
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            },
+            None => {
+                let popped = if self.left {
+                    db.lpop(&self.key)
+                } else {
+                    db.rpop(&self.key)
+                };
+
+                match popped {
+                    Ok(Some(element)) => Frame::Bulk(element),
+                    Ok(None) => Frame::Null,
+                    Err(err) => Frame::Error(err.to_string()),
+                }
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from(count.to_string()));
+        }
+
+        frame
+    }
+}
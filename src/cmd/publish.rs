@@ -1,34 +1,60 @@
 use bytes::Bytes;
 
-use crate::{Frame, Connection, Db, Parse};
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
 
+/// 向频道发送消息，返回收到消息的订阅者数量
+/// `PUBLISH channel message [WAIT]`
+///
+/// `WAIT` 为本服务的扩展：频道缓存已满或有订阅者积压超过软上限时等待最慢的订阅者，
+/// 而不是丢弃最早的消息或使订阅者被断开，代价是发布者会被慢的订阅者拖慢
 #[derive(Debug)]
 pub struct Publish {
     channel: String,
     message: Bytes,
+    wait: bool,
 }
 
+/// `PUBLISH channel message [WAIT]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::string("channel"), ArgSpec::string("message"), ArgSpec::pure_token("wait", "WAIT").optional()];
+
 impl Publish {
     /// 创建一个 `Publish` 命令，包含对应的广播频道和要发送的消息
     pub(crate) fn new(channel: impl ToString, message: Bytes) -> Self {
         Publish {
             channel: channel.to_string(),
             message,
+            wait: false,
         }
     }
 
+    /// 频道缓存已满时等待，而不是丢弃消息
+    pub(crate) fn wait(self) -> Self {
+        Publish { wait: true, ..self }
+    }
+
     /// 服务收到命令的 `Frame::Array` ，确认何种命令后调此生成对应的命令
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Publish> {
         let channel = parse.next_string()?;
         let message = parse.next_bytes()?;
 
-        Ok(Publish { channel, message })
+        let wait = match parse.next_string() {
+            Ok(s) if s.eq_ignore_ascii_case("wait") => true,
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Publish { channel, message, wait })
     }
 
     /// 服务端接收命令后，处理并返回
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let num_subscribers = db.publish(&self.channel, self.message);
-        let response = Frame::Integer(num_subscribers as u64);
+        let num_subscribers = if self.wait {
+            db.publish_wait(&self.channel, self.message).await
+        } else {
+            db.publish(&self.channel, self.message)
+        };
+        let response = Frame::Integer(num_subscribers as i64);
         dst.write_frame(&response).await?;
 
         Ok(())
@@ -40,6 +66,9 @@ impl Publish {
         frame.push_bulk(Bytes::from("publish".as_bytes()));
         frame.push_bulk(Bytes::from(self.channel.into_bytes()));
         frame.push_bulk(self.message);
+        if self.wait {
+            frame.push_bulk(Bytes::from_static(b"wait"));
+        }
 
         frame
     }
@@ -0,0 +1,94 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 将一个或多个元素插入列表的头部或尾部，返回插入后列表的长度
+/// `LPUSH key element [element ...]`/`RPUSH key element [element ...]`，键不存在时创建一个新的列表
+#[derive(Debug)]
+pub struct Push {
+    key: String,
+    elements: Vec<Bytes>,
+    /// 命令为 `LPUSH`，插入列表的头部
+    left: bool,
+}
+
+/// `LPUSH`/`RPUSH key element [element ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("element").multiple()];
+
+impl Push {
+    /// 新建一条 `LPUSH` 命令
+    pub fn lpush(key: impl ToString, elements: Vec<Bytes>) -> Push {
+        Push {
+            key: key.to_string(),
+            elements,
+            left: true,
+        }
+    }
+
+    /// 新建一条 `RPUSH` 命令
+    pub fn rpush(key: impl ToString, elements: Vec<Bytes>) -> Push {
+        Push {
+            key: key.to_string(),
+            elements,
+            left: false,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Push` 命令，命令头已被读取
+    /// `left` 由命令名决定，为 `true` 时插入列表的头部
+    pub(crate) fn parse_frames(left: bool, parse: &mut Parse) -> crate::Result<Push> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // 至少得插入一个元素
+        let mut elements = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(element) => elements.push(element),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Push { key, elements, left })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.left {
+            "lpush"
+        } else {
+            "rpush"
+        }
+    }
+
+    /// 将元素插入数据库中的列表，并返回列表的长度
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.push(self.key, self.elements, self.left) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for element in self.elements {
+            frame.push_bulk(element);
+        }
+
+        frame
+    }
+}
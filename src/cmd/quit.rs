@@ -0,0 +1,36 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection};
+
+/// 请求服务端关闭连接，服务端回复 `OK` 后关闭
+#[derive(Debug, Default)]
+pub struct Quit;
+
+impl Quit {
+    /// 新建一条 `Quit` 命令
+    pub fn new() -> Quit {
+        Quit
+    }
+
+    /// 回复 `OK`，并标记连接在回复后关闭
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::ok();
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+        dst.close_after_reply();
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"quit"));
+
+        frame
+    }
+}
@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 将 `key` 重命名为 `newkey`，值与有效期保持不变，成功时返回 `OK`
+/// `newkey` 已存在时其原有的值被覆盖，`key` 不存在时返回错误
+#[derive(Debug)]
+pub struct Rename {
+    key: String,
+    newkey: String,
+}
+
+/// `RENAME key newkey` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::key("newkey")];
+
+impl Rename {
+    /// 新建一条 `Rename` 命令
+    pub fn new(key: impl ToString, newkey: impl ToString) -> Rename {
+        Rename {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Rename` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Rename> {
+        let key = parse.next_string()?;
+        let newkey = parse.next_string()?;
+
+        Ok(Rename { key, newkey })
+    }
+
+    /// 在数据库中重命名键，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.rename(&self.key, self.newkey) {
+            Ok(()) => Frame::ok(),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"rename"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.newkey.into_bytes()));
+
+        frame
+    }
+}
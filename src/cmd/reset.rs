@@ -0,0 +1,38 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection};
+
+/// 将连接恢复到刚建立时的状态：退出订阅模式，协议恢复为 RESP2
+#[derive(Debug, Default)]
+pub struct Reset;
+
+impl Reset {
+    /// 新建一条 `Reset` 命令
+    pub fn new() -> Reset {
+        Reset
+    }
+
+    /// 恢复连接的状态，并回复 `RESET`
+    /// 订阅模式下由订阅命令在退出前取消所有订阅
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        dst.set_protocol(2);
+
+        let response = Frame::from_static_simple("RESET");
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"reset"));
+
+        frame
+    }
+}
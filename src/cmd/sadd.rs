@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 向集合中添加一个或多个成员，返回新添加的成员数量
+/// 键不存在时创建一个新的集合
+#[derive(Debug)]
+pub struct Sadd {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+/// `SADD key member [member ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("member").multiple()];
+
+impl Sadd {
+    /// 新建一条 `Sadd` 命令
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> Sadd {
+        Sadd {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Sadd` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Sadd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // 至少得添加一个成员
+        let mut members = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Sadd { key, members })
+    }
+
+    /// 向数据库中的集合添加成员，并返回新添加的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.sadd(self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"sadd"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,42 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, server::Config};
+
+/// 将数据库保存到配置的快照文件中，服务下次启动时会从中恢复数据
+/// 保存过程中会短暂地持有数据库的锁
+#[derive(Debug, Default)]
+pub struct Save;
+
+impl Save {
+    /// 新建一条 `Save` 命令
+    pub fn new() -> Save {
+        Save
+    }
+
+    /// 保存快照，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst, config))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, config: &Config) -> crate::Result<()> {
+        let response = match &config.save_path {
+            Some(path) => match db.save(path) {
+                Ok(()) => Frame::ok(),
+                Err(err) => Frame::Error(format!("ERR {}", err)),
+            },
+            None => Frame::Error("ERR no save path configured".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"save"));
+
+        frame
+    }
+}
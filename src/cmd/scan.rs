@@ -0,0 +1,97 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 增量地遍历键空间，`SCAN cursor [MATCH pattern] [COUNT count]`
+/// 回复下一次使用的游标及本次遍历到的键，游标为 0 时遍历结束
+/// 与 `KEYS` 不同，每次只检查一部分键，不会长时间阻塞其它连接
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: Option<u64>,
+}
+
+/// 未指定 `COUNT` 时每次检查的键数量，与 Redis 相同
+const DEFAULT_COUNT: u64 = 10;
+
+/// `SCAN cursor [MATCH pattern] [COUNT count]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::integer("cursor"),
+    ArgSpec::pattern("pattern").token("MATCH").optional(),
+    ArgSpec::integer("count").token("COUNT").optional(),
+];
+
+impl Scan {
+    /// 新建一条 `Scan` 命令
+    pub fn new(cursor: u64, pattern: Option<String>, count: Option<u64>) -> Scan {
+        Scan { cursor, pattern, count }
+    }
+
+    /// 从 `Parse` 中解析出 `Scan` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        use ParseError::EndOfStream;
+
+        let cursor = match parse.next_int() {
+            Ok(cursor) => cursor,
+            Err(ParseError::WrongType { .. }) => return Err("ERR invalid cursor".into()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut pattern = None;
+        let mut count = None;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "MATCH" => pattern = Some(parse.next_string()?),
+                Ok(s) if s.to_uppercase() == "COUNT" => match parse.next_int()? {
+                    0 => return Err("ERR syntax error".into()),
+                    n => count = Some(n),
+                },
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Scan { cursor, pattern, count })
+    }
+
+    /// 遍历一部分键，并将游标及遍历到的键写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.count.unwrap_or(DEFAULT_COUNT).min(usize::MAX as u64) as usize;
+        let (next, keys) = db.scan(self.cursor, self.pattern.as_deref(), count);
+
+        let mut found = Frame::array();
+        for key in keys {
+            found.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        // 与 Redis 相同，游标以字符串形式返回
+        let response = Frame::Array(vec![Frame::Bulk(Bytes::from(next.to_string())), found]);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"scan"));
+        frame.push_bulk(Bytes::from(self.cursor.to_string()));
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from_static(b"MATCH"));
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from_static(b"COUNT"));
+            frame.push_bulk(Bytes::from(count.to_string()));
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,59 @@
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Parse, cmd::ArgSpec};
+
+/// 脚本相关的命令 `EVAL`/`EVALSHA`/`SCRIPT`
+/// mini-redis 不支持 Lua 脚本，这些命令总是返回错误。注册这些命令是为了让探测脚本支持的
+/// 客户端得到明确的回复，而不是未知命令的错误
+#[derive(Debug)]
+pub struct Script {
+    name: &'static str,
+}
+
+/// `EVAL script numkeys [key [key ...]] [arg [arg ...]]` 的参数
+pub(crate) const EVAL_ARGS: &[ArgSpec] = &[
+    ArgSpec::string("script"),
+    ArgSpec::integer("numkeys"),
+    ArgSpec::key("key").optional().multiple(),
+    ArgSpec::string("arg").optional().multiple(),
+];
+
+/// `EVALSHA sha1 numkeys [key [key ...]] [arg [arg ...]]` 的参数
+pub(crate) const EVALSHA_ARGS: &[ArgSpec] = &[
+    ArgSpec::string("sha1"),
+    ArgSpec::integer("numkeys"),
+    ArgSpec::key("key").optional().multiple(),
+    ArgSpec::string("arg").optional().multiple(),
+];
+
+/// `SCRIPT subcommand [arg [arg ...]]` 的参数
+pub(crate) const SCRIPT_ARGS: &[ArgSpec] = &[ArgSpec::string("subcommand"), ArgSpec::string("arg").optional().multiple()];
+
+impl Script {
+    /// 从 `Parse` 中解析出 `Script` 命令，命令头已被读取
+    /// 不会执行，因此其余参数全部忽略
+    pub(crate) fn parse_frames(name: &'static str, parse: &mut Parse) -> crate::Result<Script> {
+        while parse.remaining() > 0 {
+            parse.next_bytes()?;
+        }
+
+        Ok(Script { name })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 返回不支持脚本的错误
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Error("ERR this build does not support scripting".to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
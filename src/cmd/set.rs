@@ -1,25 +1,165 @@
 use bytes::Bytes;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
 use tracing::{debug, instrument};
 
-use crate::{Connection, Db, Frame, Parse, ParseError};
+use crate::{Connection, Frame, KvStore, Parse, ParseError, cmd::ArgSpec};
 
 /// 设置一个键，对应保存一个数据，可以选择设置键值的有效期
 /// 若数据库中已有此键保存数据，则更新其值
+/// 支持 `NX`/`XX`/`GET`/`KEEPTTL`/`EX`/`PX`/`EXAT`/`PXAT` 选项
 #[derive(Debug)]
 pub struct Set {
     key: String,
     value: Bytes,
-    expire: Option<Duration>,
+    options: SetOptions,
+}
+
+/// `SET` 的选项，使用链式调用构建
+///
+/// ```
+/// use std::time::Duration;
+/// use mini_redis::cmd::SetOptions;
+///
+/// // SET key value NX GET PX 1000
+/// let options = SetOptions::new().nx().get().expire(Duration::from_secs(1));
+/// # let _ = options;
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    condition: Option<SetCondition>,
+    expiration: Option<Expiration>,
+    get: bool,
+}
+
+/// 写入的条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// 键不存在时才写入
+    Nx,
+    /// 键存在时才写入
+    Xx,
+}
+
+/// 写入后键的有效期，未指定时键不会过期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// 从现在起经过一段时间后过期
+    In(Duration),
+    /// 在某一时刻过期
+    At(SystemTime),
+    /// 保留键原有的有效期
+    KeepTtl,
+}
+
+/// 存储执行带选项的 `SET` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetOutcome {
+    /// 是否满足条件并写入了新值
+    pub written: bool,
+    /// 键原先的值，只在指定了 `GET` 选项时返回
+    pub previous: Option<Bytes>,
+}
+
+/// `SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("key"),
+    ArgSpec::string("value"),
+    ArgSpec::one_of("condition", &[
+        ArgSpec::pure_token("nx", "NX"),
+        ArgSpec::pure_token("xx", "XX"),
+    ]).optional(),
+    ArgSpec::pure_token("get", "GET").optional(),
+    ArgSpec::one_of("expiration", &[
+        ArgSpec::integer("seconds").token("EX"),
+        ArgSpec::integer("milliseconds").token("PX"),
+        ArgSpec::integer("unix-time-seconds").token("EXAT"),
+        ArgSpec::integer("unix-time-milliseconds").token("PXAT"),
+        ArgSpec::pure_token("keepttl", "KEEPTTL"),
+    ]).optional(),
+];
+
+/// 有效期为 0 或超出范围时返回的错误
+const INVALID_EXPIRE: &str = "ERR invalid expire time in 'set' command";
+
+impl SetOptions {
+    /// 不带任何选项，与普通的 `SET` 相同
+    pub fn new() -> SetOptions {
+        SetOptions::default()
+    }
+
+    /// 键不存在时才写入
+    pub fn nx(self) -> SetOptions {
+        SetOptions { condition: Some(SetCondition::Nx), ..self }
+    }
+
+    /// 键存在时才写入
+    pub fn xx(self) -> SetOptions {
+        SetOptions { condition: Some(SetCondition::Xx), ..self }
+    }
+
+    /// 返回键原先的值
+    pub fn get(self) -> SetOptions {
+        SetOptions { get: true, ..self }
+    }
+
+    /// 经过 `expire` 后过期
+    pub fn expire(self, expire: Duration) -> SetOptions {
+        SetOptions { expiration: Some(Expiration::In(expire)), ..self }
+    }
+
+    /// 在 `at` 时刻过期
+    pub fn expire_at(self, at: SystemTime) -> SetOptions {
+        SetOptions { expiration: Some(Expiration::At(at)), ..self }
+    }
+
+    /// 保留键原有的有效期
+    pub fn keep_ttl(self) -> SetOptions {
+        SetOptions { expiration: Some(Expiration::KeepTtl), ..self }
+    }
+
+    /// 写入的条件
+    pub fn condition(&self) -> Option<SetCondition> {
+        self.condition
+    }
+
+    /// 写入后键的有效期
+    pub fn expiration(&self) -> Option<Expiration> {
+        self.expiration
+    }
+
+    /// 是否需要返回键原先的值
+    pub fn is_get(&self) -> bool {
+        self.get
+    }
+
+    /// 只设置了相对有效期或没有任何选项时，返回有效期，可以使用普通的 `KvStore::set`
+    fn plain_expire(&self) -> Option<Option<Duration>> {
+        match (self.condition, self.get, self.expiration) {
+            (None, false, None) => Some(None),
+            (None, false, Some(Expiration::In(expire))) => Some(Some(expire)),
+            _ => None,
+        }
+    }
 }
 
 impl Set {
     /// 新建一条 `Set` 命令
     pub fn new(key: impl ToString, value: Bytes, expire: Option<Duration>) -> Set {
+        let options = match expire {
+            Some(expire) => SetOptions::new().expire(expire),
+            None => SetOptions::new(),
+        };
+
+        Set::with_options(key, value, options)
+    }
+
+    /// 新建一条带选项的 `Set` 命令
+    pub fn with_options(key: impl ToString, value: Bytes, options: SetOptions) -> Set {
         Set {
             key: key.to_string(),
             value,
-            expire
+            options,
         }
     }
 
@@ -33,9 +173,17 @@ impl Set {
         &self.value
     }
 
-    /// 返回有效期
+    /// 返回相对的有效期，使用 `EXAT`/`PXAT`/`KEEPTTL` 或未设置有效期时返回 `None`
     pub fn expire(&self) -> Option<Duration> {
-        self.expire
+        match self.options.expiration {
+            Some(Expiration::In(expire)) => Some(expire),
+            _ => None,
+        }
+    }
+
+    /// 返回所有选项
+    pub fn options(&self) -> &SetOptions {
+        &self.options
     }
 
     /// 和 `Get` 类似
@@ -45,33 +193,71 @@ impl Set {
 
         let key = parse.next_string()?;
         let value = parse.next_bytes()?;
-        let mut expire = None;
+        let mut options = SetOptions::new();
 
-        match parse.next_string() {
-            // 当前只支持设置秒或毫秒，标志为 EX/PX
-            Ok(s) if s.to_uppercase() == "EX" => {
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
-            },
-            Ok(s) if s.to_uppercase() == "PX" => {
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
-            },
-            Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
-            // 没设置生效时间，无妨
-            Err(EndOfStream) => {},
-            Err(err) => return Err(err.into())
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            // 条件与有效期都只能指定一个
+            match &option[..] {
+                "NX" | "XX" if options.condition.is_some() => return Err("ERR syntax error".into()),
+                "NX" => options = options.nx(),
+                "XX" => options = options.xx(),
+                "GET" => options = options.get(),
+                "KEEPTTL" | "EX" | "PX" | "EXAT" | "PXAT" if options.expiration.is_some() => {
+                    return Err("ERR syntax error".into())
+                },
+                "KEEPTTL" => options = options.keep_ttl(),
+                "EX" | "PX" | "EXAT" | "PXAT" => {
+                    let time = match parse.next_int()? {
+                        0 => return Err(INVALID_EXPIRE.into()),
+                        time if option.starts_with('E') => Duration::from_secs(time),
+                        time => Duration::from_millis(time),
+                    };
+
+                    // 过期时刻无法表示时报错，而不是在写入时溢出
+                    options = if option.ends_with("AT") {
+                        let at = UNIX_EPOCH.checked_add(time).ok_or(INVALID_EXPIRE)?;
+                        let expire = at.duration_since(SystemTime::now()).unwrap_or_default();
+                        Instant::now().checked_add(expire).ok_or(INVALID_EXPIRE)?;
+                        options.expire_at(at)
+                    } else {
+                        Instant::now().checked_add(time).ok_or(INVALID_EXPIRE)?;
+                        options.expire(time)
+                    };
+                },
+                _ => return Err("ERR syntax error".into()),
+            }
         }
 
-        Ok( Set { key, value, expire } )
+        Ok(Set { key, value, options })
+    }
+
+    /// 向存储中写入，返回回复的 `Frame`
+    /// 未指定 `GET` 时写入成功回复 `OK`，不满足 `NX`/`XX` 条件时回复 `nil`；
+    /// 指定 `GET` 时回复键原先的值
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        if let Some(expire) = self.options.plain_expire() {
+            store.set(self.key, self.value, expire);
+            return Frame::ok();
+        }
+
+        match store.set_with_options(self.key, self.value, &self.options) {
+            Ok(outcome) if self.options.get => outcome.previous.map_or(Frame::Null, Frame::Bulk),
+            Ok(outcome) if outcome.written => Frame::ok(),
+            Ok(_) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
     }
 
     /// 服务端调用此函数，向数据库中写入，并返回结果
     #[instrument(skip(self, db, dst))]
-    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        db.set(self.key, self.value, self.expire);
-
-        let response = Frame::Simple("OK".to_string());
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
         debug!(?response);
         dst.write_frame(&response).await?;
 
@@ -85,10 +271,29 @@ impl Set {
         frame.push_bulk(Bytes::from(self.key.into_bytes()));
         frame.push_bulk(self.value);
 
+        match self.options.condition {
+            Some(SetCondition::Nx) => frame.push_bulk(Bytes::from_static(b"nx")),
+            Some(SetCondition::Xx) => frame.push_bulk(Bytes::from_static(b"xx")),
+            None => {},
+        }
+
+        if self.options.get {
+            frame.push_bulk(Bytes::from_static(b"get"));
+        }
+
         // 这里只使用毫秒，更精确的缘故？
-        if let Some(expire) = self.expire {
-            frame.push_bulk(Bytes::from("px".as_bytes()));
-            frame.push_int(expire.as_millis() as u64);
+        match self.options.expiration {
+            Some(Expiration::In(expire)) => {
+                frame.push_bulk(Bytes::from("px".as_bytes()));
+                frame.push_int(expire.as_millis() as i64);
+            },
+            Some(Expiration::At(at)) => {
+                let millis = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                frame.push_bulk(Bytes::from_static(b"pxat"));
+                frame.push_int(millis as i64);
+            },
+            Some(Expiration::KeepTtl) => frame.push_bulk(Bytes::from_static(b"keepttl")),
+            None => {},
         }
 
         frame
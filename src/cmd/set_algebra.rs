@@ -0,0 +1,110 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec, db::SetOperation};
+
+/// 集合运算命令
+/// `SINTER`/`SUNION`/`SDIFF key [key ...]` 返回运算结果的成员
+/// `SINTERSTORE`/`SUNIONSTORE`/`SDIFFSTORE destination key [key ...]` 将结果保存至
+/// `destination` 并返回其成员数量，结果为空集时删除 `destination`
+#[derive(Debug)]
+pub struct SetAlgebra {
+    op: SetOperation,
+    destination: Option<String>,
+    keys: Vec<String>,
+}
+
+/// `SINTER`/`SUNION`/`SDIFF key [key ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key").multiple()];
+
+/// `SINTERSTORE`/`SUNIONSTORE`/`SDIFFSTORE destination key [key ...]` 的参数
+pub(crate) const STORE_ARGS: &[ArgSpec] = &[ArgSpec::key("destination"), ArgSpec::key("key").multiple()];
+
+impl SetAlgebra {
+    /// 新建一条集合运算命令，`destination` 不为 `None` 时保存运算结果
+    pub(crate) fn new(op: SetOperation, destination: Option<String>, keys: &[String]) -> SetAlgebra {
+        SetAlgebra {
+            op,
+            destination,
+            keys: keys.to_vec(),
+        }
+    }
+
+    /// 从 `Parse` 中解析出集合运算命令，命令头已被读取
+    /// `store` 表示命令为 `*STORE` 形式，第一个参数为保存结果的键
+    pub(crate) fn parse_frames(op: SetOperation, store: bool, parse: &mut Parse) -> crate::Result<SetAlgebra> {
+        use ParseError::EndOfStream;
+
+        let destination = if store {
+            Some(parse.next_string()?)
+        } else {
+            None
+        };
+
+        // 至少得有一个集合
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(SetAlgebra { op, destination, keys })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        match (self.op, self.destination.is_some()) {
+            (SetOperation::Inter, false) => "sinter",
+            (SetOperation::Union, false) => "sunion",
+            (SetOperation::Diff, false) => "sdiff",
+            (SetOperation::Inter, true) => "sinterstore",
+            (SetOperation::Union, true) => "sunionstore",
+            (SetOperation::Diff, true) => "sdiffstore",
+        }
+    }
+
+    /// 执行集合运算，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.destination {
+            Some(destination) => match db.set_operation_store(self.op, destination, &self.keys) {
+                Ok(len) => Frame::Integer(len as i64),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            None => match db.set_operation(self.op, &self.keys) {
+                Ok(members) => {
+                    let mut response = Frame::array();
+                    for member in members {
+                        response.push_bulk(member);
+                    }
+                    response
+                },
+                Err(err) => Frame::Error(err.to_string()),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from(self.get_name().as_bytes()));
+        if let Some(destination) = self.destination {
+            frame.push_bulk(Bytes::from(destination.into_bytes()));
+        }
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        frame
+    }
+}
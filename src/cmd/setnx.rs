@@ -0,0 +1,57 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 键不存在时设置它的值，`SETNX key value`
+/// 写入时返回 1，键已存在时返回 0 且不修改原有的值
+#[derive(Debug)]
+pub struct Setnx {
+    key: String,
+    value: Bytes,
+}
+
+/// `SETNX key value` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("value")];
+
+impl Setnx {
+    /// 新建一条 `Setnx` 命令
+    pub fn new(key: impl ToString, value: Bytes) -> Setnx {
+        Setnx {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Setnx` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Setnx> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        Ok(Setnx { key, value })
+    }
+
+    /// 键不存在时写入，并将是否写入写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let written = db.set_nx(self.key, self.value);
+
+        let response = Frame::Integer(written as i64);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"setnx"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.value);
+
+        frame
+    }
+}
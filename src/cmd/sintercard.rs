@@ -0,0 +1,89 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 返回多个集合的交集的成员数量，不返回成员本身
+/// `SINTERCARD numkeys key [key ...] [LIMIT limit]`，指定 `LIMIT` 时数到 `limit` 即停止，0 表示不限制
+#[derive(Debug)]
+pub struct Sintercard {
+    keys: Vec<String>,
+    limit: u64,
+}
+
+/// `SINTERCARD numkeys key [key ...] [LIMIT limit]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::integer("numkeys"),
+    ArgSpec::key("key").multiple(),
+    ArgSpec::integer("limit").token("LIMIT").optional(),
+];
+
+impl Sintercard {
+    /// 新建一条 `Sintercard` 命令，`limit` 为 0 时不限制
+    pub fn new(keys: &[String], limit: u64) -> Sintercard {
+        Sintercard {
+            keys: keys.to_vec(),
+            limit,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Sintercard` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Sintercard> {
+        let numkeys = parse.next_signed_int()?;
+        if numkeys <= 0 {
+            return Err("ERR numkeys should be greater than 0".into());
+        }
+        if numkeys as usize > parse.remaining() {
+            return Err("ERR Number of keys can't be greater than number of args".into());
+        }
+
+        let keys = (0..numkeys)
+            .map(|_| parse.next_string())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let limit = match parse.next_string() {
+            Ok(option) if option.eq_ignore_ascii_case("limit") => {
+                let limit = parse.next_signed_int()?;
+                u64::try_from(limit).map_err(|_| "ERR LIMIT can't be negative")?
+            },
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(ParseError::EndOfStream) => 0,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Sintercard { keys, limit })
+    }
+
+    /// 计算交集的成员数量，并将结果写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let limit = usize::try_from(self.limit).unwrap_or(usize::MAX);
+
+        let response = match db.sintercard(&self.keys, limit) {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"sintercard"));
+        frame.push_bulk(Bytes::from(self.keys.len().to_string()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        if self.limit > 0 {
+            frame.push_bulk(Bytes::from_static(b"limit"));
+            frame.push_bulk(Bytes::from(self.limit.to_string()));
+        }
+
+        frame
+    }
+}
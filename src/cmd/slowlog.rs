@@ -0,0 +1,86 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 查看或清空慢命令日志
+/// `SLOWLOG GET [count]` 返回最近的 `count` 条记录（默认 10 条，-1 为全部）
+/// `SLOWLOG LEN` 返回记录的条数
+/// `SLOWLOG RESET` 清空所有记录
+#[derive(Debug)]
+pub enum Slowlog {
+    Get { count: Option<usize> },
+    Len,
+    Reset,
+}
+
+/// `SLOWLOG GET` 默认返回的条数
+const DEFAULT_COUNT: usize = 10;
+
+/// `SLOWLOG GET [count] | SLOWLOG LEN | SLOWLOG RESET` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::one_of("subcommand", &[
+    ArgSpec::block("get", &[ArgSpec::integer("count").optional()]).token("GET"),
+    ArgSpec::pure_token("len", "LEN"),
+    ArgSpec::pure_token("reset", "RESET"),
+])];
+
+impl Slowlog {
+    /// 从 `Parse` 中解析出 `Slowlog` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Slowlog> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => {
+                let count = match parse.next_signed_int() {
+                    Ok(-1) => None,
+                    Ok(count) => Some(usize::try_from(count).map_err(|_| "ERR count should be greater than or equal to -1")?),
+                    Err(ParseError::EndOfStream) => Some(DEFAULT_COUNT),
+                    Err(err) => return Err(err.into()),
+                };
+                Ok(Slowlog::Get { count })
+            },
+            "len" => Ok(Slowlog::Len),
+            "reset" => Ok(Slowlog::Reset),
+            _ => Err(format!("ERR unknown subcommand '{}' for 'slowlog'", subcommand).into()),
+        }
+    }
+
+    /// 读取或清空慢命令日志，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Slowlog::Get { count } => {
+                // 每条记录的格式为 [id, 时间戳, 耗时（微秒）, [参数, ..], 客户端地址, 客户端名称]
+                let entries = db
+                    .slowlog_get(count)
+                    .into_iter()
+                    .map(|entry| {
+                        let addr = entry.addr.map(|addr| addr.to_string()).unwrap_or_default();
+
+                        Frame::Array(vec![
+                            Frame::Integer(entry.id as i64),
+                            Frame::Integer(entry.timestamp as i64),
+                            Frame::Integer(entry.duration.as_micros() as i64),
+                            Frame::Array(entry.args.into_iter().map(Frame::Bulk).collect()),
+                            Frame::Bulk(Bytes::from(addr)),
+                            Frame::Bulk(Bytes::new()),
+                        ])
+                    })
+                    .collect();
+
+                Frame::Array(entries)
+            },
+            Slowlog::Len => Frame::Integer(db.slowlog_len() as i64),
+            Slowlog::Reset => {
+                db.slowlog_reset();
+                Frame::ok()
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回集合的所有成员，键不存在时返回空数组
+#[derive(Debug)]
+pub struct Smembers {
+    key: String,
+}
+
+/// `SMEMBERS key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
+impl Smembers {
+    /// 新建一条 `Smembers` 命令
+    pub fn new(key: impl ToString) -> Smembers {
+        Smembers {
+            key: key.to_string(),
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Smembers` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Smembers> {
+        let key = parse.next_string()?;
+
+        Ok(Smembers { key })
+    }
+
+    /// 查找集合的成员，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.smembers(&self.key) {
+            Ok(members) => {
+                let mut response = Frame::array();
+                for member in members {
+                    response.push_bulk(member);
+                }
+                response
+            },
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"smembers"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame
+    }
+}
@@ -1,15 +1,20 @@
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin};
 
 use bytes::Bytes;
 use tokio::{
     select,
     sync::broadcast,
+    time::{self, Instant},
 };
+use tracing::warn;
 use tokio_stream::{Stream, StreamExt, StreamMap};
 
 use crate::{
     Frame, Connection, Command, Db, Parse, ParseError, Shutdown,
-    cmd::Unknown,
+    cmd::ArgSpec,
+    connection::Draining,
+    db::{Congestion, Published},
+    server::Config,
 };
 
 /// 订阅一个或多个频道
@@ -18,6 +23,15 @@ use crate::{
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
+    /// 由 `PSUBSCRIBE` 进入订阅模式时订阅的模式
+    patterns: Vec<String>,
+}
+
+/// 按模式订阅频道，名称匹配任意一个模式的频道发送的消息都会收到
+/// 与 `SUBSCRIBE` 一样进入订阅模式
+#[derive(Debug)]
+pub struct Psubscribe {
+    patterns: Vec<String>,
 }
 
 /// 客户端取消某个或某几个频道的订阅
@@ -27,64 +41,136 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
+/// 客户端取消某个或某几个模式的订阅
+/// 若不指定模式，则取消所有现有的模式订阅
+#[derive(Debug)]
+pub struct Punsubscribe {
+    patterns: Vec<String>,
+}
+
+/// `SUBSCRIBE channel [channel ...]` 的参数
+pub(crate) const SUBSCRIBE_ARGS: &[ArgSpec] = &[ArgSpec::string("channel").multiple()];
+
+/// `UNSUBSCRIBE [channel ...]` 的参数
+pub(crate) const UNSUBSCRIBE_ARGS: &[ArgSpec] = &[ArgSpec::string("channel").optional().multiple()];
+
+/// `PSUBSCRIBE pattern [pattern ...]` 的参数
+pub(crate) const PSUBSCRIBE_ARGS: &[ArgSpec] = &[ArgSpec::pattern("pattern").multiple()];
+
+/// `PUNSUBSCRIBE [pattern ...]` 的参数
+pub(crate) const PUNSUBSCRIBE_ARGS: &[ArgSpec] = &[ArgSpec::pattern("pattern").optional().multiple()];
+
 /// 消息流
-/// `Messages` 是一个使用智能指针包装的且被固定的，以 `Bytes` 为产出的流
-type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+/// `Messages` 是一个使用智能指针包装的且被固定的，以 `Published` 为产出的流
+type Messages = Pin<Box<dyn Stream<Item = Published> + Send>>;
+
+/// 模式订阅的消息流，产出 (频道, 消息)
+type PatternMessages = Pin<Box<dyn Stream<Item = (String, Published)> + Send>>;
+
+/// 订阅的频道与模式，回复中的订阅数量为两者之和
+#[derive(Default)]
+struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+    patterns: StreamMap<String, PatternMessages>,
+    /// 每个订阅的频道上的积压标记，模式订阅不参与 `PUBLISH ... WAIT` 的等待
+    congestion: HashMap<String, Congestion>,
+    /// 待发送数据是否超过了软上限
+    congested: bool,
+}
 
 impl Subscribe {
     /// 根据指定的频道创建一个 `Subscribe` 命令
     pub(crate) fn new(channels: &[String]) -> Self {
         Subscribe {
             channels: channels.to_vec(),
+            patterns: vec![],
         }
     }
 
     /// 从 `Parse` 中解析出 `Subscribe` 命令，此时 `SUBSCRIBE` 头已被读取
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Subscribe> {
-        use ParseError::EndOfStream;
-
-        // 至少得订阅一个频道
-        let mut channels = vec![parse.next_string()?];
-
-        loop {
-            match parse.next_string() {
-                Ok(s) => channels.push(s),
-                Err(EndOfStream) => break,
-                Err(err) => return Err(err.into()),
-            }
-        }
-
-        Ok(Subscribe { channels })
-    } 
+        Ok(Subscribe::new(&parse_names(parse)?))
+    }
 
     /// 服务端收到请求后，建立连接？
-    pub(crate) async fn apply(mut self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
-        // 使用 StreamMap 保存订阅的频道
-        let mut subscriptions = StreamMap::new();
+    pub(crate) async fn apply(
+        mut self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+        config: &Config,
+    ) -> crate::Result<()> {
+        // 使用 StreamMap 保存订阅的频道与模式
+        let mut subscriptions = Subscriptions::default();
+        let limit = &config.pubsub_output_buffer_limit;
+        // 待发送数据开始超过软上限的时间
+        let mut over_soft_since: Option<Instant> = None;
 
         loop {
             // 消费掉 channels 条目，订阅频道，返回结果
             for channel in self.channels.drain(..) {
                 subscribe_to_channel(channel, &mut subscriptions, db, dst).await?;
             }
+            for pattern in self.patterns.drain(..) {
+                subscribe_to_pattern(pattern, &mut subscriptions, db, dst).await?;
+            }
+
+            // 积压超过软上限时标记订阅的频道，`PUBLISH ... WAIT` 的发布者随之等待，
+            // 不等待的发布者则继续发送，积压超过硬上限或持续超过软上限时断开
+            let over_soft = limit.is_over_soft(dst.pending_bytes());
+            subscriptions.set_congested(over_soft);
+
+            let soft_deadline = over_soft_since.map(|since| since + limit.soft_duration);
+            // 超过软上限时，积压降到软上限以下也需要返回，以便取消标记并重新计时
+            let low_water = over_soft.then_some(limit.soft);
 
             // select 等待下面几个事件
             select! {
-                // 订阅的频道有新消息
-                Some((channel, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel, msg)).await?;
+                // 订阅的频道有新消息，消息先放入队列，对端不读取时不会阻塞
+                Some((channel, msg)) = subscriptions.channels.next() => {
+                    let response = for_protocol(make_message_frame(channel, msg), dst);
+                    dst.queue_frame(&response)?;
+
+                    if limit.is_exceeded(dst.pending_bytes(), &mut over_soft_since) {
+                        return close_slow_subscriber(dst);
+                    }
+                },
+                // 订阅的模式匹配的频道有新消息，与频道消息一样受积压上限的限制
+                Some((pattern, (channel, msg))) = subscriptions.patterns.next() => {
+                    let response = for_protocol(make_pmessage_frame(pattern, channel, msg), dst);
+                    dst.queue_frame(&response)?;
+
+                    if limit.is_exceeded(dst.pending_bytes(), &mut over_soft_since) {
+                        return close_slow_subscriber(dst);
+                    }
+                },
+                // 持续超过软上限的时间已到，期间积压降到软上限以下则继续
+                _ = time::sleep_until(soft_deadline.unwrap_or_else(Instant::now)), if soft_deadline.is_some() => {
+                    if limit.is_exceeded(dst.pending_bytes(), &mut over_soft_since) {
+                        return close_slow_subscriber(dst);
+                    }
                 },
-                // 客户端发送了新的请求，或者连接断开
-                res = dst.read_frame() => {
+                // 客户端发送了新的请求，或者连接断开，等待期间发送队列中的消息
+                res = dst.read_frame_draining(low_water) => {
                     let frame = match res? {
-                        Some(frame) => frame,
-                        None => return Ok(()),
+                        Draining::Frame(Some(frame)) => frame,
+                        Draining::Frame(None) => return Ok(()),
+                        // 对端读走了积压的数据，不再超过软上限
+                        Draining::Drained => {
+                            over_soft_since = None;
+                            continue;
+                        },
                     };
 
-                    // 这里处理客户端发送的消息
-                    handle_command(frame, &mut self.channels, &mut subscriptions, dst).await?;
+                    // 这里处理客户端发送的消息，`RESET`/`QUIT` 时退出订阅模式
+                    if handle_command(frame, &mut self, &mut subscriptions, dst, config).await? {
+                        return Ok(());
+                    }
                 },
                 _ = shutdown.recv() => {
+                    // 关闭连接前告知客户端，以便与服务异常退出区分开
+                    let response = Frame::Error("ERR server shutting down".to_string());
+                    dst.write_frame(&response).await?;
                     return Ok(())
                 }
             }
@@ -103,16 +189,33 @@ impl Subscribe {
     }
 }
 
+/// 积压的数据超过上限，记录日志后断开订阅者的连接
+/// 积压的消息不再发送，返回后连接的处理循环退出并关闭 socket
+fn close_slow_subscriber(dst: &mut Connection) -> crate::Result<()> {
+    warn!(
+        peer = ?dst.peer_addr().ok(),
+        pending = dst.pending_bytes(),
+        "closing subscriber over output buffer limit"
+    );
+
+    dst.close_after_reply();
+
+    Ok(())
+}
+
 /// 订阅一个频道，并将接收消息的 stream 流放入 subscriptions 订阅列表里
 /// 若订阅成功，向客户端返回消息
 async fn subscribe_to_channel(
     channel: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscriptions: &mut Subscriptions,
     db: &Db,
     dst: &mut Connection
     ) -> crate::Result<()> {
     let mut rx = db.subscribe(channel.clone());
 
+    let mut congestion = rx.congestion();
+    congestion.set(subscriptions.congested);
+
     // 返回一个固定的，实现了 async/await 的 stream
     let rx = Box::pin(async_stream::stream! {
         loop {
@@ -124,81 +227,305 @@ async fn subscribe_to_channel(
         }
     });
 
-    subscriptions.insert(channel.clone(), rx);
+    subscriptions.channels.insert(channel.clone(), rx);
+    subscriptions.congestion.insert(channel.clone(), congestion);
+
+    let response = for_protocol(make_subscribe_frame("subscribe", channel, subscriptions.len()), dst);
+    dst.write_frame(&response).await?;
+
+    Ok(())
+}
+
+/// 订阅一个模式，并将接收消息的 stream 流放入模式订阅列表里
+/// 若订阅成功，向客户端返回消息
+async fn subscribe_to_pattern(
+    pattern: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection
+    ) -> crate::Result<()> {
+    let mut rx = db.psubscribe(pattern.clone());
+
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield msg,
+                Err(broadcast::error::RecvError::Lagged(_)) => {},
+                Err(_) => break,
+            }
+        }
+    });
+
+    subscriptions.patterns.insert(pattern.clone(), rx);
 
-    let response = make_subscribe_frame(channel, subscriptions.len());
+    let response = for_protocol(make_subscribe_frame("psubscribe", pattern, subscriptions.len()), dst);
     dst.write_frame(&response).await?;
 
     Ok(())
 }
 
-/// 订阅后，服务端返回的消息
-fn make_subscribe_frame(channel: String, sub_nums: usize) -> Frame {
+/// 订阅后，服务端返回的消息，`kind` 为 `subscribe` 或 `psubscribe`
+fn make_subscribe_frame(kind: &'static str, channel: String, sub_nums: usize) -> Frame {
     let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"subscribe"));
+    response.push_bulk(Bytes::from_static(kind.as_bytes()));
     response.push_bulk(Bytes::from(channel));
-    response.push_int(sub_nums as u64);
+    response.push_int(sub_nums as i64);
 
     response
 }
 
-/// 取消订阅后，服务端返回的消息
+/// 取消订阅后，服务端返回的消息，`kind` 为 `unsubscribe` 或 `punsubscribe`
 /// 取消订阅的频道名，和当前订阅的数量
-fn make_unsubscribe_frame(channel: String, sub_nums: usize) -> Frame {
+/// 没有任何订阅时取消全部订阅，频道名为 `nil`
+fn make_unsubscribe_frame(kind: &'static str, channel: Option<String>, sub_nums: usize) -> Frame {
+    let channel = match channel {
+        Some(channel) => Frame::Bulk(Bytes::from(channel)),
+        None => Frame::Null,
+    };
+
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from_static(kind.as_bytes())),
+        channel,
+        Frame::Integer(sub_nums as i64),
+    ])
+}
+
+/// 从订阅频道的消息生成 frame
+/// 开启消息序号时，序号作为非标准的第 4 项附加在最后
+fn make_message_frame(channel: String, msg: Published) -> Frame {
     let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"unsubscribe"));
+    response.push_bulk(Bytes::from_static(b"message"));
     response.push_bulk(Bytes::from(channel));
-    response.push_int(sub_nums as u64);
+    response.push_bulk(msg.content);
+    if let Some(seq) = msg.seq {
+        response.push_int(seq as i64);
+    }
 
     response
 }
 
-/// 从订阅频道的消息生成 frame
-fn make_message_frame(channel: String, msg: Bytes) -> Frame {
+/// 从模式订阅的消息生成 frame，包括匹配的模式和实际的频道
+/// 与频道消息相同，开启消息序号时附加在最后
+fn make_pmessage_frame(pattern: String, channel: String, msg: Published) -> Frame {
     let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"message"));
+    response.push_bulk(Bytes::from_static(b"pmessage"));
+    response.push_bulk(Bytes::from(pattern));
     response.push_bulk(Bytes::from(channel));
-    response.push_bulk(msg);
+    response.push_bulk(msg.content);
+    if let Some(seq) = msg.seq {
+        response.push_int(seq as i64);
+    }
 
     response
 }
 
-/// 处理客户端发送的命令
+/// RESP3 连接使用 push 类型发送订阅相关的消息，RESP2 连接则保持数组
+fn for_protocol(response: Frame, dst: &Connection) -> Frame {
+    match response {
+        Frame::Array(parts) if dst.protocol() == 3 => Frame::Push(parts),
+        response => response,
+    }
+}
+
+/// 处理客户端发送的命令，需要退出订阅模式时返回 `true`
+/// 只允许执行命令表中标记了订阅模式的命令，其它已知命令返回错误
 async fn handle_command(
     frame: Frame,
-    channels: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
-    dst: &mut Connection
-    ) -> crate::Result<()> {
-    match Command::from_frame(frame)? {
+    pending: &mut Subscribe,
+    subscriptions: &mut Subscriptions,
+    dst: &mut Connection,
+    config: &Config,
+    ) -> crate::Result<bool> {
+    let command = match Command::from_frame(frame) {
+        Ok(command) => command,
+        Err(err) => {
+            let response = Frame::Error(err.to_string());
+            dst.write_frame(&response).await?;
+            return Ok(false);
+        },
+    };
+
+    if !command.allowed_in_subscribe() && !matches!(command, Command::Unknown(_)) {
+        let response = Frame::Error(format!(
+            "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+            command.get_name()
+        ));
+        dst.write_frame(&response).await?;
+        return Ok(false);
+    }
+
+    match command {
         Command::Subscribe(subscribe) => {
             // vec.extend(append) 使用迭代器的内容扩展集合
-            channels.extend(subscribe.channels.into_iter());
+            pending.channels.extend(subscribe.channels);
         },
-        Command::Unsubscribe(mut unsubscribe) => {
-            // 若未指定 channels 则清空所有现有订阅
-            if unsubscribe.channels.is_empty() {
-                unsubscribe.channels = subscriptions
-                    .keys()
-                    .map(|channel| channel.to_string())
-                    .collect();
-            }
-
-            for channel in unsubscribe.channels {
-                subscriptions.remove(&channel);
-
-                let response = make_unsubscribe_frame(channel, subscriptions.len());
-                dst.write_frame(&response).await?;
-            }
+        Command::Psubscribe(psubscribe) => {
+            pending.patterns.extend(psubscribe.patterns);
         },
-        command => {
-            let cmd = Unknown::new(command.get_name());
-            cmd.apply(dst).await?;
+        Command::Unsubscribe(unsubscribe) => {
+            unsubscribe_from_channels(unsubscribe.channels, subscriptions, dst).await?;
         },
+        Command::Punsubscribe(punsubscribe) => {
+            unsubscribe_from_patterns(punsubscribe.patterns, subscriptions, dst).await?;
+        },
+        Command::Ping(ping) => ping.apply_subscribed(dst).await?,
+        Command::Quit(quit) => {
+            quit.apply(dst).await?;
+            return Ok(true);
+        },
+        // 退出订阅模式后所有订阅随 `subscriptions` 一起被丢弃
+        Command::Reset(reset) => {
+            reset.apply(dst).await?;
+            return Ok(true);
+        },
+        Command::Unknown(unknown) => unknown.apply(config.unknown_command, dst).await?,
+        command => unreachable!("{} is not allowed in subscribe mode", command.get_name()),
+    }
+    Ok(false)
+}
+
+/// 取消对 `channels` 的订阅，每个频道回复一条消息
+/// 若未指定 channels 则清空所有现有订阅，此时若没有任何订阅，回复一条频道名为 `nil` 的消息
+async fn unsubscribe_from_channels(
+    mut channels: Vec<String>,
+    subscriptions: &mut Subscriptions,
+    dst: &mut Connection,
+    ) -> crate::Result<()> {
+    if channels.is_empty() {
+        if subscriptions.channels.is_empty() {
+            let response = for_protocol(make_unsubscribe_frame("unsubscribe", None, subscriptions.len()), dst);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        channels = subscriptions
+            .channels
+            .keys()
+            .map(|channel| channel.to_string())
+            .collect();
+    }
+
+    for channel in channels {
+        subscriptions.channels.remove(&channel);
+        subscriptions.congestion.remove(&channel);
+
+        let response = for_protocol(make_unsubscribe_frame("unsubscribe", Some(channel), subscriptions.len()), dst);
+        dst.write_frame(&response).await?;
+    }
+
+    Ok(())
+}
+
+/// 取消对 `patterns` 的订阅，每个模式回复一条消息，规则与取消频道订阅相同
+async fn unsubscribe_from_patterns(
+    mut patterns: Vec<String>,
+    subscriptions: &mut Subscriptions,
+    dst: &mut Connection,
+    ) -> crate::Result<()> {
+    if patterns.is_empty() {
+        if subscriptions.patterns.is_empty() {
+            let response = for_protocol(make_unsubscribe_frame("punsubscribe", None, subscriptions.len()), dst);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        patterns = subscriptions
+            .patterns
+            .keys()
+            .map(|pattern| pattern.to_string())
+            .collect();
     }
+
+    for pattern in patterns {
+        subscriptions.patterns.remove(&pattern);
+
+        let response = for_protocol(make_unsubscribe_frame("punsubscribe", Some(pattern), subscriptions.len()), dst);
+        dst.write_frame(&response).await?;
+    }
+
     Ok(())
 }
 
+/// 读取一个或多个频道名或模式
+/// 同一条命令中重复的名称只订阅一次，保留第一次出现的顺序
+fn parse_names(parse: &mut Parse) -> crate::Result<Vec<String>> {
+    use ParseError::EndOfStream;
+
+    // 至少得订阅一个
+    let mut names = vec![parse.next_string()?];
+
+    loop {
+        match parse.next_string() {
+            Ok(s) if names.contains(&s) => {},
+            Ok(s) => names.push(s),
+            Err(EndOfStream) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(names)
+}
+
+impl Subscriptions {
+    /// 订阅的频道与模式的总数
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// 标记或取消标记所有订阅的频道
+    fn set_congested(&mut self, congested: bool) {
+        if self.congested == congested {
+            return;
+        }
+        self.congested = congested;
+
+        for congestion in self.congestion.values_mut() {
+            congestion.set(congested);
+        }
+    }
+}
+
+impl Psubscribe {
+    /// 根据指定的模式创建一个 `Psubscribe` 命令
+    pub(crate) fn new(patterns: &[String]) -> Self {
+        Psubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Psubscribe` 命令，此时 `PSUBSCRIBE` 头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Psubscribe> {
+        Ok(Psubscribe::new(&parse_names(parse)?))
+    }
+
+    /// 订阅模式并进入订阅模式，之后的处理与 `SUBSCRIBE` 相同
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+        config: &Config,
+    ) -> crate::Result<()> {
+        let subscribe = Subscribe {
+            channels: vec![],
+            patterns: self.patterns,
+        };
+
+        subscribe.apply(db, dst, shutdown, config).await
+    }
+
+    /// 客户端发送请求前转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"psubscribe"));
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+
+        frame
+    }
+}
+
 impl Unsubscribe {
     /// 使用给定的 `channels` 创建一个 `Unsubscribe` 命令
     pub(crate) fn new(channels: &[String]) -> Self {
@@ -224,6 +551,12 @@ impl Unsubscribe {
         Ok(Unsubscribe { channels })
     }
 
+    /// 未进入订阅模式时收到 `UNSUBSCRIBE`，此时没有任何订阅，
+    /// 与 Redis 一样逐个频道回复订阅数量 0，不指定频道时回复 `nil` 频道
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        unsubscribe_from_channels(self.channels, &mut Subscriptions::default(), dst).await
+    }
+
     /// 客户端发送请求前将命令转换为 `Frame`
     pub(crate) fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
@@ -236,3 +569,46 @@ impl Unsubscribe {
         frame
     }
 }
+
+impl Punsubscribe {
+    /// 使用给定的 `patterns` 创建一个 `Punsubscribe` 命令
+    pub(crate) fn new(patterns: &[String]) -> Self {
+        Punsubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// 命令头已被读取，继续读取模式列表并生成 `Punsubscribe` 命令
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Punsubscribe, ParseError> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Punsubscribe { patterns })
+    }
+
+    /// 未进入订阅模式时收到 `PUNSUBSCRIBE`，与 `UNSUBSCRIBE` 一样回复订阅数量 0
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        unsubscribe_from_patterns(self.patterns, &mut Subscriptions::default(), dst).await
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"punsubscribe"));
+
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+
+        frame
+    }
+}
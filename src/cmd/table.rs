@@ -0,0 +1,265 @@
+//! 命令元数据表
+//!
+//! 集中记录每条命令的名称、参数个数及读写等属性，
+//! 供服务端及使用本 crate 的代理、访问控制等在执行命令前检查
+//! 每条命令的参数描述在各自的模块中声明，由 `COMMAND DOCS` 返回
+use super::*;
+
+/// 一条命令的元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandInfo {
+    /// 小写的命令名
+    pub name: &'static str,
+    /// 参数个数（包括命令名），负数 `-n` 表示至少 `n` 个
+    pub arity: i32,
+    /// 命令是否会修改数据
+    pub write: bool,
+    /// 连接未认证时是否也能执行
+    pub no_auth: bool,
+    /// 订阅模式下是否允许执行
+    pub subscribe_context: bool,
+    /// 参数描述，不包括命令名
+    pub args: &'static [ArgSpec],
+}
+
+/// 一个参数的描述，供 `redis-cli` 提示及图形化工具使用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgSpec {
+    /// 参数名
+    pub name: &'static str,
+    /// 参数的类型
+    pub kind: ArgType,
+    /// 参数前的关键字，如 `SET` 的 `EX`
+    pub token: Option<&'static str>,
+    /// 是否可以省略
+    pub optional: bool,
+    /// 是否可以重复多次
+    pub multiple: bool,
+}
+
+/// 参数的类型，名称与 Redis 的 `COMMAND DOCS` 相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgType {
+    Key,
+    String,
+    Integer,
+    Double,
+    Pattern,
+    /// 只有关键字本身，如 `WITHSCORES`
+    PureToken,
+    /// 多个参数中选择一个
+    OneOf(&'static [ArgSpec]),
+    /// 多个参数组成的整体，如 `MSET` 的 键/值
+    Block(&'static [ArgSpec]),
+}
+
+impl CommandInfo {
+    const fn read(name: &'static str, arity: i32) -> CommandInfo {
+        CommandInfo { name, arity, write: false, no_auth: false, subscribe_context: false, args: &[] }
+    }
+
+    const fn write(name: &'static str, arity: i32) -> CommandInfo {
+        CommandInfo { write: true, ..CommandInfo::read(name, arity) }
+    }
+
+    const fn no_auth(name: &'static str, arity: i32) -> CommandInfo {
+        CommandInfo { no_auth: true, ..CommandInfo::read(name, arity) }
+    }
+
+    /// 标记为订阅模式下允许执行
+    const fn subscribe_context(self) -> CommandInfo {
+        CommandInfo { subscribe_context: true, ..self }
+    }
+
+    /// 参数个数（包括命令名）为 `argc` 时是否符合 `arity`
+    pub fn check_arity(&self, argc: usize) -> bool {
+        if self.arity >= 0 {
+            argc == self.arity as usize
+        } else {
+            argc >= self.arity.unsigned_abs() as usize
+        }
+    }
+
+    /// 设置参数描述
+    const fn args(self, args: &'static [ArgSpec]) -> CommandInfo {
+        CommandInfo { args, ..self }
+    }
+}
+
+impl ArgSpec {
+    const fn new(name: &'static str, kind: ArgType) -> ArgSpec {
+        ArgSpec { name, kind, token: None, optional: false, multiple: false }
+    }
+
+    pub(crate) const fn key(name: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::Key)
+    }
+
+    pub(crate) const fn string(name: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::String)
+    }
+
+    pub(crate) const fn integer(name: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::Integer)
+    }
+
+    pub(crate) const fn double(name: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::Double)
+    }
+
+    pub(crate) const fn pattern(name: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::Pattern)
+    }
+
+    /// 只有关键字的参数，如 `WITHSCORES`
+    pub(crate) const fn pure_token(name: &'static str, token: &'static str) -> ArgSpec {
+        ArgSpec::new(name, ArgType::PureToken).token(token)
+    }
+
+    pub(crate) const fn one_of(name: &'static str, args: &'static [ArgSpec]) -> ArgSpec {
+        ArgSpec::new(name, ArgType::OneOf(args))
+    }
+
+    pub(crate) const fn block(name: &'static str, args: &'static [ArgSpec]) -> ArgSpec {
+        ArgSpec::new(name, ArgType::Block(args))
+    }
+
+    /// 设置参数前的关键字
+    pub(crate) const fn token(self, token: &'static str) -> ArgSpec {
+        ArgSpec { token: Some(token), ..self }
+    }
+
+    /// 标记为可以省略
+    pub(crate) const fn optional(self) -> ArgSpec {
+        ArgSpec { optional: true, ..self }
+    }
+
+    /// 标记为可以重复多次
+    pub(crate) const fn multiple(self) -> ArgSpec {
+        ArgSpec { multiple: true, ..self }
+    }
+}
+
+impl ArgType {
+    /// `COMMAND DOCS` 中的类型名
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArgType::Key => "key",
+            ArgType::String => "string",
+            ArgType::Integer => "integer",
+            ArgType::Double => "double",
+            ArgType::Pattern => "pattern",
+            ArgType::PureToken => "pure-token",
+            ArgType::OneOf(_) => "oneof",
+            ArgType::Block(_) => "block",
+        }
+    }
+}
+
+/// 服务端支持的所有命令，新增命令时需在此添加一项
+/// 按命令名排序，查找时使用二分查找
+pub(crate) const COMMAND_TABLE: &[CommandInfo] = &[
+    CommandInfo::write("append", 3).args(append::ARGS),
+    CommandInfo::no_auth("auth", -2).args(auth::ARGS),
+    CommandInfo::read("command", -2).args(command::ARGS),
+    CommandInfo::read("config", -2).args(config::ARGS),
+    CommandInfo::write("copy", -3).args(copy::ARGS),
+    CommandInfo::read("debug", -2).args(debug::ARGS),
+    CommandInfo::write("decr", 2).args(incr::ARGS),
+    CommandInfo::write("del", -2).args(del::ARGS),
+    CommandInfo::write("delifeq", 3).args(delifeq::ARGS),
+    CommandInfo::write("eval", -3).args(script::EVAL_ARGS),
+    CommandInfo::write("evalsha", -3).args(script::EVALSHA_ARGS),
+    CommandInfo::write("expire", 3).args(expire::ARGS),
+    CommandInfo::write("geoadd", -5).args(geoadd::ARGS),
+    CommandInfo::read("geodist", -4).args(geodist::ARGS),
+    CommandInfo::read("get", 2).args(get::ARGS),
+    CommandInfo::read("getrange", 4).args(getrange::ARGS),
+    CommandInfo::write("getset", 3).args(getset::ARGS),
+    CommandInfo::no_auth("hello", -1).args(hello::ARGS),
+    CommandInfo::read("hget", 3).args(hget::ARGS),
+    CommandInfo::write("hset", -4).args(hset::ARGS),
+    CommandInfo::write("incr", 2).args(incr::ARGS),
+    CommandInfo::read("keys", 2).args(keys::ARGS),
+    CommandInfo::read("lastsave", 1),
+    CommandInfo::write("lpop", -2).args(pop::ARGS),
+    CommandInfo::write("lpush", -3).args(push::ARGS),
+    CommandInfo::read("memory", -2).args(memory::ARGS),
+    CommandInfo::write("mset", -3).args(mset::ARGS),
+    CommandInfo::read("object", -2).args(object::ARGS),
+    CommandInfo::write("persist", 2).args(persist::ARGS),
+    CommandInfo::write("pexpire", 3).args(expire::PEXPIRE_ARGS),
+    CommandInfo::write("pfadd", -2).args(pfadd::ARGS),
+    CommandInfo::read("pfcount", -2).args(pfcount::ARGS),
+    CommandInfo::no_auth("ping", -1).subscribe_context().args(ping::ARGS),
+    CommandInfo::read("psubscribe", -2).subscribe_context().args(subscribe::PSUBSCRIBE_ARGS),
+    CommandInfo::read("pttl", 2).args(ttl::ARGS),
+    CommandInfo::read("publish", -3).args(publish::ARGS),
+    CommandInfo::read("punsubscribe", -1).subscribe_context().args(subscribe::PUNSUBSCRIBE_ARGS),
+    CommandInfo::no_auth("quit", -1).subscribe_context(),
+    CommandInfo::write("rename", 3).args(rename::ARGS),
+    CommandInfo::no_auth("reset", 1).subscribe_context(),
+    CommandInfo::write("rpop", -2).args(pop::ARGS),
+    CommandInfo::write("rpush", -3).args(push::ARGS),
+    CommandInfo::write("sadd", -3).args(sadd::ARGS),
+    CommandInfo::read("save", 1),
+    CommandInfo::read("scan", -2).args(scan::ARGS),
+    CommandInfo::read("script", -2).args(script::SCRIPT_ARGS),
+    CommandInfo::read("sdiff", -2).args(set_algebra::ARGS),
+    CommandInfo::write("sdiffstore", -3).args(set_algebra::STORE_ARGS),
+    CommandInfo::write("set", -3).args(set::ARGS),
+    CommandInfo::write("setnx", 3).args(setnx::ARGS),
+    CommandInfo::read("sinter", -2).args(set_algebra::ARGS),
+    CommandInfo::read("sintercard", -3).args(sintercard::ARGS),
+    CommandInfo::write("sinterstore", -3).args(set_algebra::STORE_ARGS),
+    CommandInfo::read("slowlog", -2).args(slowlog::ARGS),
+    CommandInfo::read("smembers", 2).args(smembers::ARGS),
+    CommandInfo::read("subscribe", -2).subscribe_context().args(subscribe::SUBSCRIBE_ARGS),
+    CommandInfo::read("substr", 4).args(getrange::ARGS),
+    CommandInfo::read("sunion", -2).args(set_algebra::ARGS),
+    CommandInfo::write("sunionstore", -3).args(set_algebra::STORE_ARGS),
+    CommandInfo::read("ttl", 2).args(ttl::ARGS),
+    CommandInfo::read("type", 2).args(r#type::ARGS),
+    CommandInfo::read("unsubscribe", -1).subscribe_context().args(subscribe::UNSUBSCRIBE_ARGS),
+    CommandInfo::read("waitaof", 4).args(waitaof::ARGS),
+    CommandInfo::write("zadd", -4).args(zadd::ARGS),
+    CommandInfo::write("zincrby", 4).args(zincrby::ARGS),
+    CommandInfo::read("zrangebyscore", -4).args(zrangebyscore::ARGS),
+    CommandInfo::read("zrank", 3).args(zrank::ARGS),
+    CommandInfo::write("zrem", -3).args(zrem::ARGS),
+];
+
+/// 按命令名查找元数据，命令名不区分大小写
+pub fn lookup(name: &str) -> Option<&'static CommandInfo> {
+    lookup_bytes(name.as_bytes())
+}
+
+/// 按字节形式的命令名查找元数据，解析命令时直接使用 frame 中的字节，无需先转换为 `String`
+pub(crate) fn lookup_bytes(name: &[u8]) -> Option<&'static CommandInfo> {
+    COMMAND_TABLE
+        .binary_search_by(|info| info.name.bytes().cmp(name.iter().map(u8::to_ascii_lowercase)))
+        .ok()
+        .map(|index| &COMMAND_TABLE[index])
+}
+
+impl Command {
+    /// 命令的元数据，未知命令返回 `None`
+    pub fn info(&self) -> Option<&'static CommandInfo> {
+        lookup(self.get_name())
+    }
+
+    /// 命令是否会修改数据，可用于实现只读模式等
+    pub fn is_write(&self) -> bool {
+        self.info().is_some_and(|info| info.write)
+    }
+
+    /// 订阅模式下是否允许执行此命令
+    pub fn allowed_in_subscribe(&self) -> bool {
+        self.info().is_some_and(|info| info.subscribe_context)
+    }
+
+    /// 连接是否需要先认证才能执行此命令，未知命令总是需要认证
+    pub fn requires_auth(&self) -> bool {
+        !self.info().is_some_and(|info| info.no_auth)
+    }
+}
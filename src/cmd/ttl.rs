@@ -0,0 +1,92 @@
+use bytes::Bytes;
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, KvStore, Parse, cmd::ArgSpec};
+
+/// 返回键剩余的有效期
+/// `TTL key` 以秒为单位（四舍五入），`PTTL key` 以毫秒为单位
+/// 键不存在时返回 -2，键没有有效期时返回 -1
+#[derive(Debug)]
+pub struct Ttl {
+    key: String,
+    /// `PTTL` 为 `true`
+    millis: bool,
+}
+
+/// `TTL key`/`PTTL key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
+impl Ttl {
+    /// 新建一条 `TTL` 命令
+    pub fn new(key: impl ToString) -> Ttl {
+        Ttl {
+            key: key.to_string(),
+            millis: false,
+        }
+    }
+
+    /// 新建一条 `PTTL` 命令
+    pub fn pttl(key: impl ToString) -> Ttl {
+        Ttl {
+            key: key.to_string(),
+            millis: true,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Ttl` 命令，命令头已被读取
+    /// `millis` 由命令名决定，`PTTL` 为 `true`
+    pub(crate) fn parse_frames(millis: bool, parse: &mut Parse) -> crate::Result<Ttl> {
+        let key = parse.next_string()?;
+
+        Ok(Ttl { key, millis })
+    }
+
+    /// 返回命令的名称
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.millis {
+            "pttl"
+        } else {
+            "ttl"
+        }
+    }
+
+    /// 从存储中查找键的过期时刻，返回回复的 `Frame`
+    pub fn execute(self, store: &impl KvStore) -> Frame {
+        let remaining = match store.ttl(&self.key) {
+            None => return Frame::Integer(-2),
+            Some(None) => return Frame::Integer(-1),
+            // 已过期但尚未被清理的键视为剩余 0
+            Some(Some(expires_at)) => expires_at.saturating_duration_since(Instant::now()),
+        };
+
+        let millis = remaining.as_millis().min(i64::MAX as u128) as i64;
+        if self.millis {
+            Frame::Integer(millis)
+        } else {
+            // 与 Redis 相同，四舍五入到秒
+            Frame::Integer(millis.saturating_add(500) / 1000)
+        }
+    }
+
+    /// 查找键的有效期，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &impl KvStore, dst: &mut Connection) -> crate::Result<()> {
+        let response = self.execute(db);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(self.get_name().as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame
+    }
+}
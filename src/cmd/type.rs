@@ -0,0 +1,48 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回键存储的值的类型，如 `string`、`set`，键不存在时返回 `none`
+#[derive(Debug)]
+pub struct Type {
+    key: String,
+}
+
+/// `TYPE key` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key")];
+
+impl Type {
+    /// 新建一条 `Type` 命令
+    pub fn new(key: impl ToString) -> Type {
+        Type { key: key.to_string() }
+    }
+
+    /// 从 `Parse` 中解析出 `Type` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Type> {
+        let key = parse.next_string()?;
+
+        Ok(Type { key })
+    }
+
+    /// 查询键的类型，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple(db.key_type(&self.key).to_string());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"type"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame
+    }
+}
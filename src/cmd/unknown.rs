@@ -1,6 +1,6 @@
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
-use crate::{Frame, Connection};
+use crate::{Frame, Connection, server::UnknownCommandMode};
 
 #[derive(Debug)]
 pub struct Unknown {
@@ -9,9 +9,9 @@ pub struct Unknown {
 
 impl Unknown {
     /// 从字符中生成一个 `Unknown` 命令
-    pub(crate) fn new(command: impl ToString) -> Self {
+    pub(crate) fn new(command: impl Into<String>) -> Self {
         Unknown {
-            command: command.to_string(),
+            command: command.into(),
         }
     }
 
@@ -21,8 +21,14 @@ impl Unknown {
     }
 
     /// 生成 `Unknown` 错误消息，并发送至客户端
+    /// `Lenient` 模式下只记录日志，不发送任何内容
     #[instrument(skip(self, dst))]
-    pub(crate) async fn apply(&self, dst: &mut Connection) -> crate::Result<()> {
+    pub(crate) async fn apply(&self, mode: UnknownCommandMode, dst: &mut Connection) -> crate::Result<()> {
+        if mode == UnknownCommandMode::Lenient {
+            warn!(command = %self.command, "ignoring unknown command");
+            return Ok(());
+        }
+
         let response = Frame::Error(format!("Err: unknown command '{}'", self.command));
 
         debug!(?response);
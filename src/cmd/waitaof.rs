@@ -0,0 +1,42 @@
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Parse, cmd::ArgSpec};
+
+/// 等待之前的写入被追加到 AOF 并 fsync
+/// mini-redis 没有 AOF 也没有副本，因此总是立即返回 `[0, 0]`，
+/// 即本地与副本都没有完成 fsync；要求本地 fsync 时与未开启 AOF 的 Redis 一样返回错误
+#[derive(Debug)]
+pub struct Waitaof {
+    numlocal: u64,
+}
+
+/// `WAITAOF numlocal numreplicas timeout` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::integer("numlocal"), ArgSpec::integer("numreplicas"), ArgSpec::integer("timeout")];
+
+impl Waitaof {
+    /// 从 `Parse` 中解析出 `Waitaof` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Waitaof> {
+        let numlocal = parse.next_int()?;
+        // 没有副本，无需等待，副本数量与超时时间只检查格式
+        parse.next_int()?;
+        parse.next_int()?;
+
+        Ok(Waitaof { numlocal })
+    }
+
+    /// 返回 fsync 的状态
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.numlocal > 0 {
+            Frame::Error("ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled.".to_string())
+        } else {
+            Frame::Array(vec![Frame::Integer(0), Frame::Integer(0)])
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, sorted_set, cmd::ArgSpec};
+
+/// 向有序集合中添加成员或更新已有成员的分值，返回新添加的成员数量
+/// `ZADD key score member [score member ...]`
+#[derive(Debug)]
+pub struct Zadd {
+    key: String,
+    members: Vec<(f64, Bytes)>,
+}
+
+/// `ZADD key score member [score member ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::block("data", &[ArgSpec::double("score"), ArgSpec::string("member")]).multiple()];
+
+impl Zadd {
+    /// 新建一条 `Zadd` 命令
+    pub fn new(key: impl ToString, members: Vec<(f64, Bytes)>) -> Zadd {
+        Zadd {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Zadd` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Zadd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+        let mut members = vec![];
+
+        // 至少得有一对 分值/成员
+        loop {
+            let score = match parse.next_string() {
+                Ok(score) => score,
+                Err(EndOfStream) if !members.is_empty() => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            let score = sorted_set::parse_score(&score).ok_or("ERR value is not a valid float")?;
+            let member = parse.next_bytes()?;
+
+            members.push((score, member));
+        }
+
+        Ok(Zadd { key, members })
+    }
+
+    /// 向数据库中的有序集合添加成员，并返回新添加的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zadd(self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"zadd"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for (score, member) in self.members {
+            frame.push_bulk(sorted_set::format_score(score));
+            frame.push_bulk(member);
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, sorted_set, cmd::ArgSpec};
+
+/// 为有序集合中成员的分值加上 `increment`，返回新的分值
+/// `ZINCRBY key increment member`，键或成员不存在时视其分值为 0
+#[derive(Debug)]
+pub struct Zincrby {
+    key: String,
+    increment: f64,
+    member: Bytes,
+}
+
+/// `ZINCRBY key increment member` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::double("increment"), ArgSpec::string("member")];
+
+impl Zincrby {
+    /// 新建一条 `Zincrby` 命令
+    pub fn new(key: impl ToString, increment: f64, member: Bytes) -> Zincrby {
+        Zincrby {
+            key: key.to_string(),
+            increment,
+            member,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Zincrby` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Zincrby> {
+        let key = parse.next_string()?;
+        let increment = sorted_set::parse_score(&parse.next_string()?)
+            .ok_or("ERR value is not a valid float")?;
+        let member = parse.next_bytes()?;
+
+        Ok(Zincrby { key, increment, member })
+    }
+
+    /// 修改成员的分值，并将新的分值写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zincrby(self.key, self.increment, self.member) {
+            Ok(score) => Frame::Bulk(sorted_set::format_score(score)),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"zincrby"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(sorted_set::format_score(self.increment));
+        frame.push_bulk(self.member);
+
+        frame
+    }
+}
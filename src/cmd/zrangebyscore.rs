@@ -0,0 +1,144 @@
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, sorted_set, cmd::ArgSpec};
+
+/// 按分值从小到大返回分值在 `min` 与 `max` 之间的成员
+/// `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]`
+///
+/// `min`/`max` 支持 `-inf`/`+inf`，以 `(` 开头表示不包含该分值，如 `(5`
+#[derive(Debug)]
+pub struct Zrangebyscore {
+    key: String,
+    min: Bound<f64>,
+    max: Bound<f64>,
+    with_scores: bool,
+    limit: Option<(u64, i64)>,
+}
+
+/// `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[
+    ArgSpec::key("key"),
+    ArgSpec::double("min"),
+    ArgSpec::double("max"),
+    ArgSpec::pure_token("withscores", "WITHSCORES").optional(),
+    ArgSpec::block("limit", &[ArgSpec::integer("offset"), ArgSpec::integer("count")]).token("LIMIT").optional(),
+];
+
+impl Zrangebyscore {
+    /// 新建一条 `Zrangebyscore` 命令
+    /// `limit` 为 (偏移量, 数量)，数量为负数时返回偏移量之后的所有成员
+    pub fn new(
+        key: impl ToString,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        with_scores: bool,
+        limit: Option<(u64, i64)>,
+    ) -> Zrangebyscore {
+        Zrangebyscore {
+            key: key.to_string(),
+            min,
+            max,
+            with_scores,
+            limit,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Zrangebyscore` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Zrangebyscore> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+        let min = parse_bound(&parse.next_string()?)?;
+        let max = parse_bound(&parse.next_string()?)?;
+
+        let mut with_scores = false;
+        let mut limit = None;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "WITHSCORES" => with_scores = true,
+                Ok(s) if s.to_uppercase() == "LIMIT" => {
+                    let offset = parse.next_int()?;
+                    let count = parse.next_signed_int()?;
+                    limit = Some((offset, count));
+                },
+                Ok(_) => return Err("ERR syntax error".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Zrangebyscore { key, min, max, with_scores, limit })
+    }
+
+    /// 查找有序集合中的成员，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let limit = self.limit.map(|(offset, count)| {
+            (offset as usize, usize::try_from(count).ok())
+        });
+
+        let response = match db.zrangebyscore(&self.key, self.min, self.max, limit) {
+            Ok(members) => {
+                let mut response = Frame::array();
+                for (member, score) in members {
+                    response.push_bulk(member);
+                    if self.with_scores {
+                        response.push_bulk(sorted_set::format_score(score));
+                    }
+                }
+                response
+            },
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"zrangebyscore"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(format_bound(self.min, "-inf"));
+        frame.push_bulk(format_bound(self.max, "+inf"));
+
+        if self.with_scores {
+            frame.push_bulk(Bytes::from_static(b"withscores"));
+        }
+
+        if let Some((offset, count)) = self.limit {
+            frame.push_bulk(Bytes::from_static(b"limit"));
+            frame.push_bulk(Bytes::from(offset.to_string()));
+            frame.push_bulk(Bytes::from(count.to_string()));
+        }
+
+        frame
+    }
+}
+
+/// 解析分值的边界，以 `(` 开头表示不包含该分值
+fn parse_bound(src: &str) -> crate::Result<Bound<f64>> {
+    const MSG: &str = "ERR min or max is not a float";
+
+    match src.strip_prefix('(') {
+        Some(score) => Ok(Bound::Excluded(sorted_set::parse_score(score).ok_or(MSG)?)),
+        None => Ok(Bound::Included(sorted_set::parse_score(src).ok_or(MSG)?)),
+    }
+}
+
+/// 将分值的边界转换为命令参数，`Unbounded` 使用 `unbounded` 表示
+fn format_bound(bound: Bound<f64>, unbounded: &'static str) -> Bytes {
+    match bound {
+        Bound::Included(score) => sorted_set::format_score(score),
+        Bound::Excluded(score) => Bytes::from(format!("({}", score)),
+        Bound::Unbounded => Bytes::from_static(unbounded.as_bytes()),
+    }
+}
@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, cmd::ArgSpec};
+
+/// 返回成员在有序集合中按分值从小到大的排名（从 0 开始）
+/// 键或成员不存在时返回 `Null`
+#[derive(Debug)]
+pub struct Zrank {
+    key: String,
+    member: Bytes,
+}
+
+/// `ZRANK key member` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("member")];
+
+impl Zrank {
+    /// 新建一条 `Zrank` 命令
+    pub fn new(key: impl ToString, member: Bytes) -> Zrank {
+        Zrank {
+            key: key.to_string(),
+            member,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Zrank` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Zrank> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+
+        Ok(Zrank { key, member })
+    }
+
+    /// 查找成员的排名，并写入客户端的连接
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrank(&self.key, &self.member) {
+            Ok(Some(rank)) => Frame::Integer(rank as i64),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"zrank"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.member);
+
+        frame
+    }
+}
@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{Frame, Connection, Db, Parse, ParseError, cmd::ArgSpec};
+
+/// 从有序集合中删除一个或多个成员，返回实际删除的成员数量
+/// `ZREM key member [member ...]`
+#[derive(Debug)]
+pub struct Zrem {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+/// `ZREM key member [member ...]` 的参数
+pub(crate) const ARGS: &[ArgSpec] = &[ArgSpec::key("key"), ArgSpec::string("member").multiple()];
+
+impl Zrem {
+    /// 新建一条 `Zrem` 命令
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> Zrem {
+        Zrem {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// 从 `Parse` 中解析出 `Zrem` 命令，命令头已被读取
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Zrem> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // 至少得删除一个成员
+        let mut members = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Zrem { key, members })
+    }
+
+    /// 从数据库中的有序集合删除成员，并返回删除的数量
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// 客户端发送请求前将命令转换为 `Frame`
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from_static(b"zrem"));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        for member in self.members {
+            frame.push_bulk(member);
+        }
+
+        frame
+    }
+}
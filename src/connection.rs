@@ -1,5 +1,9 @@
 #![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused_variables, unused_mut))]
-use std::io::{self, Cursor};
+use std::{
+    io::{self, Cursor},
+    net::SocketAddr,
+    time::Duration,
+};
 
 use crate::frame::{self, Frame};
 
@@ -7,8 +11,15 @@ use bytes::{Buf, BytesMut};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt, BufWriter},
     net::TcpStream,
+    time,
 };
 
+/// 写入后保留的编码 buffer 的最大容量
+const MAX_RETAINED_WRITE_BUFFER: usize = 64 * 1024;
+
+/// `Connection::new` 读取时单个 bulk 的字节数及数组的元素个数的上限
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
 /// 通过此远程连接发送和接收 `Frame`
 #[derive(Debug)]
 pub struct Connection {
@@ -16,18 +27,103 @@ pub struct Connection {
 
     // 读取 frames 的 buffer
     buffer: BytesMut,
+
+    // 编码待写入的 frame 的 buffer，在多次写入间复用
+    write_buffer: BytesMut,
+
+    // 与对端协商的 RESP 协议版本，默认为 2，通过 `HELLO 3` 切换至 RESP3
+    protocol: u8,
+
+    // 写入的单个 bulk 的最大字节数
+    max_bulk_len: usize,
+
+    // 读取时声明的单个 bulk 的字节数或数组的元素个数的上限，超过时不再继续读取
+    max_read_len: usize,
+
+    // 回复当前命令后关闭连接，如 `QUIT`
+    closing: bool,
+
+    // 订阅模式下已编码但对端尚未读走的数据
+    output: BytesMut,
+
+    // `read_frame` 等待对端数据的最长时间，`None` 表示一直等待
+    read_timeout: Option<Duration>,
+}
+
+/// `read_frame_draining` 返回的事件
+#[derive(Debug)]
+pub(crate) enum Draining {
+    /// 读取到一条 frame，连接断开时为 `None`
+    Frame(Option<Frame>),
+    /// 待发送队列降到了指定的字节数以下
+    Drained,
 }
 
 impl Connection {
     /// 通过 socket 创建一个新连接
-    /// buffer 大小为 4K
+    /// buffer 大小为 4K，读取时单个 bulk 的字节数及数组的元素个数的上限为 `DEFAULT_MAX_FRAME_SIZE`
     pub fn new(socket: TcpStream) -> Self {
+        Connection::with_limits(socket, DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    /// 与 `new` 相同，但指定读取时单个 bulk 的字节数及数组的元素个数的上限
+    /// 对端声明的长度超过上限时 `read_frame` 返回协议错误，而不是等待并缓存全部数据
+    pub fn with_limits(socket: TcpStream, max_read_len: usize) -> Self {
         Connection {
             stream: BufWriter::new(socket),
-            buffer: BytesMut::with_capacity(4 * 1024)
+            buffer: BytesMut::with_capacity(4 * 1024),
+            write_buffer: BytesMut::with_capacity(4 * 1024),
+            protocol: 2,
+            max_bulk_len: usize::MAX,
+            max_read_len,
+            closing: false,
+            output: BytesMut::new(),
+            read_timeout: None,
         }
     }
 
+    /// 返回当前连接使用的 RESP 协议版本
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// 设置当前连接使用的 RESP 协议版本，为 3 时 `Null` 写为 `_\r\n`
+    /// 不经过 `Client` 自行发送 `HELLO 3` 时，需在收到回复后调用
+    pub fn set_protocol(&mut self, protocol: u8) {
+        self.protocol = protocol;
+    }
+
+    /// 设置写入的单个 bulk 的最大字节数，超过时写入返回错误，默认不限制
+    pub fn set_max_bulk_len(&mut self, max_bulk_len: usize) {
+        self.max_bulk_len = max_bulk_len;
+    }
+
+    /// 设置读取时单个 bulk 的字节数及数组的元素个数的上限，见 `with_limits`
+    pub fn set_max_read_len(&mut self, max_read_len: usize) {
+        self.max_read_len = max_read_len;
+    }
+
+    /// 设置 `read_frame` 等待对端数据的最长时间，默认一直等待
+    /// 超时后 `read_frame` 返回 `io::ErrorKind::TimedOut` 错误，可用 `is_timeout` 判断
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// 标记连接在回复当前命令后关闭
+    pub(crate) fn close_after_reply(&mut self) {
+        self.closing = true;
+    }
+
+    /// 连接是否已被标记为关闭
+    pub(crate) fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    /// 返回对端的地址，可用于日志及客户端列表等
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.get_ref().peer_addr()
+    }
+
     /// 从当前连接中读取一条 `Frame`
     /// 这个函数会等待直到收到的数据足够解析出一条 `Frame`
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
@@ -38,8 +134,18 @@ impl Connection {
                 return Ok(Some(frame))
             }
 
+            // 设置了读取超时时，等待对端数据超过该时间返回超时错误
+            let read = self.stream.read_buf(&mut self.buffer);
+            let n = match self.read_timeout {
+                Some(timeout) => match time::timeout(timeout, read).await {
+                    Ok(res) => res?,
+                    Err(_) => return Err(timed_out()),
+                },
+                None => read.await?,
+            };
+
             // 读取不到数据时连接断开，若 buffer 不为空则异常
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+            if 0 == n {
                 if self.buffer.is_empty() {
                     return Ok(None)
                 } else {
@@ -49,6 +155,70 @@ impl Connection {
         }
     }
 
+    /// 与 `read_frame` 相同，但最多等待 `timeout`
+    /// 超时后返回 `io::ErrorKind::TimedOut` 错误，已读到 buffer 中的数据不会丢失，
+    /// 可以再次调用继续读取
+    pub async fn read_frame_timeout(&mut self, timeout: Duration) -> crate::Result<Option<Frame>> {
+        match time::timeout(timeout, self.read_frame()).await {
+            Ok(res) => res,
+            Err(_) => Err(timed_out()),
+        }
+    }
+
+    /// 错误是否为 `read_frame` 或 `read_frame_timeout` 读取超时
+    pub fn is_timeout(err: &crate::Error) -> bool {
+        matches!(err.downcast_ref::<io::Error>(), Some(e) if e.kind() == io::ErrorKind::TimedOut)
+    }
+
+    /// 将 frame 编码后放入待发送队列，不等待对端读取，由 `read_frame_draining` 逐步发送
+    pub(crate) fn queue_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.check_bulk_len(frame)?;
+
+        let resp3 = self.protocol == 3;
+        self.output.reserve(frame.encoded_len_with(resp3));
+        frame.encode_with(&mut self.output, resp3);
+
+        Ok(())
+    }
+
+    /// 待发送队列中的字节数
+    pub(crate) fn pending_bytes(&self) -> usize {
+        self.output.len()
+    }
+
+    /// 与 `read_frame` 相同，但等待期间同时发送待发送队列中的数据
+    /// 指定 `low_water` 时，发送后队列中的数据不超过此字节数也会返回 `Drained`
+    pub(crate) async fn read_frame_draining(&mut self, low_water: Option<usize>) -> crate::Result<Draining> {
+        loop {
+            if let Some(frame) = self.parse_frame()? {
+                return Ok(Draining::Frame(Some(frame)))
+            }
+
+            // `write_frame` 每次都会 flush，`BufWriter` 中没有数据，可以直接使用底层的 socket
+            let (mut reader, mut writer) = self.stream.get_mut().split();
+
+            tokio::select! {
+                res = reader.read_buf(&mut self.buffer) => {
+                    if 0 == res? {
+                        if self.buffer.is_empty() {
+                            return Ok(Draining::Frame(None))
+                        } else {
+                            return Err("Connection reset by peer".into())
+                        }
+                    }
+                },
+                res = writer.write(&self.output), if !self.output.is_empty() => {
+                    let n = res?;
+                    self.output.advance(n);
+
+                    if low_water.is_some_and(|low_water| self.output.len() <= low_water) {
+                        return Ok(Draining::Drained)
+                    }
+                },
+            }
+        }
+    }
+
     /// 从 self.buffer 中解析出 frame
     pub fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
         use frame::Error::Incomplete;
@@ -57,7 +227,7 @@ impl Connection {
         // `Cursor` 也实现了 `Buf`
         let mut buf = Cursor::new(&self.buffer[..]);
 
-        match Frame::check(&mut buf) {
+        match Frame::check(&mut buf, self.max_read_len) {
             Ok(_) => {
                 // 检查通过则当前位置之前为一个 `Frame`
                 let len = buf.position() as usize;
@@ -76,25 +246,59 @@ impl Connection {
     }
 
     /// 将 frame 写入 stream
+    /// 先将整个 frame 编码到复用的 buffer 中，再一次性写入，最后 flush
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            // Array(Vec<Frame>):
-            // b'*' + bytes(len) + '\r\n' + bytes(frames)
-            Frame::Array(arr) => {
-                self.stream.write_u8(b'*').await?;
-                self.write_decimal(arr.len() as u64).await?;
-
-                for entry in arr {
-                    self.write_value(&entry).await?;
-                }
-            },
-            _ => self.write_value(frame).await?,
+        self.write_frame_buffered(frame).await?;
+
+        self.stream.flush().await
+    }
+
+    /// 与 `write_frame` 相同，但不 flush，数据留在 stream 的缓冲中
+    /// 用于流水线一次写入多条命令，全部写入后需调用 `flush`
+    pub async fn write_frame_buffered(&mut self, frame: &Frame) -> io::Result<()> {
+        // 写入前检查，出错时不会有任何数据写入 stream
+        self.check_bulk_len(frame)?;
+
+        // 先发送队列中的数据，保证回复的顺序
+        if !self.output.is_empty() {
+            self.stream.write_all(&self.output).await?;
+            self.output.clear();
+        }
+
+        self.write_buffer.clear();
+        let resp3 = self.protocol == 3;
+        self.write_buffer.reserve(frame.encoded_len_with(resp3));
+        frame.encode_with(&mut self.write_buffer, resp3);
+
+        self.stream.write_all(&self.write_buffer).await?;
+
+        // 写入较大的回复后释放 buffer，避免每个连接都一直持有大块内存
+        if self.write_buffer.capacity() > MAX_RETAINED_WRITE_BUFFER {
+            self.write_buffer = BytesMut::with_capacity(4 * 1024);
+        }
+
+        Ok(())
+    }
+
+    /// 将待发送队列及 stream 中缓冲的数据发送出去，`write_value` 写入后需调用此函数
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if !self.output.is_empty() {
+            self.stream.write_all(&self.output).await?;
+            self.output.clear();
         }
 
         self.stream.flush().await
     }
 
+    /// 发送缓冲的数据后关闭写端，对端读到 EOF，之后仍可读取对端发来的数据
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.stream.shutdown().await
+    }
+
     pub async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
+        self.check_bulk_len(frame)?;
+
         match frame {
             Frame::Simple(val) => {
                 self.stream.write_u8(b'+').await?;
@@ -110,26 +314,56 @@ impl Connection {
                 self.stream.write_u8(b':').await?;
                 self.write_decimal(*val).await?;
             },
+            // 与 `Frame::encode_with` 相同，协商 RESP3 后使用 RESP3 的 Null
+            Frame::Null if self.protocol == 3 => {
+                self.stream.write_all(b"_\r\n").await?;
+            },
             Frame::Null => {
                 self.stream.write_all(b"$-1\r\n").await?;
-            }
+            },
             Frame::Bulk(val) => {
                 self.stream.write_u8(b'$').await?;
-                self.write_decimal(val.len() as u64).await?;
+                self.write_decimal(val.len() as i64).await?;
                 self.stream.write_all(val).await?;
                 self.stream.write_all(b"\r\n").await?;
             },
-            Frame::Array(_val) => unreachable!(),
+            // 嵌套的数组，如 `SLOWLOG GET` 的回复，递归写入每一项
+            Frame::Array(val) | Frame::Push(val) => {
+                let prefix = match frame {
+                    Frame::Push(_) => b'>',
+                    _ => b'*',
+                };
+
+                self.stream.write_u8(prefix).await?;
+                self.write_decimal(val.len() as i64).await?;
+
+                for entry in val {
+                    Box::pin(self.write_value(entry)).await?;
+                }
+            },
         }
 
         Ok(())
     }
 
-    pub async fn write_decimal(&mut self, value: u64) -> io::Result<()> {
+    /// 检查 frame 中的所有 bulk 都没有超过 `max_bulk_len`
+    fn check_bulk_len(&self, frame: &Frame) -> io::Result<()> {
+        match frame {
+            Frame::Bulk(val) if val.len() > self.max_bulk_len => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bulk of {} bytes exceeds proto-max-bulk-len {}", val.len(), self.max_bulk_len),
+            )),
+            Frame::Array(val) | Frame::Push(val) => val.iter().try_for_each(|entry| self.check_bulk_len(entry)),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn write_decimal(&mut self, value: i64) -> io::Result<()> {
         use std::io::Write;
 
         // 初始化一个，并将 value 写入，获得字节数
-        let mut buf = [0u8; 20];
+        // `i64::MIN` 有 19 位数字加一个负号，留出余量
+        let mut buf = [0u8; 24];
         let mut buf = Cursor::new(&mut buf[..]);
         write!(&mut buf, "{}", value)?;
 
@@ -141,3 +375,8 @@ impl Connection {
         Ok(())
     }
 }
+
+/// 读取超时时返回的错误
+fn timed_out() -> crate::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "read frame timed out").into()
+}
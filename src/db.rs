@@ -1,16 +1,31 @@
 #![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused_variables, unused_mut))]
 use std::{
-    collections::{BTreeMap, HashMap},
-    sync::{Arc, Mutex},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet, VecDeque},
+    fs,
+    hash::{Hash, Hasher},
+    io, mem,
+    net::SocketAddr,
+    ops::{Bound, Deref, DerefMut},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use tokio::{
     sync::{broadcast, Notify},
+    task::JoinHandle,
     time::{self, Duration, Instant},
 };
 use tracing::debug;
 
+use crate::{cmd::{Expiration, SetCondition, SetOptions, SetOutcome}, glob, slowlog::{SlowLog, SlowLogEntry}, sorted_set::SortedSet, hyperloglog::HyperLogLog};
+
+mod snapshot;
+
 /// 关闭服务的时候 drop `DbDropGuard`。其会通知所有持有 db 的连接关闭
 #[derive(Debug)]
 pub(crate) struct DbDropGuard {
@@ -20,35 +35,180 @@ pub(crate) struct DbDropGuard {
 /// Db 拥有 `Arc` Shared，在所有连接之间共享
 ///
 /// 每个连接程序会共享地持有此 db 句柄
+/// 也可以通过 `KvStore` 直接使用，无需启动服务，最后一个句柄被 drop 时后台任务退出
 #[derive(Debug, Clone)]
-pub(crate) struct Db {
+pub struct Db {
+    shared: Arc<Shared>,
+    /// 所有句柄共享，不交给后台任务
+    _handles: Arc<Handles>,
+}
+
+/// 最后一个 `Db` 句柄被 drop 时通知后台任务退出
+/// 后台任务也持有 `Shared`，无法通过 `Shared` 的引用计数判断是否还有句柄
+#[derive(Debug)]
+struct Handles {
     shared: Arc<Shared>,
 }
 
 /// Shared
-/// state 加锁，读写数据
+/// shards 为按键名哈希划分的键空间分片，每个分片单独加锁，读写不同分片的键不会相互等待
 /// background_task 用来做什么
 /// background_task 用来自我通知，在过期数据清理任务中，若有新的、更早过期的数据，则会通过
 /// background_task.notify_one() 来发送消息，而在 purge_expired_tasks 中的 background_task
 /// 会收到消息，重新开始新的倒计时
 #[derive(Debug)]
 struct Shared {
-    /// 共享的 state 被 mutex 保护，
-    /// 因其内部操作都是同步的故使用 `std::sync::Mutex` 而非 `Tokio` mutex
-    state: Mutex<State>,
+    /// 键空间的分片，每个分片被各自的读写锁保护，只读取的命令加读锁，可以同时执行
+    /// 因其内部操作都是同步的故使用 `std::sync::RwLock` 而非 `Tokio` 的锁
+    ///
+    /// 涉及多个键的命令按分片下标升序加锁，避免死锁
+    shards: Box<[RwLock<State>]>,
+    /// 广播、订阅的频道，与键空间分开加锁
+    /// 订阅会创建频道，发布会更新消息序号，两者都需要修改，因此仍使用 mutex
+    pub_sub: Mutex<PubSub>,
+    /// 数据库是否已关闭，关闭后清理任务退出
+    shutdown: AtomicBool,
     /// 用来发送通知，清理过期数据
     background_task: Notify,
+    /// 清理过期数据的后台任务，关闭时等待其退出
+    purge_task: Mutex<Option<JoinHandle<()>>>,
+    /// 慢命令日志，与数据分开加锁
+    slowlog: Mutex<SlowLog>,
+    /// 是否发布键空间通知
+    keyspace_events: AtomicBool,
+    /// 回复中单个 bulk 的最大字节数，可通过 `CONFIG SET` 修改
+    proto_max_bulk_len: AtomicUsize,
+    /// 写入时未指定有效期的键使用的有效期，以毫秒为单位，0 表示不过期
+    default_ttl: AtomicU64,
+    /// 每个连接每秒最多执行的命令数量，0 表示不限制
+    rate_limit: AtomicU64,
+    /// 最近一次成功保存快照的 Unix 时间（秒），未保存过时为服务启动的时间
+    last_save: AtomicI64,
+}
+
+/// 键空间分片的数量，须为 2 的幂，`SCAN` 的游标依赖这一点
+const SHARDS: usize = 16;
+
+/// 选择分片使用的哈希位数，哈希值的其余位用作键在分片内的 `SCAN` 位置
+const SHARD_BITS: u32 = SHARDS.trailing_zeros();
+
+/// 每个频道缓存的消息数量，订阅者落后超过此数量时会丢失最早的消息
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// 一个广播频道
+#[derive(Debug)]
+struct Channel {
+    tx: broadcast::Sender<Published>,
+    space: Arc<Space>,
+}
+
+/// `publish_wait` 的发布者等待的条件
+#[derive(Debug, Default)]
+struct Space {
+    /// 订阅者取走消息、取消订阅或积压降到软上限以下时通知，唤醒等待的发布者
+    notify: Notify,
+    /// 待发送数据超过软上限的订阅者数量，不为 0 时 `publish_wait` 等待
+    congested: AtomicUsize,
+}
+
+/// 发布到频道的一条消息
+#[derive(Debug, Clone)]
+pub(crate) struct Published {
+    /// 消息在频道中的序号，开启消息序号时从 1 开始逐条递增
+    pub(crate) seq: Option<u64>,
+    pub(crate) content: Bytes,
+}
+
+/// 订阅一个频道得到的接收端
+#[derive(Debug)]
+pub(crate) struct Subscription {
+    rx: broadcast::Receiver<Published>,
+    /// 字段按声明顺序释放，`rx` 释放之后才会唤醒发布者
+    space: SpaceNotify,
 }
 
+/// 释放时唤醒等待缓存空间的发布者
 #[derive(Debug)]
+struct SpaceNotify(Arc<Space>);
+
+/// 订阅者标记自己的待发送数据超过了软上限，由 `Subscription::congestion` 得到
+/// 被标记时 `publish_wait` 的发布者等待，释放时取消标记
+#[derive(Debug)]
+pub(crate) struct Congestion {
+    space: Arc<Space>,
+    congested: bool,
+}
+
+/// 一个键空间分片
+#[derive(Debug, Default)]
 struct State {
     /// KV 数据
     entries: HashMap<String, Entry>,
-    /// 广播、订阅的频道
-    pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+    /// 本分片中的键的有效期，清理任务依次检查每个分片
     expirations: BTreeMap<(Instant, u64), String>,
     next_id: u64,
-    shutdown: bool,
+}
+
+/// 同时持有的多个分片的锁，用于涉及多个键的命令
+/// 只读取的命令持有读锁，修改的命令持有写锁
+struct Shards<G> {
+    /// 按分片下标排列，未加锁的分片为 `None`
+    guards: Vec<Option<G>>,
+}
+
+/// 广播、订阅的频道
+#[derive(Debug, Default)]
+struct PubSub {
+    /// 按名称订阅的频道
+    channels: HashMap<String, Channel>,
+    /// 按模式订阅的频道，发布时向名称匹配模式的订阅者发送 (频道, 消息)
+    patterns: HashMap<String, broadcast::Sender<(String, Published)>>,
+    /// 每个频道最近一条消息的序号，为 `None` 时不开启消息序号
+    /// 没有订阅者的频道同样保留序号，重新订阅的订阅者可以据此发现丢失的消息
+    sequences: Option<HashMap<String, u64>>,
+}
+
+/// 数据库内存占用的统计信息，由 `MEMORY STATS` 使用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MemoryStats {
+    /// 键的总数
+    pub(crate) keys: usize,
+    /// 所有条目估算占用的字节数
+    pub(crate) bytes: usize,
+}
+
+/// 对类型不匹配的键执行操作时返回的错误
+pub(crate) const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// 有序集合的分值运算结果为 `NaN` 时返回的错误
+const NAN_SCORE: &str = "ERR resulting score is not a number (NaN)";
+
+/// `proto-max-bulk-len` 的默认值，与 Redis 相同为 512MB
+pub(crate) const DEFAULT_PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// 值不是整数，或自增后溢出时返回的错误
+const NOT_INTEGER: &str = "ERR value is not an integer or out of range";
+
+/// 集合运算的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SetOperation {
+    /// 交集
+    Inter,
+    /// 并集
+    Union,
+    /// 差集，第一个集合减去其余集合
+    Diff,
+}
+
+impl SetOperation {
+    /// 保存运算结果时发送的键空间通知事件名
+    fn store_event(self) -> &'static str {
+        match self {
+            SetOperation::Inter => "sinterstore",
+            SetOperation::Union => "sunionstore",
+            SetOperation::Diff => "sdiffstore",
+        }
+    }
 }
 
 /// 键值存储中的条目
@@ -57,11 +217,30 @@ struct Entry {
     /// 唯一标识 ID
     id: u64,
     /// 存储的数据
-    data: Bytes,
+    value: Value,
     /// 有效期，超过后将从数据库中删除
     expires_at: Option<Instant>,
 }
 
+/// 条目中存储的值，不同类型的命令只能操作对应类型的值
+#[derive(Debug, Clone)]
+enum Value {
+    /// 字符串，`GET`/`SET` 等命令使用
+    String(Bytes),
+    /// 可以表示为 64 位整数的字符串，即 `int` 编码，读取时再格式化为字符串
+    Int(i64),
+    /// 无序且不重复的集合，`SADD`/`SMEMBERS` 等命令使用
+    Set(HashSet<Bytes>),
+    /// 按分值排序的集合，`ZADD`/`ZRANGEBYSCORE` 等命令使用
+    ZSet(SortedSet),
+    /// HyperLogLog 的寄存器，`PFADD`/`PFCOUNT` 使用
+    Hll(HyperLogLog),
+    /// 两端均可插入、弹出的列表，`LPUSH`/`RPOP` 等命令使用
+    List(VecDeque<Bytes>),
+    /// 字段到值的映射，`HSET`/`HGET` 使用
+    Hash(crate::hash::Hash),
+}
+
 impl DbDropGuard {
     /// 创建一个包括 `Db` 的 `DbHolder`
     /// 当他被删除时，`Db` 清除任务将被关闭？？
@@ -73,6 +252,17 @@ impl DbDropGuard {
     pub(crate) fn db(&self) -> Db {
         self.db.clone()
     }
+
+    /// 通知后台清理任务退出，并等待其结束
+    /// 返回后不会再有后台任务访问共享的数据
+    pub(crate) async fn shutdown(&self) {
+        self.db.shutdown_purge_task();
+
+        let purge_task = self.db.shared.purge_task.lock().unwrap().take();
+        if let Some(purge_task) = purge_task {
+            let _ = purge_task.await;
+        }
+    }
 }
 
 impl Drop for DbDropGuard {
@@ -82,179 +272,1762 @@ impl Drop for DbDropGuard {
     }
 }
 
+impl Default for Db {
+    fn default() -> Db {
+        Db::new()
+    }
+}
+
 impl Db {
-    pub(crate) fn new() -> Self {
+    /// 创建一个空的数据库，并启动清理过期数据的后台任务，需在 Tokio 运行时中调用
+    pub fn new() -> Self {
         let shared = Arc::new(Shared {
-            state: Mutex::new(State {
-                entries: HashMap::new(),
-                pub_sub: HashMap::new(),
-                expirations: BTreeMap::new(),
-                next_id: 0,
-                shutdown: false,
-            }),
+            shards: (0..SHARDS).map(|_| RwLock::new(State::default())).collect(),
+            pub_sub: Mutex::new(PubSub::default()),
+            shutdown: AtomicBool::new(false),
             background_task: Notify::new(),
+            purge_task: Mutex::new(None),
+            slowlog: Mutex::new(SlowLog::default()),
+            keyspace_events: AtomicBool::new(false),
+            proto_max_bulk_len: AtomicUsize::new(DEFAULT_PROTO_MAX_BULK_LEN),
+            default_ttl: AtomicU64::new(0),
+            rate_limit: AtomicU64::new(0),
+            last_save: AtomicI64::new(unix_seconds()),
         });
 
         // 启动后台任务
-        tokio::spawn(purge_expired_tasks(shared.clone()));
+        let purge_task = tokio::spawn(purge_expired_tasks(shared.clone()));
+        *shared.purge_task.lock().unwrap() = Some(purge_task);
 
-        Db { shared }
+        Db {
+            _handles: Arc::new(Handles { shared: shared.clone() }),
+            shared,
+        }
     }
 
-    /// 通过键查找值
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
-        // 首先得到锁，然后查找、克隆值
+    /// 通过键查找值，键存储的不是字符串时返回 `WRONGTYPE` 错误
+    pub(crate) fn get(&self, key: &str) -> crate::Result<Option<Bytes>> {
+        // 首先得到键所在分片的锁，然后查找、克隆值
         // 因为 data 使用 `Bytes` 存储，所以 clone 只是浅拷贝
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+        let state = self.shared.read(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Ok(Some(data.clone())),
+            Some(Value::Int(value)) => Ok(Some(Bytes::from(value.to_string()))),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    /// 键的过期时刻，外层的 `None` 表示键不存在，内层的 `None` 表示键没有有效期
+    /// 已过期但尚未被清理的键返回已经过去的时刻，由调用方处理
+    pub(crate) fn ttl(&self, key: &str) -> Option<Option<Instant>> {
+        let state = self.shared.read(key);
+
+        state.entries.get(key).map(|entry| entry.expires_at)
+    }
+
+    /// 为已存在的键设置 `when` 之后过期，替换原有的有效期，返回键是否存在
+    /// 调用方需保证 `when` 不会使时刻溢出
+    pub(crate) fn set_expire(&self, key: &str, when: Duration) -> bool {
+        let mut state = self.shared.write(key);
+
+        let expires_at = Instant::now() + when;
+        let notify = state.is_next_expiration(Some(expires_at));
+
+        if !state.set_expires_at(key, expires_at) {
+            return false;
+        }
+
+        drop(state);
+
+        // 新的有效期早于后台任务等待的时刻时，通知其重新计算
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        self.notify("expire", key);
+
+        true
+    }
+
+    /// 移除键的有效期，同时删除清理记录，使后台任务不再删除该键
+    /// 键存在且有有效期时返回 `true`
+    pub(crate) fn persist(&self, key: &str) -> bool {
+        let removed = self.shared.write(key).clear_expires_at(key);
+
+        if removed {
+            self.notify("persist", key);
+        }
+
+        removed
+    }
+
+    /// 将键存储的整数加上 `delta`，返回新的值，键不存在时视其值为 0
+    /// 读取、修改、写回都在同一次加锁中完成，并发的自增不会相互覆盖
+    pub(crate) fn incr_by(&self, key: &str, delta: i64) -> crate::Result<i64> {
+        let mut state = self.shared.write(key);
+
+        let value = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(value) => {
+                let current = match value {
+                    Value::Int(current) => *current,
+                    Value::String(data) => std::str::from_utf8(data)
+                        .ok()
+                        .and_then(|data| data.parse::<i64>().ok())
+                        .ok_or(NOT_INTEGER)?,
+                    _ => return Err(WRONGTYPE.into()),
+                };
+                let sum = current.checked_add(delta).ok_or(NOT_INTEGER)?;
+                // 原地修改，保留键的有效期
+                *value = Value::Int(sum);
+                sum
+            },
+            None => {
+                state.insert(key.to_string(), Value::Int(delta), None);
+                delta
+            },
+        };
+
+        drop(state);
+
+        self.notify("incrby", key);
+
+        Ok(value)
+    }
+
+    /// 查找键对应的值，只返回前 `len` 个字节
+    /// 返回的是原值的切片，共享同一块内存，不会复制数据
+    pub(crate) fn get_prefix(&self, key: &str, len: usize) -> crate::Result<Option<Bytes>> {
+        let state = self.shared.read(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Ok(Some(data.slice(..len.min(data.len())))),
+            Some(Value::Int(value)) => {
+                let data = Bytes::from(value.to_string());
+                Ok(Some(data.slice(..len.min(data.len()))))
+            },
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
     }
 
-    /// 通过键存储值
+    /// 通过键存储值，无论键原先存储的是何种类型都会被覆盖
+    /// 未指定有效期时使用配置的默认有效期
     pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
-        let mut state = self.shared.state.lock().unwrap();
-        // 获取自增 id
-        let id = state.next_id;
-        state.next_id += 1;
+        let mut state = self.shared.write(&key);
 
-        let mut notify = false;
+        // 失效时间，无法表示的时刻视为不过期，`SET` 解析时已拒绝这样的有效期
+        let expires_at = expire.or_else(|| self.default_ttl()).and_then(|duration| Instant::now().checked_add(duration));
 
-        let expires_at = expire.map(|duration| {
-            // 失效时间
-            let when = Instant::now() + duration;
+        // 若当前最早失效时间晚于当前键的有效期
+        // 则需通知后台使其更新状态
+        let notify = state.is_next_expiration(expires_at);
 
-            // 若当前最早失效时间晚于当前键的有效期
-            // 则需通知后台使其更新状态
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
+        let event_key = self.keyspace_events_enabled().then(|| key.clone());
+        state.insert(key, Value::string(value), expires_at);
 
-            state.expirations.insert((when, id), key.clone());
-            when
-        });
+        drop(state);
 
-        // 将新条目添加到 `HashMap` 中，并得到旧的条目
-        let prev = state.entries.insert(
-            key,
-            Entry {
-                id,
-                data: value,
-                expires_at,
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        if let Some(key) = event_key {
+            self.notify("set", &key);
+            if expire.is_some() {
+                self.notify("expire", &key);
             }
-        );
+        }
+    }
 
-        // 若替换了旧的 `Entry`，则需将其从有效期清理列表中去除
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                state.expirations.remove(&(when, prev.id));
+    /// 按 `options` 设置键的值，条件检查、读取旧值与写入在同一次加锁中完成
+    pub(crate) fn set_with_options(&self, key: String, value: Bytes, options: &SetOptions) -> crate::Result<SetOutcome> {
+        let mut state = self.shared.write(&key);
+
+        let prev = state.entries.get(&key);
+
+        let previous = if options.is_get() {
+            match prev.map(|entry| &entry.value) {
+                Some(Value::String(data)) => Some(data.clone()),
+                Some(Value::Int(value)) => Some(Bytes::from(value.to_string())),
+                Some(_) => return Err(WRONGTYPE.into()),
+                None => None,
             }
+        } else {
+            None
+        };
+
+        let written = match options.condition() {
+            Some(SetCondition::Nx) => prev.is_none(),
+            Some(SetCondition::Xx) => prev.is_some(),
+            None => true,
+        };
+        if !written {
+            return Ok(SetOutcome { written, previous });
         }
 
+        // 有效期溢出时报错，不能在持有锁时 panic
+        let now = Instant::now();
+        let invalid = || crate::Error::from("ERR invalid expire time in 'set' command");
+        let expires_at = match options.expiration() {
+            Some(Expiration::In(expire)) => Some(now.checked_add(expire).ok_or_else(invalid)?),
+            // 已经过去的时刻视为立即过期
+            Some(Expiration::At(at)) => {
+                let expire = at.duration_since(SystemTime::now()).unwrap_or_default();
+                Some(now.checked_add(expire).ok_or_else(invalid)?)
+            },
+            Some(Expiration::KeepTtl) => prev.and_then(|entry| entry.expires_at),
+            None => self.default_ttl().and_then(|ttl| now.checked_add(ttl)),
+        };
+
+        let notify = state.is_next_expiration(expires_at);
+
+        let event_key = self.keyspace_events_enabled().then(|| key.clone());
+        state.insert(key, Value::string(value), expires_at);
+
         drop(state);
 
         if notify {
             self.shared.background_task.notify_one();
         }
+
+        if let Some(key) = event_key {
+            self.notify("set", &key);
+            if matches!(options.expiration(), Some(Expiration::In(_) | Expiration::At(_))) {
+                self.notify("expire", &key);
+            }
+        }
+
+        Ok(SetOutcome { written, previous })
     }
 
-    /// 请求订阅一个频道，返回一个 `Reciever` 来接收此频道发送的广播
-    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
+    /// 键不存在时写入，返回是否写入，检查与写入在同一次加锁中完成，并发时只有一个能成功
+    pub(crate) fn set_nx(&self, key: String, value: Bytes) -> bool {
+        // 未指定 `GET`，不会因类型不符出错
+        self.set_with_options(key, value, &SetOptions::new().nx())
+            .is_ok_and(|outcome| outcome.written)
+    }
 
-        // 先获取锁
-        let mut state = self.shared.state.lock().unwrap();
-
-        match state.pub_sub.entry(key) {
-            // 已有对应的频道
-            Entry::Occupied(e) => e.get().subscribe(),
-            // 当前无此频道，创建一个并加入
-            Entry::Vacant(e) => {
-                let (tx, rx) = broadcast::channel(1024);
-                e.insert(tx);
-                rx
-            },
+    /// 一次设置多个键的值，配置了默认有效期时使用默认有效期
+    /// 所有键所在的分片同时加锁，其它连接不会看到只写入了一部分的结果
+    pub(crate) fn mset(&self, pairs: Vec<(String, Bytes)>) {
+        let mut shards = self.shared.write_keys(pairs.iter().map(|(key, _)| &key[..]));
+
+        let expires_at = self.default_ttl().and_then(|ttl| Instant::now().checked_add(ttl));
+        let mut notify = false;
+
+        let mut event_keys = self.keyspace_events_enabled().then(|| Vec::with_capacity(pairs.len()));
+        for (key, value) in pairs {
+            if let Some(event_keys) = &mut event_keys {
+                event_keys.push(key.clone());
+            }
+            let state = shards.state_mut(&key);
+            notify |= state.is_next_expiration(expires_at);
+            state.insert(key, Value::string(value), expires_at);
+        }
+
+        drop(shards);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        for key in event_keys.into_iter().flatten() {
+            self.notify("set", &key);
         }
     }
 
-    /// 向广播中发送数据，并返回此频道的订阅者的数量
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
+    /// 删除键，无论其存储的是何种类型，返回实际删除的键数量
+    /// 过期时间等附带的记录由 `State::remove` 一并清理
+    pub(crate) fn del(&self, keys: &[String]) -> usize {
+        let mut shards = self.shared.write_keys(keys.iter().map(|key| &key[..]));
+
+        let deleted: Vec<&String> = keys.iter().filter(|key| shards.state_mut(key).remove(key).is_some()).collect();
 
-        state
-            .pub_sub
-            .get(key)
-            // 发送失败或无此频道则返回 0
-            .map(|tx| tx.send(value).unwrap_or(0))
-            .unwrap_or(0)
+        drop(shards);
+
+        for key in &deleted {
+            self.notify("del", key);
+        }
+
+        deleted.len()
     }
 
-    /// 改变 shutdown 标志位后通知清理任务，其在一个 while 循环中会退出
-    fn shutdown_purge_task(&self) {
-        let mut state = self.shared.state.lock().unwrap();
-        state.shutdown = true;
+    /// 键的值等于 `value` 时删除它，返回是否删除
+    /// 比较与删除在同一次加锁中完成，其它客户端无法在两者之间修改键
+    pub(crate) fn del_if_eq(&self, key: &str, value: &[u8]) -> crate::Result<bool> {
+        let mut state = self.shared.write(key);
+
+        let equal = match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => data[..] == *value,
+            Some(Value::Int(data)) => data.to_string().as_bytes() == value,
+            Some(_) => return Err(WRONGTYPE.into()),
+            None => false,
+        };
+
+        if !equal {
+            return Ok(false);
+        }
+
+        state.remove(key);
 
         drop(state);
-        self.shared.background_task.notify_one();
+
+        self.notify("del", key);
+
+        Ok(true)
     }
-}
 
-impl Shared {
-    /// 清除所有的已过期的键，并返回最近的将过期的时间
-    /// 后台任务将休眠到过期时间再执行清理任务
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+    /// 将 `source` 的值及有效期复制到 `destination`，返回是否复制
+    /// `source` 不存在，或 `destination` 已存在且未指定 `replace` 时不复制
+    pub(crate) fn copy(&self, source: &str, destination: String, replace: bool) -> bool {
+        let mut shards = self.shared.write_keys([source, &destination[..]]);
 
-        if state.shutdown {
-            return None;
+        let (value, expires_at) = match shards.state(source).entries.get(source) {
+            Some(entry) => (entry.value.clone(), entry.expires_at),
+            None => return false,
+        };
+
+        let state = shards.state_mut(&destination);
+        if !replace && state.entries.contains_key(&destination) {
+            return false;
         }
 
-        let state = &mut *state;
+        let notify = state.is_next_expiration(expires_at);
+        state.insert(destination.clone(), value, expires_at);
 
-        let now = Instant::now();
+        drop(shards);
 
-        while let Some((&(when, id), key)) = state.expirations.iter().next() {
-            if when > now {
-                return Some(when)
-            }
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+        self.notify("copy_to", &destination);
 
-            // 清理已过期的键
-            state.entries.remove(key);
-            state.expirations.remove(&(when, id));
+        true
+    }
+
+    /// 将 `source` 重命名为 `destination`，值与有效期保持不变，`destination` 原有的值被覆盖
+    /// `source` 不存在时返回错误
+    pub(crate) fn rename(&self, source: &str, destination: String) -> crate::Result<()> {
+        let mut shards = self.shared.write_keys([source, &destination[..]]);
+
+        let entry = match shards.state_mut(source).remove(source) {
+            Some(entry) => entry,
+            None => return Err("ERR no such key".into()),
+        };
+
+        let state = shards.state_mut(&destination);
+        let notify = state.is_next_expiration(entry.expires_at);
+        state.insert(destination.clone(), entry.value, entry.expires_at);
+
+        drop(shards);
+
+        if notify {
+            self.shared.background_task.notify_one();
         }
+        self.notify("rename_from", source);
+        self.notify("rename_to", &destination);
 
-        None
+        Ok(())
     }
 
-    /// 当数据库关闭时，返回 `true`
-    fn is_shutdown(&self) -> bool {
-        self.state.lock().unwrap().shutdown
+    /// 在键存储的字符串末尾追加 `value`，返回追加后的长度，键不存在时等同于 `SET`
+    /// `int` 编码的值先格式化为字符串再追加，追加后不再是整数编码；键的有效期保持不变
+    pub(crate) fn append(&self, key: &str, value: Bytes) -> crate::Result<usize> {
+        let mut state = self.shared.write(key);
+
+        let len = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(current) => {
+                // 没有其它引用时直接在原有的 buffer 后追加，容量按倍数增长，多次追加不必每次复制整个值
+                let mut buf = match current {
+                    Value::String(data) => match mem::take(data).try_into_mut() {
+                        Ok(buf) => buf,
+                        Err(data) => BytesMut::from(&data[..]),
+                    },
+                    Value::Int(int) => BytesMut::from(int.to_string().as_bytes()),
+                    _ => return Err(WRONGTYPE.into()),
+                };
+                buf.extend_from_slice(&value);
+                let len = buf.len();
+                *current = Value::String(buf.freeze());
+                len
+            },
+            None => {
+                let len = value.len();
+                state.insert(key.to_string(), Value::string(value), None);
+                len
+            },
+        };
+
+        drop(state);
+
+        self.notify("append", key);
+
+        Ok(len)
     }
-}
 
-impl State {
-    /// 下一个临近键的过期时间
-    fn next_expiration(&self) -> Option<Instant> {
-        self.expirations
-            .keys()
-            .next()
-            .map(|expiration| expiration.0)
+    /// 返回键存储的值的类型，由 `TYPE` 使用，键不存在时返回 `"none"`
+    pub(crate) fn key_type(&self, key: &str) -> &'static str {
+        let state = self.shared.read(key);
+
+        state.entries.get(key).map_or("none", |entry| entry.value.type_name())
     }
-}
 
-async fn purge_expired_tasks(shared: Arc<Shared>) {
-    // 如果设置了关闭标识，则退出后台任务
-    while !shared.is_shutdown() {
-        if let Some(when) = shared.purge_expired_keys() {
-            tokio::select! {
-                _ = time::sleep_until(when) => {},
-                // 当在等待时得到通知，则更新最早生效的键的时间
-                _ = shared.background_task.notified() => {},
-            }
-        } else {
-            // 没有将生效的键，等待通知
-            shared.background_task.notified().await;
+    /// 返回键存储的值的内部编码，键不存在时返回 `None`
+    pub(crate) fn encoding(&self, key: &str) -> Option<&'static str> {
+        let state = self.shared.read(key);
+
+        state.entries.get(key).map(|entry| entry.value.encoding())
+    }
+
+    /// 将元素依次插入列表的头部（`left` 为 `true`）或尾部，返回插入后列表的长度
+    /// 键不存在时创建一个新的列表
+    pub(crate) fn push(&self, key: String, elements: Vec<Bytes>, left: bool) -> crate::Result<usize> {
+        let mut state = self.shared.write(&key);
+
+        if !state.entries.contains_key(&key) {
+            state.insert(key.clone(), Value::List(VecDeque::new()), None);
         }
+
+        let len = match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::List(list)) => {
+                for element in elements {
+                    if left {
+                        list.push_front(element);
+                    } else {
+                        list.push_back(element);
+                    }
+                }
+                list.len()
+            },
+            _ => return Err(WRONGTYPE.into()),
+        };
+
+        drop(state);
+
+        self.notify(if left { "lpush" } else { "rpush" }, &key);
+
+        Ok(len)
     }
 
-    debug!("Purge background task shutdown")
+    /// 弹出列表头部的一个元素，键不存在时返回 `None`
+    pub(crate) fn lpop(&self, key: &str) -> crate::Result<Option<Bytes>> {
+        Ok(self.pop(key, 1, true)?.and_then(|mut elements| elements.pop()))
+    }
+
+    /// 弹出列表尾部的一个元素，键不存在时返回 `None`
+    pub(crate) fn rpop(&self, key: &str) -> crate::Result<Option<Bytes>> {
+        Ok(self.pop(key, 1, false)?.and_then(|mut elements| elements.pop()))
+    }
+
+    /// 从列表头部依次弹出至多 `count` 个元素，键不存在时返回 `None`
+    pub(crate) fn lpop_count(&self, key: &str, count: usize) -> crate::Result<Option<Vec<Bytes>>> {
+        self.pop(key, count, true)
+    }
+
+    /// 从列表尾部依次弹出至多 `count` 个元素，键不存在时返回 `None`
+    pub(crate) fn rpop_count(&self, key: &str, count: usize) -> crate::Result<Option<Vec<Bytes>>> {
+        self.pop(key, count, false)
+    }
+
+    /// 从列表的头部（`left` 为 `true`）或尾部弹出至多 `count` 个元素，按弹出的顺序返回
+    /// 弹出后列表为空时删除键
+    fn pop(&self, key: &str, count: usize, left: bool) -> crate::Result<Option<Vec<Bytes>>> {
+        let mut state = self.shared.write(key);
+
+        let (popped, is_empty) = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::List(list)) => {
+                let count = count.min(list.len());
+                let popped: Vec<Bytes> = if left {
+                    list.drain(..count).collect()
+                } else {
+                    list.drain(list.len() - count..).rev().collect()
+                };
+                (popped, list.is_empty())
+            },
+            Some(_) => return Err(WRONGTYPE.into()),
+            None => return Ok(None),
+        };
+
+        if is_empty {
+            state.remove(key);
+        }
+
+        drop(state);
+
+        if !popped.is_empty() {
+            self.notify(if left { "lpop" } else { "rpop" }, key);
+        }
+        if is_empty {
+            self.notify("del", key);
+        }
+
+        Ok(Some(popped))
+    }
+
+    /// 设置哈希中字段的值，返回新添加的字段数量
+    /// 键不存在时创建一个新的哈希，字段较多或值较长时哈希的编码由 `listpack` 转为 `hashtable`
+    pub(crate) fn hset(&self, key: String, pairs: Vec<(Bytes, Bytes)>) -> crate::Result<usize> {
+        let mut state = self.shared.write(&key);
+
+        if !state.entries.contains_key(&key) {
+            state.insert(key.clone(), Value::Hash(crate::hash::Hash::new()), None);
+        }
+
+        let added = match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::Hash(hash)) => pairs.into_iter().filter(|(field, value)| hash.insert(field.clone(), value.clone())).count(),
+            _ => return Err(WRONGTYPE.into()),
+        };
+
+        drop(state);
+
+        self.notify("hset", &key);
+
+        Ok(added)
+    }
+
+    /// 返回哈希中字段的值，键或字段不存在时返回 `None`
+    pub(crate) fn hget(&self, key: &str, field: &[u8]) -> crate::Result<Option<Bytes>> {
+        let state = self.shared.read(key);
+
+        match state.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Hash(hash)) => Ok(hash.get(field).cloned()),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    /// 向集合中添加成员，返回新添加的成员数量
+    /// 键不存在时创建一个新的集合
+    pub(crate) fn sadd(&self, key: String, members: Vec<Bytes>) -> crate::Result<usize> {
+        let mut state = self.shared.write(&key);
+
+        if !state.entries.contains_key(&key) {
+            state.insert(key.clone(), Value::Set(HashSet::new()), None);
+        }
+
+        let added = match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::Set(set)) => members.into_iter().filter(|member| set.insert(member.clone())).count(),
+            _ => return Err(WRONGTYPE.into()),
+        };
+
+        drop(state);
+
+        if added > 0 {
+            self.notify("sadd", &key);
+        }
+
+        Ok(added)
+    }
+
+    /// 返回集合的所有成员，键不存在时返回空列表
+    pub(crate) fn smembers(&self, key: &str) -> crate::Result<Vec<Bytes>> {
+        let state = self.shared.read(key);
+
+        Ok(state
+            .get_set(key)?
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// 对 `keys` 对应的集合做集合运算，不存在的键视为空集合
+    pub(crate) fn set_operation(&self, op: SetOperation, keys: &[String]) -> crate::Result<Vec<Bytes>> {
+        let shards = self.shared.read_keys(keys.iter().map(|key| &key[..]));
+
+        Ok(shards.set_operation(op, keys)?.into_iter().collect())
+    }
+
+    /// 返回 `keys` 对应的集合的交集的成员数量，`limit` 不为 0 时数到 `limit` 即停止
+    /// 与 `set_operation` 相同，遍历最小的集合，不构建交集
+    pub(crate) fn sintercard(&self, keys: &[String], limit: usize) -> crate::Result<usize> {
+        let shards = self.shared.read_keys(keys.iter().map(|key| &key[..]));
+
+        let limit = if limit == 0 { usize::MAX } else { limit };
+
+        Ok(shards.intersection(keys, limit)?.0.len())
+    }
+
+    /// 求 `keys` 对应的集合的交集时遍历的成员数量，由 `DEBUG INTERSECTION` 使用，
+    /// 用于确认只遍历了最小的集合
+    pub(crate) fn intersection_iterations(&self, keys: &[String]) -> crate::Result<usize> {
+        let shards = self.shared.read_keys(keys.iter().map(|key| &key[..]));
+
+        Ok(shards.intersection(keys, usize::MAX)?.1)
+    }
+
+    /// 对 `keys` 对应的集合做集合运算，并将结果保存至 `destination`，返回结果的成员数量
+    /// `destination` 原有的值（无论何种类型）会被覆盖，结果为空集时删除 `destination`
+    ///
+    /// 运算与保存在同一次加锁中完成，涉及的分片同时加锁
+    pub(crate) fn set_operation_store(
+        &self,
+        op: SetOperation,
+        destination: String,
+        keys: &[String],
+    ) -> crate::Result<usize> {
+        let mut shards = self.shared.write_keys(keys.iter().chain([&destination]).map(|key| &key[..]));
+
+        let result = shards.set_operation(op, keys)?;
+        let len = result.len();
+
+        let state = shards.state_mut(&destination);
+        let event = if result.is_empty() {
+            state.remove(&destination).map(|_| "del")
+        } else {
+            state.insert(destination.clone(), Value::Set(result), None);
+            Some(op.store_event())
+        };
+
+        drop(shards);
+
+        if let Some(event) = event {
+            self.notify(event, &destination);
+        }
+
+        Ok(len)
+    }
+
+    /// 向 HyperLogLog 中添加元素，估算的基数可能改变时返回 `true`
+    /// 键不存在时创建一个新的 HyperLogLog，此时即使没有元素也返回 `true`
+    pub(crate) fn pfadd(&self, key: String, elements: Vec<Bytes>) -> crate::Result<bool> {
+        let mut state = self.shared.write(&key);
+
+        let mut changed = false;
+        if !state.entries.contains_key(&key) {
+            state.insert(key.clone(), Value::Hll(HyperLogLog::new()), None);
+            changed = true;
+        }
+
+        match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::Hll(hll)) => {
+                for element in &elements {
+                    changed |= hll.insert(element);
+                }
+            },
+            _ => return Err(WRONGTYPE.into()),
+        }
+
+        drop(state);
+
+        if changed {
+            self.notify("pfadd", &key);
+        }
+
+        Ok(changed)
+    }
+
+    /// 合并 `keys` 对应的 HyperLogLog 并返回估算的基数，不存在的键视为空
+    pub(crate) fn pfcount(&self, keys: &[String]) -> crate::Result<u64> {
+        let shards = self.shared.read_keys(keys.iter().map(|key| &key[..]));
+
+        let mut merged: Option<HyperLogLog> = None;
+        for key in keys {
+            match shards.state(key).entries.get(key).map(|entry| &entry.value) {
+                Some(Value::Hll(hll)) => match &mut merged {
+                    Some(merged) => merged.merge(hll),
+                    None => merged = Some(hll.clone()),
+                },
+                Some(_) => return Err(WRONGTYPE.into()),
+                None => {},
+            }
+        }
+
+        Ok(merged.map(|hll| hll.count()).unwrap_or(0))
+    }
+
+    /// 向有序集合中添加成员或更新已有成员的分值，返回新添加的成员数量
+    /// 键不存在时创建一个新的有序集合
+    pub(crate) fn zadd(&self, key: String, members: Vec<(f64, Bytes)>) -> crate::Result<usize> {
+        let mut state = self.shared.write(&key);
+
+        if !state.entries.contains_key(&key) {
+            state.insert(key.clone(), Value::ZSet(SortedSet::new()), None);
+        }
+
+        let added = match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::ZSet(zset)) => members
+                .into_iter()
+                .filter(|(score, member)| zset.insert(member.clone(), *score))
+                .count(),
+            _ => return Err(WRONGTYPE.into()),
+        };
+
+        drop(state);
+
+        self.notify("zadd", &key);
+
+        Ok(added)
+    }
+
+    /// 从有序集合中删除成员，返回实际删除的成员数量
+    /// 有序集合被删空时同时删除该键
+    pub(crate) fn zrem(&self, key: &str, members: &[Bytes]) -> crate::Result<usize> {
+        let mut state = self.shared.write(key);
+
+        let (removed, is_empty) = match state.entries.get_mut(key).map(|entry| &mut entry.value) {
+            Some(Value::ZSet(zset)) => {
+                let removed = members.iter().filter(|member| zset.remove(member)).count();
+                (removed, zset.is_empty())
+            },
+            Some(_) => return Err(WRONGTYPE.into()),
+            None => return Ok(0),
+        };
+
+        if is_empty {
+            state.remove(key);
+        }
+
+        drop(state);
+
+        if removed > 0 {
+            self.notify("zrem", key);
+        }
+        if is_empty {
+            self.notify("del", key);
+        }
+
+        Ok(removed)
+    }
+
+    /// 返回有序集合中成员的分值，键或成员不存在时返回 `None`
+    pub(crate) fn zscore(&self, key: &str, member: &Bytes) -> crate::Result<Option<f64>> {
+        let state = self.shared.read(key);
+
+        Ok(state.get_zset(key)?.and_then(|zset| zset.score(member)))
+    }
+
+    /// 返回成员按分值从小到大的排名（从 0 开始），键或成员不存在时返回 `None`
+    pub(crate) fn zrank(&self, key: &str, member: &Bytes) -> crate::Result<Option<usize>> {
+        let state = self.shared.read(key);
+
+        Ok(state.get_zset(key)?.and_then(|zset| zset.rank(member)))
+    }
+
+    /// 为有序集合中成员的分值加上 `increment`，返回新的分值
+    /// 键或成员不存在时，视其分值为 0 并创建
+    pub(crate) fn zincrby(&self, key: String, increment: f64, member: Bytes) -> crate::Result<f64> {
+        let mut state = self.shared.write(&key);
+
+        // 先检查类型，避免结果为 `NaN` 时留下一个空的有序集合
+        let score = match state.entries.get_mut(&key).map(|entry| &mut entry.value) {
+            Some(Value::ZSet(zset)) => zset.incr(member, increment).ok_or(NAN_SCORE)?,
+            Some(_) => return Err(WRONGTYPE.into()),
+            None => {
+                let mut zset = SortedSet::new();
+                let score = zset.incr(member, increment).ok_or(NAN_SCORE)?;
+                state.insert(key.clone(), Value::ZSet(zset), None);
+                score
+            },
+        };
+
+        drop(state);
+
+        self.notify("zincr", &key);
+
+        Ok(score)
+    }
+
+    /// 按分值从小到大返回分值在 `min` 与 `max` 之间的成员及其分值
+    /// `limit` 为 (偏移量, 数量)，数量为 `None` 时返回偏移量之后的所有成员
+    pub(crate) fn zrangebyscore(
+        &self,
+        key: &str,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        limit: Option<(usize, Option<usize>)>,
+    ) -> crate::Result<Vec<(Bytes, f64)>> {
+        let state = self.shared.read(key);
+
+        let zset = match state.get_zset(key)? {
+            Some(zset) => zset,
+            None => return Ok(vec![]),
+        };
+
+        let (offset, count) = limit.unwrap_or((0, None));
+
+        Ok(zset
+            .range_by_score(min, max)
+            .skip(offset)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
+    }
+
+    /// 从 `cursor` 开始遍历键空间，返回下一次调用使用的游标及本次遍历到且匹配 `pattern` 的键
+    /// 游标为 0 时从头开始，返回的游标为 0 时遍历结束
+    ///
+    /// 游标的高 `SHARD_BITS` 位为分片下标，其余位为分片内的位置，分片内的键按 `scan_position`
+    /// 从小到大遍历。与插入顺序无关，整个遍历期间一直存在的键恰好返回一次，
+    /// 遍历期间新增或删除的键可能返回也可能不返回。
+    /// `count` 是每次至少检查的键数量（剩余的键足够时），匹配 `pattern` 前计数，
+    /// 位置相同的键总在同一次中返回
+    pub(crate) fn scan(&self, cursor: u64, pattern: Option<&str>, count: usize) -> (u64, Vec<String>) {
+        let shift = 64 - SHARD_BITS;
+        let mut shard = (cursor >> shift) as usize;
+        let mut position = cursor & (u64::MAX >> SHARD_BITS);
+
+        let mut keys = vec![];
+        let mut next = 0;
+
+        while shard < SHARDS {
+            let state = self.shared.shards[shard].read().unwrap();
+
+            let mut found: Vec<(u64, &String)> = state
+                .entries
+                .keys()
+                .map(|key| (scan_position(key), key))
+                .filter(|(pos, _)| *pos >= position)
+                .collect();
+            found.sort_unstable();
+
+            // 取出还需要的数量，位置与最后一个相同的键一并取出
+            let wanted = count.saturating_sub(keys.len()).max(1);
+            let end = match found.get(wanted - 1) {
+                Some(&(last, _)) => found.partition_point(|(pos, _)| *pos <= last),
+                None => found.len(),
+            };
+            keys.extend(found[..end].iter().map(|(_, key)| (*key).clone()));
+
+            // 本分片还有剩余的键，下次从剩余的第一个键继续
+            if let Some(&(pos, _)) = found.get(end) {
+                next = ((shard as u64) << shift) | pos;
+                break;
+            }
+
+            shard += 1;
+            position = 0;
+            if shard < SHARDS && keys.len() >= count {
+                next = (shard as u64) << shift;
+                break;
+            }
+        }
+
+        if let Some(pattern) = pattern {
+            keys.retain(|key| glob::matches(pattern.as_bytes(), key.as_bytes()));
+        }
+
+        (next, keys)
+    }
+
+    /// 估算一个键及其值占用的内存（字节），键不存在时返回 `None`
+    pub(crate) fn memory_usage(&self, key: &str) -> Option<usize> {
+        let state = self.shared.read(key);
+        state.entries.get(key).map(|entry| entry.memory_usage(key))
+    }
+
+    /// 返回所有匹配 `pattern` 的键
+    ///
+    /// 需要遍历所有的键，复杂度为 O(n)，与模式是否有固定前缀无关
+    /// 持有锁时只复制一份键名，释放锁后再做匹配，避免键很多时长时间阻塞其它连接
+    /// 各分片依次加锁，结果不是同一时刻的快照
+    pub(crate) fn keys(&self, pattern: &str) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for shard in self.shared.shards.iter() {
+            let state = shard.read().unwrap();
+            keys.extend(state.entries.keys().cloned());
+        }
+
+        keys.into_iter()
+            .filter(|key| glob::matches(pattern.as_bytes(), key.as_bytes()))
+            .collect()
+    }
+
+    /// 统计整个数据库的键数量与估算的内存占用
+    ///
+    /// 需要遍历所有条目，复杂度为 O(n)，各分片依次加锁
+    pub(crate) fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats { keys: 0, bytes: 0 };
+
+        for shard in self.shared.shards.iter() {
+            let state = shard.read().unwrap();
+
+            stats.keys += state.entries.len();
+            stats.bytes += state
+                .entries
+                .iter()
+                .map(|(key, entry)| entry.memory_usage(key))
+                .sum::<usize>();
+        }
+
+        stats
+    }
+
+    /// 按内部编码统计键的数量，由 `DEBUG OBJECT-STATS` 使用
+    /// 与 `memory_stats` 相同，依次统计每个分片
+    pub(crate) fn encoding_histogram(&self) -> HashMap<&'static str, u64> {
+        let mut histogram = HashMap::new();
+
+        for shard in self.shared.shards.iter() {
+            let state = shard.read().unwrap();
+
+            for entry in state.entries.values() {
+                *histogram.entry(entry.value.encoding()).or_insert(0) += 1;
+            }
+        }
+
+        histogram
+    }
+
+    /// 有有效期的键的数量，及距最近的过期时刻的时长（已过期但尚未清理时为 0），由 `DEBUG EXPIRES` 使用
+    /// 与 `encoding_histogram` 相同，依次统计每个分片
+    pub(crate) fn expiration_stats(&self) -> (u64, Option<Duration>) {
+        let mut count = 0;
+        let mut nearest: Option<Instant> = None;
+
+        for shard in self.shared.shards.iter() {
+            let state = shard.read().unwrap();
+
+            count += state.expirations.len() as u64;
+            if let Some(&(when, _)) = state.expirations.keys().next() {
+                nearest = Some(nearest.map_or(when, |nearest| nearest.min(when)));
+            }
+        }
+
+        (count, nearest.map(|when| when.saturating_duration_since(Instant::now())))
+    }
+
+    /// 检查有效期相关的内部数据是否一致，由 `DEBUG INVARIANTS` 使用
+    /// 返回发现的第一个不一致之处
+    pub(crate) fn check_invariants(&self) -> Result<(), String> {
+        for (index, shard) in self.shared.shards.iter().enumerate() {
+            shard.read().unwrap().check_invariants(index)?;
+        }
+
+        Ok(())
+    }
+
+    /// 每个频道的订阅者数量及频道缓存中尚未被所有订阅者取走的消息数量，按频道名称排序
+    /// 由 `DEBUG CHANNELS` 使用，缓存中的消息接近 `CHANNEL_CAPACITY` 说明有订阅者落后
+    pub(crate) fn channel_stats(&self) -> Vec<(String, usize, usize)> {
+        let pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        let mut stats: Vec<_> = pub_sub
+            .channels
+            .iter()
+            .map(|(name, channel)| (name.clone(), channel.tx.receiver_count(), channel.tx.len()))
+            .collect();
+        stats.sort_unstable();
+
+        stats
+    }
+
+    /// 将数据库保存为快照文件
+    /// 先写入临时文件再重命名，避免中途失败时留下不完整的文件
+    pub(crate) fn save(&self, path: &Path) -> crate::Result<()> {
+        // 只在编码时持有锁，写文件时不阻塞其它连接
+        // 所有分片同时加锁，保存的是同一时刻的数据
+        let snapshot = {
+            let shards = self.shared.read_all();
+            snapshot::encode(&shards, Instant::now(), SystemTime::now())
+        };
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &snapshot)?;
+        fs::rename(&tmp, path)?;
+
+        self.shared.last_save.store(unix_seconds(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// 最近一次成功保存快照的 Unix 时间（秒），由 `LASTSAVE` 返回
+    pub(crate) fn last_save(&self) -> i64 {
+        self.shared.last_save.load(Ordering::Relaxed)
+    }
+
+    /// 从快照文件中恢复数据，返回恢复的键数量，文件不存在时返回 0
+    pub(crate) fn load(&self, path: &Path) -> crate::Result<usize> {
+        let src = match fs::read(path) {
+            Ok(src) => src,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        let entries = snapshot::decode(&src, Instant::now(), SystemTime::now())?;
+        let restored = entries.len();
+
+        for (key, value, expires_at) in entries {
+            self.shared.write(&key).insert(key, value, expires_at);
+        }
+
+        // 恢复的键可能带有过期时间，通知后台任务重新计算
+        self.shared.background_task.notify_one();
+
+        Ok(restored)
+    }
+
+    /// 记录一条慢命令
+    pub(crate) fn slowlog_push(&self, args: Vec<Bytes>, duration: Duration, addr: Option<SocketAddr>) {
+        self.shared.slowlog.lock().unwrap().push(args, duration, addr);
+    }
+
+    /// 返回最近的 `count` 条慢命令记录，`None` 时返回全部
+    pub(crate) fn slowlog_get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        self.shared.slowlog.lock().unwrap().get(count)
+    }
+
+    /// 返回慢命令记录的条数
+    pub(crate) fn slowlog_len(&self) -> usize {
+        self.shared.slowlog.lock().unwrap().len()
+    }
+
+    /// 清空慢命令记录
+    pub(crate) fn slowlog_reset(&self) {
+        self.shared.slowlog.lock().unwrap().reset();
+    }
+
+    /// 请求订阅一个频道，返回一个 `Reciever` 来接收此频道发送的广播
+    pub(crate) fn subscribe(&self, key: String) -> Subscription {
+        // 先获取锁
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        // 当前无此频道时创建一个并加入
+        let channel = pub_sub.channels.entry(key).or_insert_with(|| Channel {
+            tx: broadcast::channel(CHANNEL_CAPACITY).0,
+            space: Arc::new(Space::default()),
+        });
+
+        Subscription {
+            rx: channel.tx.subscribe(),
+            space: SpaceNotify(channel.space.clone()),
+        }
+    }
+
+    /// 按模式订阅频道，返回一个 `Receiver` 接收名称匹配 `pattern` 的频道发送的 (频道, 消息)
+    /// 模式订阅者不参与 `publish_wait` 的等待，落后时同样会丢失消息
+    pub(crate) fn psubscribe(&self, pattern: String) -> broadcast::Receiver<(String, Published)> {
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        pub_sub
+            .patterns
+            .entry(pattern)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// 向广播中发送数据，并返回此频道及匹配的模式的订阅者的数量
+    /// 缓存已满时丢弃最早的消息，落后的订阅者会丢失这些消息
+    ///
+    /// 持有锁时只确定要发送给哪些 `Sender`，释放锁后再发送，订阅者很多时不会阻塞
+    /// 其它频道的发布与订阅。开启消息序号时仍在锁内发送，保证订阅者按序号收到消息
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        let delivery = {
+            let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+            if pub_sub.sequences.is_some() {
+                return pub_sub.publish(key, value);
+            }
+            pub_sub.prepare(key, value)
+        };
+
+        delivery.send(key)
+    }
+
+    /// 与 `publish` 相同，但缓存已满或有订阅者的待发送数据超过软上限时等待，不会丢弃消息
+    ///
+    /// 代价是发布者的速度受限于最慢的订阅者，订阅者一直不读取时会一直等待；
+    /// 同一频道上不等待的 `publish` 仍可能使落后的订阅者丢失消息
+    pub(crate) async fn publish_wait(&self, key: &str, value: Bytes) -> usize {
+        loop {
+            let space = match self.shared.pub_sub.lock().unwrap().channels.get(key) {
+                Some(channel) => channel.space.clone(),
+                // 没有直接订阅此频道的订阅者，只需发送给模式订阅者
+                None => return self.publish(key, value),
+            };
+
+            // 先注册通知再检查缓存，避免错过检查之后、等待之前发出的通知
+            let notified = space.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            {
+                let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+                let channel = &pub_sub.channels[key];
+                let congested = channel.space.congested.load(Ordering::Acquire) > 0;
+                if channel.tx.receiver_count() == 0 || (channel.tx.len() < CHANNEL_CAPACITY && !congested) {
+                    return pub_sub.publish(key, value);
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    /// 一次加锁向多个频道发送消息，返回每条消息的订阅者数量
+    /// 用于一次修改需要发送多条通知的情况，如键空间通知
+    pub(crate) fn publish_batch(&self, msgs: &[(String, Bytes)]) -> Vec<usize> {
+        self.shared.publish_batch(msgs)
+    }
+
+    /// 开启或关闭消息序号，关闭时清除所有频道的序号
+    pub(crate) fn set_message_sequence(&self, enabled: bool) {
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        if enabled != pub_sub.sequences.is_some() {
+            pub_sub.sequences = enabled.then(HashMap::new);
+        }
+    }
+
+    /// 开启或关闭键空间通知
+    pub(crate) fn set_keyspace_events(&self, enabled: bool) {
+        self.shared.keyspace_events.store(enabled, Ordering::Relaxed);
+    }
+
+    /// 回复中单个 bulk 的最大字节数
+    pub(crate) fn proto_max_bulk_len(&self) -> usize {
+        self.shared.proto_max_bulk_len.load(Ordering::Relaxed)
+    }
+
+    /// 修改回复中单个 bulk 的最大字节数
+    pub(crate) fn set_proto_max_bulk_len(&self, len: usize) {
+        self.shared.proto_max_bulk_len.store(len, Ordering::Relaxed);
+    }
+
+    /// 每个连接每秒最多执行的命令数量，`None` 表示不限制
+    pub(crate) fn rate_limit(&self) -> Option<u64> {
+        match self.shared.rate_limit.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// 修改每个连接每秒最多执行的命令数量，`None` 或 0 表示不限制
+    pub(crate) fn set_rate_limit(&self, limit: Option<u64>) {
+        self.shared.rate_limit.store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// 写入时未指定有效期的键使用的有效期
+    pub(crate) fn default_ttl(&self) -> Option<Duration> {
+        match self.shared.default_ttl.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// 修改写入时未指定有效期的键使用的有效期，`None` 表示不过期
+    pub(crate) fn set_default_ttl(&self, ttl: Option<Duration>) {
+        // 不足 1 毫秒的有效期按 1 毫秒保存，以免与 0 混淆
+        let millis = ttl.map_or(0, |ttl| (ttl.as_millis() as u64).max(1));
+        self.shared.default_ttl.store(millis, Ordering::Relaxed);
+    }
+
+    fn keyspace_events_enabled(&self) -> bool {
+        self.shared.keyspace_events_enabled()
+    }
+
+    /// 发送键空间通知，每个修改数据的方法在释放锁之后调用
+    /// 新增修改数据的命令时，需在此发送对应的事件
+    fn notify(&self, event: &str, key: &str) {
+        self.shared.notify(event, key);
+    }
+
+    /// 改变 shutdown 标志位后通知清理任务，其在一个 while 循环中会退出
+    fn shutdown_purge_task(&self) {
+        self.shared.shutdown_purge_task();
+    }
+}
+
+impl Drop for Handles {
+    fn drop(&mut self) {
+        self.shared.shutdown_purge_task();
+    }
+}
+
+impl Shared {
+    /// 改变 shutdown 标志位后通知清理任务，其在一个 while 循环中会退出
+    fn shutdown_purge_task(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        self.background_task.notify_one();
+    }
+
+    /// 清除所有的已过期的键，并返回最近的将过期的时间
+    /// 后台任务将休眠到过期时间再执行清理任务
+    fn purge_expired_keys(&self) -> Option<Instant> {
+        if self.is_shutdown() {
+            return None;
+        }
+
+        let now = Instant::now();
+
+        // 开启键空间通知时记录清理的键，释放锁后再发送 `expired` 事件
+        let mut expired = self.keyspace_events_enabled().then(Vec::new);
+        let mut next: Option<Instant> = None;
+
+        // 依次清理每个分片，同一时刻只持有一个分片的锁
+        for shard in self.shards.iter() {
+            let shard_next = shard.write().unwrap().purge_expired_keys(now, expired.as_mut());
+
+            next = match (next, shard_next) {
+                (Some(next), Some(shard_next)) => Some(next.min(shard_next)),
+                (next, shard_next) => next.or(shard_next),
+            };
+        }
+
+        for key in expired.into_iter().flatten() {
+            self.notify("expired", &key);
+        }
+
+        next
+    }
+
+    /// 一次加锁向多个频道发送消息，返回每条消息的订阅者数量
+    fn publish_batch(&self, msgs: &[(String, Bytes)]) -> Vec<usize> {
+        let mut pub_sub = self.pub_sub.lock().unwrap();
+
+        if pub_sub.sequences.is_some() {
+            return msgs.iter()
+                .map(|(channel, value)| pub_sub.publish(channel, value.clone()))
+                .collect();
+        }
+
+        let deliveries: Vec<_> = msgs.iter()
+            .map(|(channel, value)| pub_sub.prepare(channel, value.clone()))
+            .collect();
+        drop(pub_sub);
+
+        deliveries.into_iter()
+            .zip(msgs)
+            .map(|(delivery, (channel, _))| delivery.send(channel))
+            .collect()
+    }
+
+    fn keyspace_events_enabled(&self) -> bool {
+        self.keyspace_events.load(Ordering::Relaxed)
+    }
+
+    /// 开启键空间通知时，为键的修改发送两条通知
+    /// `__keyspace@0__:<key>` 频道收到事件名，`__keyevent@0__:<event>` 频道收到键名
+    fn notify(&self, event: &str, key: &str) {
+        if !self.keyspace_events_enabled() {
+            return;
+        }
+
+        self.publish_batch(&[
+            (format!("__keyspace@0__:{}", key), Bytes::copy_from_slice(event.as_bytes())),
+            (format!("__keyevent@0__:{}", event), Bytes::copy_from_slice(key.as_bytes())),
+        ]);
+    }
+
+    /// 当数据库关闭时，返回 `true`
+    fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    /// 对键所在的分片加读锁，只读取键的命令使用，可以同时执行
+    fn read(&self, key: &str) -> RwLockReadGuard<'_, State> {
+        self.shards[shard_index(key)].read().unwrap()
+    }
+
+    /// 对键所在的分片加写锁
+    fn write(&self, key: &str) -> RwLockWriteGuard<'_, State> {
+        self.shards[shard_index(key)].write().unwrap()
+    }
+
+    /// 对 `keys` 所在的所有分片加读锁
+    fn read_keys<'k>(&self, keys: impl IntoIterator<Item = &'k str>) -> Shards<RwLockReadGuard<'_, State>> {
+        self.lock_keys(keys, |shard| shard.read().unwrap())
+    }
+
+    /// 对 `keys` 所在的所有分片加写锁
+    fn write_keys<'k>(&self, keys: impl IntoIterator<Item = &'k str>) -> Shards<RwLockWriteGuard<'_, State>> {
+        self.lock_keys(keys, |shard| shard.write().unwrap())
+    }
+
+    /// 对 `keys` 所在的所有分片加锁，按分片下标升序加锁避免死锁
+    fn lock_keys<'s, 'k, G>(
+        &'s self,
+        keys: impl IntoIterator<Item = &'k str>,
+        lock: impl Fn(&'s RwLock<State>) -> G,
+    ) -> Shards<G> {
+        let mut locked = [false; SHARDS];
+        for key in keys {
+            locked[shard_index(key)] = true;
+        }
+
+        let guards = self
+            .shards
+            .iter()
+            .zip(locked)
+            .map(|(shard, locked)| locked.then(|| lock(shard)))
+            .collect();
+
+        Shards { guards }
+    }
+
+    /// 按分片下标升序对所有分片加读锁
+    fn read_all(&self) -> Vec<RwLockReadGuard<'_, State>> {
+        self.shards.iter().map(|shard| shard.read().unwrap()).collect()
+    }
+}
+
+impl Subscription {
+    /// 接收下一条消息，取走消息后唤醒等待缓存空间的发布者
+    pub(crate) async fn recv(&mut self) -> Result<Published, broadcast::error::RecvError> {
+        let msg = self.rx.recv().await;
+        self.space.0.notify.notify_waiters();
+        msg
+    }
+
+    /// 用于标记此订阅者积压的句柄，初始时未标记
+    pub(crate) fn congestion(&self) -> Congestion {
+        Congestion {
+            space: self.space.0.clone(),
+            congested: false,
+        }
+    }
+}
+
+impl Congestion {
+    /// 标记或取消标记，取消时唤醒等待的发布者
+    pub(crate) fn set(&mut self, congested: bool) {
+        if self.congested == congested {
+            return;
+        }
+        self.congested = congested;
+
+        if congested {
+            self.space.congested.fetch_add(1, Ordering::AcqRel);
+        } else {
+            self.space.congested.fetch_sub(1, Ordering::AcqRel);
+            self.space.notify.notify_waiters();
+        }
+    }
+}
+
+impl Drop for Congestion {
+    fn drop(&mut self) {
+        self.set(false);
+    }
+}
+
+impl Drop for SpaceNotify {
+    /// 取消订阅后，此订阅者未读取的消息不再占用缓存
+    fn drop(&mut self) {
+        self.0.notify.notify_waiters();
+    }
+}
+
+impl Entry {
+    /// 估算条目占用的内存：键名、数据，以及条目自身和键 `String` 的开销
+    fn memory_usage(&self, key: &str) -> usize {
+        mem::size_of::<String>() + key.len() + mem::size_of::<Entry>() + self.value.memory_usage()
+    }
+}
+
+impl Value {
+    /// 创建字符串值，能表示为整数且格式化后不变的字符串使用 `int` 编码保存
+    fn string(data: Bytes) -> Value {
+        // i64 格式化后最长 20 个字节
+        if data.len() <= 20 {
+            let value = std::str::from_utf8(&data).ok().and_then(|data| data.parse::<i64>().ok());
+            // 排除 `+1`、`01`、`-0` 等格式化后会改变的写法
+            if let Some(value) = value.filter(|value| value.to_string().as_bytes() == &data[..]) {
+                return Value::Int(value);
+            }
+        }
+
+        Value::String(data)
+    }
+
+    /// 值的类型，由 `TYPE` 返回
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) | Value::Int(_) => "string",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+            // Redis 中 HyperLogLog 以字符串保存
+            Value::Hll(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+        }
+    }
+
+    /// 值的内部编码，由 `OBJECT ENCODING` 返回
+    /// 与 Redis 不同，这里不区分 `embstr` 与 `raw`
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(_) => "raw",
+            Value::Int(_) => "int",
+            Value::Set(_) => "hashtable",
+            Value::ZSet(_) => "skiplist",
+            // Redis 中 HyperLogLog 以字符串保存
+            Value::Hll(_) => "raw",
+            Value::List(_) => "quicklist",
+            Value::Hash(hash) => hash.encoding(),
+        }
+    }
+
+    /// 估算值的数据占用的内存，集合中的每个成员还要加上 `Bytes` 自身的开销
+    fn memory_usage(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::Int(_) => 0,
+            Value::Set(set) => set
+                .iter()
+                .map(|member| mem::size_of::<Bytes>() + member.len())
+                .sum(),
+            Value::ZSet(zset) => zset.memory_usage(),
+            Value::Hll(hll) => hll.memory_usage(),
+            Value::List(list) => list
+                .iter()
+                .map(|element| mem::size_of::<Bytes>() + element.len())
+                .sum(),
+            Value::Hash(hash) => hash.memory_usage(),
+        }
+    }
+}
+
+impl State {
+    /// 插入新条目，并返回被替换的旧条目
+    /// 旧条目的有效期会从清理列表中去除
+    fn insert(&mut self, key: String, value: Value, expires_at: Option<Instant>) -> Option<Entry> {
+        let id = self.next_id(expires_at);
+
+        if let Some(when) = expires_at {
+            self.expirations.insert((when, id), key.clone());
+        }
+
+        // 将新条目添加到 `HashMap` 中，并得到旧的条目
+        let prev = self.entries.insert(key, Entry { id, value, expires_at });
+
+        // 若替换了旧的 `Entry`，则需将其从有效期清理列表中去除
+        if let Some(prev) = &prev {
+            if let Some(when) = prev.expires_at {
+                self.expirations.remove(&(when, prev.id));
+            }
+        }
+
+        prev
+    }
+
+    /// 修改已有条目的过期时间，键不存在时返回 `false`
+    /// 原有的清理记录被删除，并以新分配的 id 记录新的过期时间
+    fn set_expires_at(&mut self, key: &str, when: Instant) -> bool {
+        if !self.entries.contains_key(key) {
+            return false;
+        }
+
+        let id = self.next_id(Some(when));
+        let entry = self.entries.get_mut(key).unwrap();
+
+        if let Some(prev) = entry.expires_at {
+            self.expirations.remove(&(prev, entry.id));
+        }
+        entry.id = id;
+        entry.expires_at = Some(when);
+        self.expirations.insert((when, id), key.to_string());
+
+        true
+    }
+
+    /// 清除已有条目的过期时间及其清理记录，条目原本有过期时间时返回 `true`
+    fn clear_expires_at(&mut self, key: &str) -> bool {
+        let entry = match self.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        match entry.expires_at.take() {
+            Some(when) => {
+                self.expirations.remove(&(when, entry.id));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 分配新条目的 id
+    /// id 回绕后可能与仍在等待清理的条目相同，此时 `expirations` 中的记录会被覆盖，
+    /// 被覆盖的键将不再过期，故跳过与相同有效期的已有记录冲突的 id
+    fn next_id(&mut self, expires_at: Option<Instant>) -> u64 {
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+
+            match expires_at {
+                Some(when) if self.expirations.contains_key(&(when, id)) => continue,
+                _ => return id,
+            }
+        }
+    }
+
+    /// 清理有效期不晚于 `now` 的键，返回本分片中下一个键的过期时间
+    /// `expired` 不为 `None` 时记录被清理的键
+    fn purge_expired_keys(&mut self, now: Instant, mut expired: Option<&mut Vec<String>>) -> Option<Instant> {
+        while let Some((&(when, id), key)) = self.expirations.iter().next() {
+            if when > now {
+                return Some(when);
+            }
+
+            // 清理已过期的键
+            self.entries.remove(key);
+            let key = self.expirations.remove(&(when, id));
+            if let (Some(expired), Some(key)) = (expired.as_deref_mut(), key) {
+                expired.push(key);
+            }
+        }
+
+        None
+    }
+
+    /// 检查本分片的数据是否一致，`index` 为本分片的下标
+    /// - 每个键都属于本分片
+    /// - 有有效期的条目在 `expirations` 中有对应的记录，反之亦然，
+    ///   因此有有效期的条目的 (有效期, id) 互不相同
+    ///
+    /// `next_id` 会回绕，回绕后的 id 可能小于已有条目的 id，故不比较两者
+    fn check_invariants(&self, index: usize) -> Result<(), String> {
+        for (key, entry) in &self.entries {
+            if shard_index(key) != index {
+                return Err(format!("key '{}' is stored in shard {} instead of {}", key, index, shard_index(key)));
+            }
+
+            if let Some(when) = entry.expires_at {
+                if self.expirations.get(&(when, entry.id)) != Some(key) {
+                    return Err(format!("key '{}' has an expiry without a matching expiration record", key));
+                }
+            }
+        }
+
+        for ((when, id), key) in &self.expirations {
+            match self.entries.get(key) {
+                Some(entry) if entry.id == *id && entry.expires_at == Some(*when) => {},
+                Some(_) => return Err(format!("expiration record for key '{}' does not match its entry", key)),
+                None => return Err(format!("expiration record for missing key '{}'", key)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 删除条目，并将其从有效期清理列表中去除
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let prev = self.entries.remove(key)?;
+
+        if let Some(when) = prev.expires_at {
+            self.expirations.remove(&(when, prev.id));
+        }
+
+        Some(prev)
+    }
+
+    /// 查找键对应的集合，键存储的不是集合时返回 `WRONGTYPE` 错误
+    fn get_set(&self, key: &str) -> crate::Result<Option<&HashSet<Bytes>>> {
+        match self.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::Set(set)) => Ok(Some(set)),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    /// 查找键对应的有序集合，键存储的不是有序集合时返回 `WRONGTYPE` 错误
+    fn get_zset(&self, key: &str) -> crate::Result<Option<&SortedSet>> {
+        match self.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::ZSet(zset)) => Ok(Some(zset)),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    /// 若新的有效期早于本分片最早的有效期，返回 `true`，此时需要通知后台任务
+    /// 早于所有分片中最早的有效期时必然早于本分片的，因此不会漏掉通知，最多多通知一次
+    fn is_next_expiration(&self, expires_at: Option<Instant>) -> bool {
+        expires_at
+            .map(|when| {
+                self.next_expiration()
+                    .map(|expiration| expiration > when)
+                    .unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+
+    /// 下一个临近键的过期时间
+    fn next_expiration(&self) -> Option<Instant> {
+        self.expirations
+            .keys()
+            .next()
+            .map(|expiration| expiration.0)
+    }
+}
+
+impl PubSub {
+    /// 向频道及名称匹配的模式发送消息，返回收到消息的订阅者数量
+    /// 开启消息序号时，即使没有订阅者，频道的序号也会递增
+    fn publish(&mut self, key: &str, value: Bytes) -> usize {
+        self.prepare(key, value).send(key)
+    }
+
+    /// 分配消息序号并找出需要发送的频道及模式，返回的 `Delivery` 可以在释放锁后发送
+    fn prepare(&mut self, key: &str, value: Bytes) -> Delivery {
+        let seq = self.sequences.as_mut().map(|sequences| {
+            let seq = sequences.entry(key.to_string()).or_insert(0);
+            *seq += 1;
+            *seq
+        });
+
+        let channel = self.channels.get(key).map(|channel| channel.tx.clone());
+        let patterns = self
+            .patterns
+            .iter()
+            // 所有订阅者都已取消订阅的模式无需再匹配
+            .filter(|(pattern, tx)| tx.receiver_count() > 0 && glob::matches(pattern.as_bytes(), key.as_bytes()))
+            .map(|(_, tx)| tx.clone())
+            .collect();
+
+        Delivery {
+            msg: Published { seq, content: value },
+            channel,
+            patterns,
+        }
+    }
+}
+
+/// 一条已确定接收方的消息，`Sender` 的复制只是增加引用计数
+struct Delivery {
+    msg: Published,
+    channel: Option<broadcast::Sender<Published>>,
+    patterns: Vec<broadcast::Sender<(String, Published)>>,
+}
+
+impl Delivery {
+    /// 发送到频道 `key` 及匹配的模式，返回收到消息的订阅者数量
+    fn send(self, key: &str) -> usize {
+        let mut receivers = self
+            .channel
+            // 发送失败或无此频道则为 0
+            .map(|tx| tx.send(self.msg.clone()).unwrap_or(0))
+            .unwrap_or(0);
+
+        for tx in self.patterns {
+            receivers += tx.send((key.to_string(), self.msg.clone())).unwrap_or(0);
+        }
+
+        receivers
+    }
+}
+
+impl<G: Deref<Target = State>> Shards<G> {
+    /// 键所在的分片，该分片需已加锁
+    fn state(&self, key: &str) -> &State {
+        self.guards[shard_index(key)].as_deref().expect("shard is not locked")
+    }
+
+    /// 对 `keys` 对应的集合做集合运算，不存在的键视为空集合
+    fn set_operation(&self, op: SetOperation, keys: &[String]) -> crate::Result<HashSet<Bytes>> {
+        if let SetOperation::Inter = op {
+            return Ok(self.intersection(keys, usize::MAX)?.0.into_iter().collect());
+        }
+
+        let sets = self.get_sets(keys)?;
+
+        let mut sets = sets.into_iter();
+        let mut result = match sets.next() {
+            Some(Some(first)) => first.clone(),
+            _ => HashSet::new(),
+        };
+
+        // 交集已由 `intersection` 计算
+        for set in sets {
+            match (op, set) {
+                (SetOperation::Union, Some(set)) => result.extend(set.iter().cloned()),
+                (SetOperation::Diff, Some(set)) => result.retain(|member| !set.contains(member)),
+                _ => {},
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// 求 `keys` 对应的集合的交集，至多返回 `limit` 个成员，以及遍历的成员数量
+    /// 遍历最小的集合，逐个检查成员是否在其它所有集合中，不构建中间结果；
+    /// 任意一个键不存在时交集为空，无需遍历
+    fn intersection(&self, keys: &[String], limit: usize) -> crate::Result<(Vec<Bytes>, usize)> {
+        let sets = match self.get_sets(keys)?.into_iter().collect::<Option<Vec<_>>>() {
+            Some(sets) => sets,
+            None => return Ok((vec![], 0)),
+        };
+
+        let smallest = match sets.iter().enumerate().min_by_key(|(_, set)| set.len()) {
+            Some((smallest, _)) => smallest,
+            None => return Ok((vec![], 0)),
+        };
+
+        let mut iterated = 0;
+        let mut result = vec![];
+        for member in sets[smallest].iter() {
+            if result.len() >= limit {
+                break;
+            }
+            iterated += 1;
+
+            let in_all = sets
+                .iter()
+                .enumerate()
+                .all(|(i, set)| i == smallest || set.contains(member));
+            if in_all {
+                result.push(member.clone());
+            }
+        }
+
+        debug!(iterated, "set intersection");
+
+        Ok((result, iterated))
+    }
+
+    /// 查找 `keys` 对应的所有集合，先检查所有键的类型，任意一个不是集合都返回错误
+    fn get_sets(&self, keys: &[String]) -> crate::Result<Vec<Option<&HashSet<Bytes>>>> {
+        keys.iter()
+            .map(|key| self.state(key).get_set(key))
+            .collect()
+    }
+}
+
+impl<G: DerefMut<Target = State>> Shards<G> {
+    /// 键所在的分片，该分片需已加写锁
+    fn state_mut(&mut self, key: &str) -> &mut State {
+        self.guards[shard_index(key)].as_deref_mut().expect("shard is not locked")
+    }
+}
+
+async fn purge_expired_tasks(shared: Arc<Shared>) {
+    // 如果设置了关闭标识，则退出后台任务
+    while !shared.is_shutdown() {
+        if let Some(when) = shared.purge_expired_keys() {
+            tokio::select! {
+                _ = time::sleep_until(when) => {},
+                // 当在等待时得到通知，则更新最早生效的键的时间
+                _ = shared.background_task.notified() => {},
+            }
+        } else {
+            // 没有将生效的键，等待通知
+            shared.background_task.notified().await;
+        }
+    }
+
+    debug!("Purge background task shutdown")
+}
+
+/// 当前的 Unix 时间（秒）
+fn unix_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 键的哈希值，低 `SHARD_BITS` 位决定所在分片
+fn key_hash(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 键所在分片的下标
+fn shard_index(key: &str) -> usize {
+    key_hash(key) as usize % SHARDS
+}
+
+/// 键在分片内的 `SCAN` 位置，同一分片的键哈希值的低位相同，故只取其余的高位
+fn scan_position(key: &str) -> u64 {
+    key_hash(key) >> SHARD_BITS
 }
@@ -0,0 +1,214 @@
+//! 数据库快照的编码与解码，`SAVE` 时写入文件，服务启动时读取
+//!
+//! 过期时间以 Unix 时间（毫秒）保存：`Instant` 只在当前进程内有意义，
+//! 保存时换算为绝对时间，读取时再按与当前时间的差值换算回 `Instant`
+use std::{
+    collections::{HashSet, VecDeque},
+    ops::Deref,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::time::Instant;
+
+use super::{State, Value};
+use crate::{hash::Hash, hyperloglog::HyperLogLog, sorted_set::SortedSet};
+
+/// 快照文件的开头，最后一个字节为格式版本
+const MAGIC: &[u8] = b"MINIREDIS\x01";
+
+const TYPE_STRING: u8 = 0;
+const TYPE_SET: u8 = 1;
+const TYPE_ZSET: u8 = 2;
+const TYPE_HLL: u8 = 3;
+const TYPE_LIST: u8 = 4;
+const TYPE_HASH: u8 = 5;
+
+const INVALID: &str = "ERR invalid snapshot";
+
+/// 将所有分片的条目编码为快照
+/// `now` 与 `wall` 为同一时刻的 `Instant` 与系统时间，用于换算过期时间
+pub(super) fn encode(shards: &[impl Deref<Target = State>], now: Instant, wall: SystemTime) -> Bytes {
+    let mut buf = BytesMut::new();
+    buf.put_slice(MAGIC);
+    buf.put_u64(shards.iter().map(|state| state.entries.len() as u64).sum());
+
+    for (key, entry) in shards.iter().flat_map(|state| &state.entries) {
+        let tag = match &entry.value {
+            Value::String(_) | Value::Int(_) => TYPE_STRING,
+            Value::Set(_) => TYPE_SET,
+            Value::ZSet(_) => TYPE_ZSET,
+            Value::Hll(_) => TYPE_HLL,
+            Value::List(_) => TYPE_LIST,
+            Value::Hash(_) => TYPE_HASH,
+        };
+        buf.put_u8(tag);
+
+        match entry.expires_at {
+            Some(when) => {
+                buf.put_u8(1);
+                buf.put_u64(unix_millis(to_unix(when, now, wall)));
+            },
+            None => buf.put_u8(0),
+        }
+
+        put_bytes(&mut buf, key.as_bytes());
+
+        match &entry.value {
+            Value::String(data) => put_bytes(&mut buf, data),
+            Value::Int(value) => put_bytes(&mut buf, value.to_string().as_bytes()),
+            Value::Set(set) => {
+                buf.put_u32(set.len() as u32);
+                for member in set {
+                    put_bytes(&mut buf, member);
+                }
+            },
+            Value::ZSet(zset) => {
+                buf.put_u32(zset.len() as u32);
+                for (member, score) in zset.iter() {
+                    buf.put_f64(score);
+                    put_bytes(&mut buf, member);
+                }
+            },
+            Value::Hll(hll) => put_bytes(&mut buf, hll.registers()),
+            Value::List(list) => {
+                buf.put_u32(list.len() as u32);
+                for element in list {
+                    put_bytes(&mut buf, element);
+                }
+            },
+            Value::Hash(hash) => {
+                buf.put_u32(hash.len() as u32);
+                for (field, value) in hash.iter() {
+                    put_bytes(&mut buf, field);
+                    put_bytes(&mut buf, value);
+                }
+            },
+        }
+    }
+
+    buf.freeze()
+}
+
+/// 从快照中解码出条目 (键, 值, 有效期)，已过期的键会被跳过
+/// 由调用方将条目插入对应的分片，并通知后台任务重新计算过期时间
+pub(super) fn decode(mut src: &[u8], now: Instant, wall: SystemTime) -> crate::Result<Vec<(String, Value, Option<Instant>)>> {
+    if !src.starts_with(MAGIC) {
+        return Err(INVALID.into());
+    }
+    src.advance(MAGIC.len());
+
+    let count = get_u64(&mut src)?;
+    let mut entries = vec![];
+
+    for _ in 0..count {
+        let tag = get_u8(&mut src)?;
+
+        let expires_at = match get_u8(&mut src)? {
+            0 => None,
+            _ => Some(UNIX_EPOCH + Duration::from_millis(get_u64(&mut src)?)),
+        };
+
+        let key = String::from_utf8(get_bytes(&mut src)?.to_vec()).map_err(|_| INVALID)?;
+
+        let value = match tag {
+            TYPE_STRING => Value::string(get_bytes(&mut src)?),
+            TYPE_SET => {
+                let len = get_u32(&mut src)?;
+                let mut set = HashSet::new();
+                for _ in 0..len {
+                    set.insert(get_bytes(&mut src)?);
+                }
+                Value::Set(set)
+            },
+            TYPE_ZSET => {
+                let len = get_u32(&mut src)?;
+                let mut zset = SortedSet::new();
+                for _ in 0..len {
+                    let score = f64::from_bits(get_u64(&mut src)?);
+                    zset.insert(get_bytes(&mut src)?, score);
+                }
+                Value::ZSet(zset)
+            },
+            TYPE_HLL => Value::Hll(HyperLogLog::from_registers(&get_bytes(&mut src)?).ok_or(INVALID)?),
+            TYPE_LIST => {
+                let len = get_u32(&mut src)?;
+                let mut list = VecDeque::new();
+                for _ in 0..len {
+                    list.push_back(get_bytes(&mut src)?);
+                }
+                Value::List(list)
+            },
+            // 按字段数量与长度重新选择编码
+            TYPE_HASH => {
+                let len = get_u32(&mut src)?;
+                let mut hash = Hash::new();
+                for _ in 0..len {
+                    let field = get_bytes(&mut src)?;
+                    hash.insert(field, get_bytes(&mut src)?);
+                }
+                Value::Hash(hash)
+            },
+            _ => return Err(INVALID.into()),
+        };
+
+        // 保存之后已经过期的键不再恢复
+        if matches!(expires_at, Some(at) if at <= wall) {
+            continue;
+        }
+
+        entries.push((key, value, expires_at.map(|at| from_unix(at, now, wall))));
+    }
+
+    Ok(entries)
+}
+
+/// 将当前进程内的 `Instant` 换算为系统时间，已过去的时间视为当前时刻
+fn to_unix(when: Instant, now: Instant, wall: SystemTime) -> SystemTime {
+    wall + when.saturating_duration_since(now)
+}
+
+/// 将系统时间换算为当前进程内的 `Instant`，已过去的时间视为当前时刻
+fn from_unix(at: SystemTime, now: Instant, wall: SystemTime) -> Instant {
+    now + at.duration_since(wall).unwrap_or_default()
+}
+
+fn unix_millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn put_bytes(buf: &mut BytesMut, data: &[u8]) {
+    buf.put_u32(data.len() as u32);
+    buf.put_slice(data);
+}
+
+fn get_u8(src: &mut &[u8]) -> crate::Result<u8> {
+    if src.remaining() < 1 {
+        return Err(INVALID.into());
+    }
+    Ok(src.get_u8())
+}
+
+fn get_u32(src: &mut &[u8]) -> crate::Result<u32> {
+    if src.remaining() < 4 {
+        return Err(INVALID.into());
+    }
+    Ok(src.get_u32())
+}
+
+fn get_u64(src: &mut &[u8]) -> crate::Result<u64> {
+    if src.remaining() < 8 {
+        return Err(INVALID.into());
+    }
+    Ok(src.get_u64())
+}
+
+fn get_bytes(src: &mut &[u8]) -> crate::Result<Bytes> {
+    let len = get_u32(src)? as usize;
+    if src.remaining() < len {
+        return Err(INVALID.into());
+    }
+    Ok(src.copy_to_bytes(len))
+}
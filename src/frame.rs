@@ -9,17 +9,18 @@ use std::{
     string::FromUtf8Error,
 };
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 /// Redis 协议里使用的 frame
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Frame {
     Simple(String),         // b'+' + bytes + '\r\n'
     Error(String),          // b'-' + bytes + '\r\n'
-    Integer(u64),           // b':' + bytes(num) + '\r\n'
-    Null,                   // b"$" + b'-1' + '\r\n'
+    Integer(i64),           // b':' + bytes(num) + '\r\n'，num 可为负数
+    Null,                   // b"$" + b'-1' + '\r\n'，RESP3 为 b"_\r\n"
     Bulk(Bytes),            // b'$' + bytes(num) + '\r\n' + bytes(data) + '\r\n'
     Array(Vec<Frame>),      // b'*' + bytes(len) + '\r\n' + bytes(frames)
+    Push(Vec<Frame>),       // b'>' + bytes(len) + '\r\n' + bytes(frames)，仅用于 RESP3
 }
 
 #[derive(Debug)]
@@ -37,6 +38,19 @@ impl Frame {
         Frame::Array(vec![])
     }
 
+    /// 返回 `+OK`，`SET` 等命令执行成功时使用
+    pub fn ok() -> Frame {
+        Frame::from_static_simple("OK")
+    }
+
+    /// 由静态字符串构建 `Simple`，用于 `OK`、`PONG` 等固定的回复
+    ///
+    /// 由于 `Simple` 持有 `String`，这里仍会复制一次字符串，
+    /// 但将固定回复的构建集中到了一处
+    pub fn from_static_simple(s: &'static str) -> Frame {
+        Frame::Simple(s.to_string())
+    }
+
     /// 将 `Bulk` 放入 array 中，`self` 必须为 Frame::Array
     pub(crate) fn push_bulk(&mut self, bytes: Bytes) {
         match self {
@@ -47,8 +61,8 @@ impl Frame {
         }
     }
 
-    /// 将 `u64` 放入 array 中，`self` 必须为 Frame::Array
-    pub(crate) fn push_int(&mut self, value: u64) {
+    /// 将 `i64` 放入 array 中，`self` 必须为 Frame::Array
+    pub(crate) fn push_int(&mut self, value: i64) {
         match self {
             Frame::Array(array) => {
                 array.push(Frame::Integer(value))
@@ -58,7 +72,8 @@ impl Frame {
     }
 
     /// 检查是否可以从 `src` 中解析出一条 Frame 消息
-    pub(crate) fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    /// 声明的 bulk 长度或数组元素个数超过 `max_len` 时返回协议错误，不等待后续数据
+    pub(crate) fn check(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<(), Error> {
         // 读取 src 中第一个字符，
         match get_u8(src)? {
             // Frame 为 Simple 或 Error
@@ -68,9 +83,11 @@ impl Frame {
             },
             // Frame 为数字
             b':' => {
-                get_decimal(src)?;
+                get_signed_decimal(src)?;
                 Ok(())
             },
+            // RESP3 的 Null
+            b'_' => skip(src, 2),
             // Frame 为 Null 或 Bulk
             b'$' => {
                 // Frame::Null = b"$-1\r\n"
@@ -78,18 +95,18 @@ impl Frame {
                     // 跳过 b"-1\r\n"
                     skip(src, 4)
                 } else {
-                    let len: usize = get_decimal(src)?.try_into()?;
+                    let len = get_len(src, max_len)?;
 
                     // 跳过 bytes + b"\r\n"
-                    skip(src, len + 2)
+                    skip(src, len.saturating_add(2))
                 }
             },
-            //Frame 为数组
-            b'*' => {
+            //Frame 为数组，RESP3 的 push 与数组格式相同
+            b'*' | b'>' => {
                 // 数组中有多少元素
-                let len = get_decimal(src)?;
+                let len = get_len(src, max_len)?;
                 for _ in 0..len {
-                    Frame::check(src)?;
+                    Frame::check(src, max_len)?;
                 }
                 Ok(())
             },
@@ -113,10 +130,17 @@ impl Frame {
                 Ok(Frame::Error(string))
             },
             b':' => {
-                let num = get_decimal(src)?;
+                let num = get_signed_decimal(src)?;
 
                 Ok(Frame::Integer(num))
             },
+            b'_' => {
+                if !get_line(src)?.is_empty() {
+                    return Err("protocol error: invalid frame format".into());
+                }
+
+                Ok(Frame::Null)
+            },
             b'$' => {
                 // Null
                 if peek_u8(src)? == b'-' {
@@ -137,17 +161,77 @@ impl Frame {
                     Ok(Frame::Bulk(data))
                 }
             },
-            b'*' => {
-                let len = get_decimal(src)?.try_into()?;
-                let mut res = Vec::with_capacity(len);
+            b'*' => Ok(Frame::Array(parse_elements(src)?)),
+            b'>' => Ok(Frame::Push(parse_elements(src)?)),
+            _ => unimplemented!(),
+        }
+    }
 
-                for _ in 0..len {
-                    res.push(Frame::parse(src)?);
+    /// 将 frame 按 RESP 格式编码，追加到 `buf` 的末尾
+    /// 与 `Connection::write_value` 写出的字节完全相同
+    pub fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    /// 与 `encode` 相同，`resp3` 为 `true` 时 `Null` 编码为 RESP3 的 `_\r\n`
+    pub fn encode_with(&self, buf: &mut BytesMut, resp3: bool) {
+        match self {
+            Frame::Simple(val) => {
+                buf.put_u8(b'+');
+                buf.put_slice(val.as_bytes());
+                buf.put_slice(b"\r\n");
+            },
+            Frame::Error(val) => {
+                buf.put_u8(b'-');
+                buf.put_slice(val.as_bytes());
+                buf.put_slice(b"\r\n");
+            },
+            Frame::Integer(val) => {
+                buf.put_u8(b':');
+                encode_decimal(*val, buf);
+            },
+            Frame::Null if resp3 => buf.put_slice(b"_\r\n"),
+            Frame::Null => buf.put_slice(b"$-1\r\n"),
+            Frame::Bulk(val) => {
+                buf.put_u8(b'$');
+                encode_decimal(val.len() as i64, buf);
+                buf.put_slice(val);
+                buf.put_slice(b"\r\n");
+            },
+            Frame::Array(val) | Frame::Push(val) => {
+                let prefix = match self {
+                    Frame::Push(_) => b'>',
+                    _ => b'*',
+                };
+
+                buf.put_u8(prefix);
+                encode_decimal(val.len() as i64, buf);
+
+                for entry in val {
+                    entry.encode_with(buf, resp3);
                 }
+            },
+        }
+    }
+
+    /// 按 RESP 格式编码后的字节数，与 `encode` 写入的长度相同
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with(false)
+    }
 
-                Ok(Frame::Array(res))
+    /// 与 `encode_with` 写入的长度相同，`resp3` 为 `true` 时 `Null` 为 3 个字节
+    pub fn encoded_len_with(&self, resp3: bool) -> usize {
+        match self {
+            // 类型字节 + 内容 + b"\r\n"
+            Frame::Simple(val) | Frame::Error(val) => 1 + val.len() + 2,
+            Frame::Integer(val) => 1 + decimal_len(*val) + 2,
+            Frame::Null if resp3 => 3,
+            Frame::Null => 5,
+            // b'$' + 长度 + b"\r\n" + 内容 + b"\r\n"
+            Frame::Bulk(val) => 1 + decimal_len(val.len() as i64) + 2 + val.len() + 2,
+            Frame::Array(val) | Frame::Push(val) => {
+                1 + decimal_len(val.len() as i64) + 2 + val.iter().map(|entry| entry.encoded_len_with(resp3)).sum::<usize>()
             },
-            _ => unimplemented!(),
         }
     }
 
@@ -180,14 +264,13 @@ impl fmt::Display for Frame {
                 Ok(string) => string.fmt(fmt),
                 Err(_) => write!(fmt, "{:?}", msg),
             },
-            Frame::Array(arr) => {
+            Frame::Array(arr) | Frame::Push(arr) => {
+                // 元素之间以一个空格分隔，首尾没有空格
                 for (i, item) in arr.iter().enumerate() {
-                    // arr 的构成为 [元素个数，元素1，元素2, ..]
-                    // 此处我们不想打印出元素个数
                     if i > 0 {
                         write!(fmt, " ")?;
-                        item.fmt(fmt)?;
                     }
+                    item.fmt(fmt)?;
                 }
 
                 Ok(())
@@ -196,6 +279,38 @@ impl fmt::Display for Frame {
     }
 }
 
+// 十进制数字的字节数，包括负号
+fn decimal_len(value: i64) -> usize {
+    let digits = value.unsigned_abs().checked_ilog10().unwrap_or(0) as usize + 1;
+
+    if value < 0 {
+        digits + 1
+    } else {
+        digits
+    }
+}
+
+// 写入十进制数字并以 b"\r\n" 结束
+fn encode_decimal(value: i64, buf: &mut BytesMut) {
+    use std::fmt::Write;
+
+    // 写入 `BytesMut` 不会失败
+    write!(buf, "{}", value).unwrap();
+    buf.put_slice(b"\r\n");
+}
+
+// 读取数组类 frame 的元素个数及所有元素
+fn parse_elements(src: &mut Cursor<&[u8]>) -> Result<Vec<Frame>, Error> {
+    let len = get_decimal(src)?.try_into()?;
+    let mut res = Vec::with_capacity(len);
+
+    for _ in 0..len {
+        res.push(Frame::parse(src)?);
+    }
+
+    Ok(res)
+}
+
 // 读取第一个 byte 且将游标后移
 fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
@@ -207,16 +322,25 @@ fn get_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
 
 // 读取一行，并设置游标
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
+    let buf: &'a [u8] = src.get_ref();
     // 获取起始位置
     let start = src.position() as usize;
-    // 
-    let end = src.get_ref().len() - 1;
-
-    for i in start..end {
-        // 若读取到 b"\r\n" 则将游标放置到下行首，并返回当前行数据（不包括 b"\r\n"）
-        if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
-            src.set_position((i + 2) as u64);
-            return Ok(&src.get_ref()[start..i])
+    let mut from = start;
+
+    // 先用 `memchr` 找到 b'\r'，再检查其后是否为 b'\n'，比逐个字节比较两个字节快
+    while let Some(offset) = memchr::memchr(b'\r', &buf[from..]) {
+        let i = from + offset;
+
+        match buf.get(i + 1) {
+            // 若读取到 b"\r\n" 则将游标放置到下行首，并返回当前行数据（不包括 b"\r\n"）
+            Some(b'\n') => {
+                src.set_position((i + 2) as u64);
+                return Ok(&buf[start..i])
+            },
+            // 单独的 b'\r' 属于行内的数据，继续查找
+            Some(_) => from = i + 1,
+            // b'\r' 是最后一个字节，需要更多的数据
+            None => break,
         }
     }
 
@@ -224,12 +348,50 @@ fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     Err(Error::Incomplete)
 }
 
-// 读取一行转化 u64
+// 读取 bulk 的长度或数组的元素个数，超过 `max_len` 时返回错误
+fn get_len(src: &mut Cursor<&[u8]>, max_len: usize) -> Result<usize, Error> {
+    let len = get_decimal(src)?;
+
+    match usize::try_from(len) {
+        Ok(len) if len <= max_len => Ok(len),
+        _ => Err(format!("protocol error: length {} exceeds the limit of {}", len, max_len).into()),
+    }
+}
+
+// 读取一行转化 u64，用于长度等不能为负数的值
 fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
-    use atoi::atoi;
+    use atoi::FromRadix10Checked;
+
+    let line = get_line(src)?;
+
+    // `$-1` 等合法的负数已由调用方处理，这里的负数都是错误的
+    if line.first() == Some(&b'-') {
+        return Err(format!("protocol error: unexpected negative integer '{}'", String::from_utf8_lossy(line)).into());
+    }
+
+    check_decimal(line, u64::from_radix_10_checked(line))
+}
+
+// 读取一行转化 i64，用于可能为负数的 `Integer`
+fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    use atoi::FromRadix10SignedChecked;
 
     let line = get_line(src)?;
-    atoi::<u64>(line).ok_or_else(|| "protocol error: invalid frame format.".into())
+    check_decimal(line, i64::from_radix_10_signed_checked(line))
+}
+
+// 检查 `atoi` 的解析结果，区分溢出与格式错误
+// 整行都必须是数字（及开头的符号），`atoi` 在第一个非数字处停止，不会报错
+fn check_decimal<T>(line: &[u8], (value, used): (Option<T>, usize)) -> Result<T, Error> {
+    let digits = line.iter().filter(|b| b.is_ascii_digit()).count();
+
+    match value {
+        Some(value) if digits > 0 && used == line.len() => Ok(value),
+        None if used == line.len() => {
+            Err(format!("protocol error: integer '{}' out of range", String::from_utf8_lossy(line)).into())
+        },
+        _ => Err(format!("protocol error: invalid integer '{}'", String::from_utf8_lossy(line)).into()),
+    }
 }
 
 // 仅读取第一个 byte 但不移动游标
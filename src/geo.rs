@@ -0,0 +1,90 @@
+//! 地理位置的编码与距离计算，`GEOADD`/`GEODIST` 使用
+//!
+//! 与 Redis 相同，经纬度各量化为 26 位并交错成 52 位的 geohash，
+//! 作为有序集合的分值保存（52 位以内的整数可以由 `f64` 精确表示）
+
+/// 经度的范围
+pub(crate) const LONGITUDE_MIN: f64 = -180.0;
+pub(crate) const LONGITUDE_MAX: f64 = 180.0;
+
+/// 纬度的范围，墨卡托投影无法表示两极附近的区域
+pub(crate) const LATITUDE_MIN: f64 = -85.051_128_78;
+pub(crate) const LATITUDE_MAX: f64 = 85.051_128_78;
+
+/// 经纬度各自量化的位数
+const STEP: u32 = 26;
+
+/// 计算距离时使用的地球半径（米），与 Redis 相同
+const EARTH_RADIUS: f64 = 6_372_797.560_856;
+
+/// 经纬度是否在可以编码的范围内
+pub(crate) fn is_valid(longitude: f64, latitude: f64) -> bool {
+    (LONGITUDE_MIN..=LONGITUDE_MAX).contains(&longitude) && (LATITUDE_MIN..=LATITUDE_MAX).contains(&latitude)
+}
+
+/// 将经纬度编码为 52 位的 geohash，调用方需保证经纬度在范围内
+pub(crate) fn encode(longitude: f64, latitude: f64) -> u64 {
+    let lon = quantize(longitude, LONGITUDE_MIN, LONGITUDE_MAX);
+    let lat = quantize(latitude, LATITUDE_MIN, LATITUDE_MAX);
+
+    // 纬度占偶数位，经度占奇数位
+    spread(lat) | (spread(lon) << 1)
+}
+
+/// 将 geohash 解码为所在区域中心的经纬度
+pub(crate) fn decode(hash: u64) -> (f64, f64) {
+    let lat = squash(hash);
+    let lon = squash(hash >> 1);
+
+    (
+        dequantize(lon, LONGITUDE_MIN, LONGITUDE_MAX),
+        dequantize(lat, LATITUDE_MIN, LATITUDE_MAX),
+    )
+}
+
+/// 使用 haversine 公式计算两点间的大圆距离（米）
+pub(crate) fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2 - lon1).to_radians() / 2.0).sin();
+
+    2.0 * EARTH_RADIUS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+/// 将 `value` 在 `[min, max]` 中的位置量化为 `STEP` 位的整数
+fn quantize(value: f64, min: f64, max: f64) -> u32 {
+    let cells = (1u64 << STEP) as f64;
+    let offset = ((value - min) / (max - min) * cells) as u64;
+
+    // `value` 等于 `max` 时落在最后一个区域中
+    offset.min((1 << STEP) - 1) as u32
+}
+
+/// `quantize` 的逆运算，返回区域的中心
+fn dequantize(offset: u32, min: f64, max: f64) -> f64 {
+    let cell = (max - min) / (1u64 << STEP) as f64;
+
+    min + (offset as f64 + 0.5) * cell
+}
+
+/// 将 32 位整数的每一位分散到 64 位整数的偶数位上
+fn spread(value: u32) -> u64 {
+    let mut x = value as u64;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// `spread` 的逆运算，取出 64 位整数的偶数位
+fn squash(value: u64) -> u32 {
+    let mut x = value & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff;
+    x as u32
+}
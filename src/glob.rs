@@ -0,0 +1,134 @@
+//! Redis 风格的 glob 匹配，`KEYS` 等命令使用
+//!
+//! 支持的语法：
+//! - `*` 匹配任意数量（包括 0 个）的字节
+//! - `?` 匹配单个字节
+//! - `[abc]`、`[a-z]` 匹配其中的一个字节，`[^a]` 匹配不在其中的字节
+//! - `\` 转义下一个字节
+
+/// 解析后的模式中的一项
+#[derive(Debug)]
+enum Token {
+    /// `*`
+    Star,
+    /// `?`
+    Any,
+    /// `[...]`，`ranges` 中的每一项为闭区间
+    Class { negate: bool, ranges: Vec<(u8, u8)> },
+    /// 普通字节或被转义的字节
+    Literal(u8),
+}
+
+impl Token {
+    /// 除 `*` 外的项是否匹配单个字节
+    fn matches(&self, byte: u8) -> bool {
+        match self {
+            Token::Star | Token::Any => true,
+            Token::Class { negate, ranges } => {
+                ranges.iter().any(|&(lo, hi)| lo <= byte && byte <= hi) != *negate
+            },
+            Token::Literal(literal) => *literal == byte,
+        }
+    }
+}
+
+/// 判断 `string` 是否匹配 `pattern`
+pub(crate) fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let tokens = parse(pattern);
+
+    // 遇到 `*` 时记录位置，后续匹配失败时回到这里，让 `*` 多匹配一个字节
+    let (mut t, mut s) = (0, 0);
+    let mut backtrack = None;
+
+    while s < string.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                backtrack = Some((t, s));
+                t += 1;
+            },
+            Some(token) if token.matches(string[s]) => {
+                t += 1;
+                s += 1;
+            },
+            _ => match backtrack {
+                Some((star, from)) => {
+                    t = star + 1;
+                    s = from + 1;
+                    backtrack = Some((star, from + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+
+    // 字符串已匹配完，剩下的只能是 `*`
+    tokens[t..].iter().all(|token| matches!(token, Token::Star))
+}
+
+/// 将模式解析为 `Token` 列表，连续的 `*` 合并为一个
+fn parse(pattern: &[u8]) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < pattern.len() {
+        let token = match pattern[i] {
+            b'*' => {
+                if matches!(tokens.last(), Some(Token::Star)) {
+                    i += 1;
+                    continue;
+                }
+                Token::Star
+            },
+            b'?' => Token::Any,
+            // 末尾单独的 `\` 视为普通字节
+            b'\\' if i + 1 < pattern.len() => {
+                i += 1;
+                Token::Literal(pattern[i])
+            },
+            b'[' => {
+                let (token, end) = parse_class(pattern, i + 1);
+                i = end;
+                token
+            },
+            byte => Token::Literal(byte),
+        };
+
+        tokens.push(token);
+        i += 1;
+    }
+
+    tokens
+}
+
+/// 解析 `[` 之后的内容，返回 `Token::Class` 及 `]` 所在的位置
+/// 没有对应的 `]` 时，一直解析到模式结尾
+fn parse_class(pattern: &[u8], mut i: usize) -> (Token, usize) {
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut ranges = vec![];
+
+    while i < pattern.len() && pattern[i] != b']' {
+        let lo = match pattern[i] {
+            b'\\' if i + 1 < pattern.len() => {
+                i += 1;
+                pattern[i]
+            },
+            byte => byte,
+        };
+
+        // `a-z` 形式的区间，两端顺序颠倒时交换
+        if pattern.get(i + 1) == Some(&b'-') && i + 2 < pattern.len() && pattern[i + 2] != b']' {
+            let hi = pattern[i + 2];
+            ranges.push((lo.min(hi), lo.max(hi)));
+            i += 3;
+        } else {
+            ranges.push((lo, lo));
+            i += 1;
+        }
+    }
+
+    (Token::Class { negate, ranges }, i)
+}
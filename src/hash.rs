@@ -0,0 +1,97 @@
+//! 哈希，字段到值的映射
+//!
+//! 与 Redis 相同，字段较少且值较短时以数组（`listpack`）顺序保存，
+//! 省去每个字段在 `HashMap` 中的额外开销；超过阈值后转换为 `HashMap`（`hashtable`），不再转换回来
+use std::{collections::HashMap, mem};
+
+use bytes::Bytes;
+
+/// 以数组保存时字段数量的上限，与 Redis 的 `hash-max-listpack-entries` 默认值相同
+pub(crate) const MAX_LISTPACK_ENTRIES: usize = 128;
+
+/// 以数组保存时字段及值的字节数上限，与 Redis 的 `hash-max-listpack-value` 默认值相同
+pub(crate) const MAX_LISTPACK_VALUE: usize = 64;
+
+/// 哈希
+#[derive(Debug, Clone)]
+pub(crate) enum Hash {
+    /// 按插入顺序保存的 (字段, 值)，查找时顺序比较
+    Listpack(Vec<(Bytes, Bytes)>),
+    Table(HashMap<Bytes, Bytes>),
+}
+
+impl Hash {
+    /// 创建一个空的哈希，以数组保存
+    pub(crate) fn new() -> Hash {
+        Hash::Listpack(vec![])
+    }
+
+    /// 设置字段的值，新添加字段时返回 `true`
+    /// 字段数量或字段、值的长度超过阈值时转换为 `HashMap`
+    pub(crate) fn insert(&mut self, field: Bytes, value: Bytes) -> bool {
+        if let Hash::Listpack(entries) = self {
+            let fits = field.len() <= MAX_LISTPACK_VALUE && value.len() <= MAX_LISTPACK_VALUE;
+
+            let full = entries.len() >= MAX_LISTPACK_ENTRIES;
+
+            match entries.iter().position(|(f, _)| *f == field) {
+                Some(index) if fits => {
+                    entries[index].1 = value;
+                    return false;
+                },
+                None if fits && !full => {
+                    entries.push((field, value));
+                    return true;
+                },
+                _ => {
+                    let table = mem::take(entries).into_iter().collect();
+                    *self = Hash::Table(table);
+                },
+            }
+        }
+
+        match self {
+            Hash::Table(table) => table.insert(field, value).is_none(),
+            Hash::Listpack(_) => unreachable!(),
+        }
+    }
+
+    /// 查找字段的值
+    pub(crate) fn get(&self, field: &[u8]) -> Option<&Bytes> {
+        match self {
+            Hash::Listpack(entries) => entries.iter().find(|(f, _)| f == field).map(|(_, value)| value),
+            Hash::Table(table) => table.get(field),
+        }
+    }
+
+    /// 字段的数量
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Hash::Listpack(entries) => entries.len(),
+            Hash::Table(table) => table.len(),
+        }
+    }
+
+    /// 遍历所有的 (字段, 值)
+    pub(crate) fn iter(&self) -> Box<dyn Iterator<Item = (&Bytes, &Bytes)> + '_> {
+        match self {
+            Hash::Listpack(entries) => Box::new(entries.iter().map(|(field, value)| (field, value))),
+            Hash::Table(table) => Box::new(table.iter()),
+        }
+    }
+
+    /// 内部编码，由 `OBJECT ENCODING` 返回
+    pub(crate) fn encoding(&self) -> &'static str {
+        match self {
+            Hash::Listpack(_) => "listpack",
+            Hash::Table(_) => "hashtable",
+        }
+    }
+
+    /// 估算占用的内存，每个字段和值都要加上 `Bytes` 自身的开销
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.iter()
+            .map(|(field, value)| 2 * mem::size_of::<Bytes>() + field.len() + value.len())
+            .sum()
+    }
+}
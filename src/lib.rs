@@ -8,7 +8,23 @@ mod parse;
 use parse::{Parse, ParseError};
 
 mod db;
-use db::{Db, DbDropGuard};
+pub use db::Db;
+use db::DbDropGuard;
+
+pub mod store;
+pub use store::KvStore;
+
+mod sorted_set;
+
+mod hyperloglog;
+
+mod hash;
+
+mod geo;
+
+mod glob;
+
+mod slowlog;
 
 mod shutdown;
 use shutdown::Shutdown;
@@ -22,6 +38,8 @@ pub mod client;
 
 pub mod blocking_client;
 
+pub mod pipeline;
+
 pub const DEFAULT_PORT: u16 = 6379;
 
 /// 定义 crate::Error
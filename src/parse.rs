@@ -15,6 +15,9 @@ pub(crate) struct Parse {
 #[derive(Debug)]
 pub(crate) enum ParseError {
     EndOfStream,
+    /// 参数存在但类型不符，`expected` 为需要的类型，`got` 描述实际收到的内容
+    /// 需要整数时显示为 Redis 的 `ERR value is not an integer or out of range`
+    WrongType { expected: &'static str, got: String },
     Other(crate::Error)
 }
 
@@ -44,8 +47,8 @@ impl Parse {
             Frame::Simple(s) => Ok(s),
             Frame::Bulk(data) => str::from_utf8(&data[..])
                 .map(|s| s.to_string())
-                .map_err(|_| "protocol error: invalid string.".into()),
-            frame => Err(format!("protocol error: expected Simple/Bulk frame, but got {:?}", frame).into()),
+                .map_err(|_| wrong_type("string", "invalid UTF-8")),
+            frame => Err(wrong_type("Simple/Bulk frame", format!("{:?}", frame))),
         }
     }
 
@@ -55,25 +58,57 @@ impl Parse {
         match self.next()? {
             Frame::Simple(s) => Ok(Bytes::from(s.into_bytes())),
             Frame::Bulk(data) => Ok(data),
-            frame => Err(format!("protocol error: expected Simple/Bulk frame, but got {:?}", frame).into()),
+            frame => Err(wrong_type("Simple/Bulk frame", format!("{:?}", frame))),
         }
     }
 
+    /// 以字节形式借用下一个 frame，不消耗也不分配
+    /// 只支持 Simple 和 Bulk
+    pub(crate) fn peek_bytes(&self) -> Result<&[u8], ParseError> {
+        match self.parts.as_slice().first() {
+            Some(Frame::Simple(s)) => Ok(s.as_bytes()),
+            Some(Frame::Bulk(data)) => Ok(data),
+            Some(frame) => Err(wrong_type("Simple/Bulk frame", format!("{:?}", frame))),
+            None => Err(ParseError::EndOfStream),
+        }
+    }
+
+    /// 跳过下一个 frame
+    pub(crate) fn skip(&mut self) -> Result<(), ParseError> {
+        self.next().map(drop)
+    }
+
     /// 读取下一个 frame 并尝试转换为整数
     /// 也可从 Simple/Bulk 解析出整数
     pub(crate) fn next_int(&mut self) -> Result<u64, ParseError> {
         use atoi::atoi;
 
-        const MSG: &str = "protocol error: invalid number";
+        match self.next()? {
+            Frame::Integer(i) => u64::try_from(i).map_err(|_| not_integer(i)),
+            Frame::Simple(s) => atoi::<u64>(s.as_bytes()).ok_or_else(|| not_integer(s)),
+            Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| not_integer(String::from_utf8_lossy(&data))),
+            frame => Err(wrong_type("Integer/Simple/Bulk frame", format!("{:?}", frame))),
+        }
+    }
+
+    /// 读取下一个 frame 并尝试转换为有符号整数
+    /// 也可从 Simple/Bulk 解析出整数
+    pub(crate) fn next_signed_int(&mut self) -> Result<i64, ParseError> {
+        use atoi::atoi;
 
         match self.next()? {
             Frame::Integer(i) => Ok(i),
-            Frame::Simple(s) => atoi::<u64>(&s.into_bytes()).ok_or_else(|| MSG.into()),
-            Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| MSG.into()),
-            frame => Err(format!("protocol error: expected Integer/Simple/Bulk frame, but got {:?}", frame).into()),
+            Frame::Simple(s) => atoi::<i64>(s.as_bytes()).ok_or_else(|| not_integer(s)),
+            Frame::Bulk(data) => atoi::<i64>(&data).ok_or_else(|| not_integer(String::from_utf8_lossy(&data))),
+            frame => Err(wrong_type("Integer/Simple/Bulk frame", format!("{:?}", frame))),
         }
     }
 
+    /// 剩余尚未读取的 frame 数量
+    pub(crate) fn remaining(&self) -> usize {
+        self.parts.len()
+    }
+
     /// 确保 array 中没有更多的可读数据
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
@@ -84,6 +119,16 @@ impl Parse {
     }
 }
 
+/// 参数类型不符的错误
+fn wrong_type(expected: &'static str, got: impl ToString) -> ParseError {
+    ParseError::WrongType { expected, got: got.to_string() }
+}
+
+/// 参数无法解析为需要的整数
+fn not_integer(got: impl ToString) -> ParseError {
+    wrong_type("integer", got)
+}
+
 impl From<String> for ParseError {
     fn from(src: String) -> Self {
         ParseError::Other(src.into())
@@ -100,6 +145,8 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseError::EndOfStream => "protocol error: unnexpected end of stream.".fmt(f),
+            ParseError::WrongType { expected: "integer", .. } => "ERR value is not an integer or out of range".fmt(f),
+            ParseError::WrongType { expected, got } => write!(f, "protocol error: expected {}, but got {}", expected, got),
             ParseError::Other(err) => err.fmt(f),
         }
     }
@@ -0,0 +1,165 @@
+//! 客户端的命令流水线
+//!
+//! 先将多条命令放入 `Pipeline`，再一次性发送给服务端，最后按顺序读取回复
+//! 每条命令在放入时记录其回复的解码方式，执行后得到带类型的结果
+use std::time::Duration;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+use crate::{
+    client::Client,
+    cmd::{Del, Get, Incr, Set},
+    Frame,
+};
+
+/// 命令流水线
+///
+/// # 示例
+///
+/// ```no_run
+/// use mini_redis::{client, pipeline::{Pipeline, Reply}};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut client = client::connect("localhost:6379").await.unwrap();
+///
+///     let replies = Pipeline::new()
+///         .set("foo", "bar".into())
+///         .get("foo")
+///         .execute(&mut client)
+///         .await
+///         .unwrap();
+///
+///     assert_eq!(replies[1], Reply::Bytes(Some("bar".into())));
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    commands: Vec<(Frame, Decode)>,
+}
+
+/// 流水线中一条命令解码后的回复
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// 只返回 `OK` 的命令，如 `SET`
+    Unit,
+    /// 返回字符串或空值的命令，如 `GET`
+    Bytes(Option<Bytes>),
+    /// 返回整数的命令，如 `INCR`、`DEL`
+    Integer(i64),
+}
+
+/// 命令回复的解码方式
+#[derive(Debug, Clone, Copy)]
+enum Decode {
+    Unit,
+    Bytes,
+    Integer,
+    /// 由 `command` 放入，回复原样返回
+    Raw,
+}
+
+impl Pipeline {
+    /// 创建一个空的流水线
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// 放入一条 `GET key`，回复解码为 `Reply::Bytes`
+    pub fn get(self, key: &str) -> Pipeline {
+        self.push(Get::new(key).into_frame(), Decode::Bytes)
+    }
+
+    /// 放入一条 `SET key value`，回复解码为 `Reply::Unit`
+    pub fn set(self, key: &str, value: Bytes) -> Pipeline {
+        self.push(Set::new(key, value, None).into_frame(), Decode::Unit)
+    }
+
+    /// 放入一条带有效期的 `SET`，回复解码为 `Reply::Unit`
+    pub fn set_expires(self, key: &str, value: Bytes, expiration: Duration) -> Pipeline {
+        self.push(Set::new(key, value, Some(expiration)).into_frame(), Decode::Unit)
+    }
+
+    /// 放入一条 `INCR key`，回复解码为 `Reply::Integer`
+    pub fn incr(self, key: &str) -> Pipeline {
+        self.push(Incr::new(key).into_frame(), Decode::Integer)
+    }
+
+    /// 放入任意一条命令，如 `["HSET", "hash", "field", "value"]`
+    /// 回复不做解码，只能由 `execute_raw` 执行，`execute` 会拒绝含有这类命令的流水线
+    pub fn command(self, args: &[&str]) -> Pipeline {
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes()))).collect());
+        self.push(frame, Decode::Raw)
+    }
+
+    /// 放入一条 `DEL key [key ...]`，回复解码为 `Reply::Integer`
+    pub fn del(self, keys: &[String]) -> Pipeline {
+        self.push(Del::new(keys).into_frame(), Decode::Integer)
+    }
+
+    /// 流水线中命令的数量
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// 流水线中是否没有命令
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    fn push(mut self, frame: Frame, decode: Decode) -> Pipeline {
+        self.commands.push((frame, decode));
+        self
+    }
+
+    /// 发送所有命令，按放入的顺序返回解码后的回复
+    ///
+    /// 所有回复都会被读取，以保证连接上的回复不会错位；
+    /// 若有命令返回了错误，则返回第一个错误。
+    /// 含有 `command` 放入的命令时不发送任何命令，直接返回错误
+    #[instrument(skip(self, client))]
+    pub async fn execute(self, client: &mut Client) -> crate::Result<Vec<Reply>> {
+        if self.commands.iter().any(|(_, decode)| matches!(decode, Decode::Raw)) {
+            return Err("raw command in pipeline, use `execute_raw`".into());
+        }
+
+        let (frames, decodes): (Vec<_>, Vec<_>) = self.commands.into_iter().unzip();
+        debug!(commands = frames.len());
+
+        let responses = client.send_batch(&frames).await?;
+
+        responses
+            .into_iter()
+            .zip(decodes)
+            .map(|(frame, decode)| decode.apply(frame))
+            .collect()
+    }
+
+    /// 发送所有命令，按放入的顺序返回未解码的回复
+    /// 命令返回的错误以 `Frame::Error` 原样返回，不会中断其它回复
+    #[instrument(skip(self, client))]
+    pub async fn execute_raw(self, client: &mut Client) -> crate::Result<Vec<Frame>> {
+        let frames: Vec<_> = self.commands.into_iter().map(|(frame, _)| frame).collect();
+        debug!(commands = frames.len());
+
+        client.send_batch(&frames).await
+    }
+}
+
+impl Decode {
+    /// 按记录的方式解码一条回复
+    fn apply(self, frame: Frame) -> crate::Result<Reply> {
+        match (self, frame) {
+            (_, Frame::Error(msg)) => Err(msg.into()),
+            (Decode::Unit, Frame::Simple(response)) if response == "OK" => Ok(Reply::Unit),
+            (Decode::Bytes, Frame::Simple(value)) => Ok(Reply::Bytes(Some(value.into()))),
+            (Decode::Bytes, Frame::Bulk(value)) => Ok(Reply::Bytes(Some(value))),
+            (Decode::Bytes, Frame::Null) => Ok(Reply::Bytes(None)),
+            (Decode::Integer, Frame::Integer(value)) => Ok(Reply::Integer(value)),
+            // `execute` 发送前已拒绝
+            (Decode::Raw, _) => unreachable!(),
+            (_, frame) => Err(frame.to_error()),
+        }
+    }
+}
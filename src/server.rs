@@ -1,29 +1,196 @@
 use std::{
     future::Future,
+    io,
+    net::SocketAddr,
+    path::PathBuf,
     sync::Arc,
 };
 
+use bytes::Bytes;
+
 use tokio::{
     net::{TcpListener, TcpStream},
-    sync::{broadcast, mpsc, Semaphore},
-    time::{self, Duration},
+    sync::{broadcast, mpsc, oneshot, Semaphore},
+    time::{self, Duration, Instant},
 };
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::{Connection, Db, DbDropGuard, Frame, Shutdown, Command};
+
+/// 服务端的配置，未指定的项使用 `Config::default()` 的值
+///
+/// ```
+/// use mini_redis::server::Config;
+///
+/// let config = Config {
+///     enable_debug_command: true,
+///     ..Config::default()
+/// };
+/// # let _ = config;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// 是否允许执行 `DEBUG` 命令，默认不允许
+    pub enable_debug_command: bool,
+
+    /// 收到未知命令时的处理方式，默认返回错误
+    pub unknown_command: UnknownCommandMode,
+
+    /// 执行时间达到该值的命令会被记录到慢日志中，默认 10ms，`None` 时不记录
+    pub slowlog_log_slower_than: Option<Duration>,
+
+    /// `SAVE` 写入的快照文件，服务启动时若文件存在则从中恢复数据
+    /// 默认为 `None`，即不保存也不恢复
+    pub save_path: Option<PathBuf>,
+
+    /// 是否在键被修改时发布键空间通知，默认不发布
+    pub notify_keyspace_events: bool,
+
+    /// 是否为发布的消息附加频道内逐条递增的序号，默认不附加
+    /// 序号作为非标准的一项附加在 `message`/`pmessage` 的最后，重新订阅的客户端可以据此发现丢失的消息
+    pub message_sequence: bool,
+
+    /// 回复中单个 bulk 的最大字节数，默认 512MB，运行时可通过 `CONFIG SET` 修改
+    pub proto_max_bulk_len: usize,
+
+    /// 写入时未指定有效期的键使用的有效期，默认为 `None`，即不过期
+    /// 对 `SET`/`MSET` 等生效，显式指定的有效期及 `KEEPTTL` 优先
+    pub default_ttl: Option<Duration>,
+
+    /// 订阅模式下客户端待发送数据的上限，超过后断开连接
+    pub pubsub_output_buffer_limit: OutputBufferLimit,
+
+    /// 接受连接出错时的处理方式，默认多次重试仍失败后停止服务
+    pub accept_error: AcceptErrorPolicy,
+
+    /// 每个连接每秒最多执行的命令数量，超过时回复错误，默认为 `None`，即不限制
+    /// 允许积累最多一秒的突发，运行时可通过 `CONFIG SET rate-limit` 修改
+    pub rate_limit: Option<u64>,
+
+    /// 客户端空闲超过此时间未发送命令时关闭连接，默认为 `DEFAULT_IDLE_TIMEOUT`
+    /// 为 `None` 时不关闭，订阅模式下的连接不受影响
+    pub idle_timeout: Option<Duration>,
+}
+
+/// 默认的客户端空闲超时时间
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            enable_debug_command: false,
+            unknown_command: UnknownCommandMode::default(),
+            slowlog_log_slower_than: Some(Duration::from_millis(10)),
+            save_path: None,
+            notify_keyspace_events: false,
+            message_sequence: false,
+            proto_max_bulk_len: crate::db::DEFAULT_PROTO_MAX_BULK_LEN,
+            default_ttl: None,
+            pubsub_output_buffer_limit: OutputBufferLimit::default(),
+            accept_error: AcceptErrorPolicy::default(),
+            rate_limit: None,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+        }
+    }
+}
+
+/// 客户端待发送数据的上限，与 Redis 的 `client-output-buffer-limit` 相同
+/// 不读取消息的订阅者会使服务端不断积压数据，超过上限后断开其连接
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    /// 待发送数据超过此字节数时立即断开，0 表示不限制
+    pub hard: usize,
+    /// 待发送数据持续超过此字节数达到 `soft_duration` 后断开，0 表示不限制
+    pub soft: usize,
+    /// 待发送数据持续超过 `soft` 多久后断开
+    pub soft_duration: Duration,
+}
+
+impl Default for OutputBufferLimit {
+    /// 与 Redis 的默认值相同：32MB，或持续 60 秒超过 8MB
+    fn default() -> OutputBufferLimit {
+        OutputBufferLimit {
+            hard: 32 * 1024 * 1024,
+            soft: 8 * 1024 * 1024,
+            soft_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+impl OutputBufferLimit {
+    /// 待发送的数据是否超过了软上限
+    pub(crate) fn is_over_soft(&self, pending: usize) -> bool {
+        self.soft > 0 && pending > self.soft
+    }
+
+    /// 待发送 `pending` 字节时是否应断开连接
+    /// `over_soft_since` 记录开始超过软上限的时间，由调用方在多次检查间保存
+    pub(crate) fn is_exceeded(&self, pending: usize, over_soft_since: &mut Option<Instant>) -> bool {
+        if self.hard > 0 && pending > self.hard {
+            return true;
+        }
+
+        if !self.is_over_soft(pending) {
+            *over_soft_since = None;
+            return false;
+        }
+
+        over_soft_since.get_or_insert_with(Instant::now).elapsed() >= self.soft_duration
+    }
+}
+
+/// 收到未知命令时的处理方式，普通模式与订阅模式下相同
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownCommandMode {
+    /// 向客户端返回错误
+    #[default]
+    Strict,
+    /// 只记录日志，不返回任何内容，连接继续处理后续命令
+    /// 适用于需要容忍未知命令的代理
+    Lenient,
+}
+
+/// 接受连接出错时的处理方式
+/// 两种方式都在出错后等待并重试，等待的间隔从 1 秒开始每次加倍
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AcceptErrorPolicy {
+    /// 等待的间隔超过 64 秒后仍然出错，则停止服务
+    #[default]
+    Fail,
+    /// 记录日志并一直重试，等待的间隔最长为 64 秒
+    /// 适用于长期运行的服务，如暂时用尽文件描述符时不应停止服务
+    Retry,
+}
 
-use crate::{Connection, Db, DbDropGuard, Shutdown, Command};
+/// 接受入站连接，服务端默认使用 `TcpListener`
+/// 可以替换为其它实现，如在测试中模拟接受连接出错
+pub trait Accept: Send {
+    /// 接受一个入站连接
+    fn accept(&mut self) -> impl Future<Output = io::Result<TcpStream>> + Send;
+}
+
+impl Accept for TcpListener {
+    async fn accept(&mut self) -> io::Result<TcpStream> {
+        let (socket, _) = TcpListener::accept(self).await?;
+        Ok(socket)
+    }
+}
 
 /// 服务监听器，运行在 Server 端，处理连接事项
 #[derive(Debug)]
-struct Listener {
+struct Listener<L> {
     /// 共享的数据库
     db_holder: DbDropGuard,
 
-    /// Tcp 监听器
-    listener: TcpListener,
+    /// 接受入站连接，通常为 `TcpListener`
+    listener: L,
 
     /// 服务器最大连接数
     limit_connections: Arc<Semaphore>,
 
+    /// 服务端配置，所有连接共享
+    config: Arc<Config>,
+
     /// 向所有存活的连接发送关闭信号，优雅地关闭服务
     /// 在执行 `run` 时初始化 `shutdown`
     notify_shutdown: broadcast::Sender<()>,
@@ -45,19 +212,67 @@ struct Handler {
     /// 监听关闭通知
     shutdown: Shutdown,
 
+    /// 服务端配置
+    config: Arc<Config>,
+
+    /// 限制此连接执行命令的速率
+    rate_limiter: RateLimiter,
+
     /// 当所有连接处理程序关闭后，且 `Listener` 亦关闭了发送端，
     /// 则 shutdown_complete_rx 会收到 `None`，服务端知道所有连接已关闭
     _shutdown_complete: mpsc::Sender<()>,
 }
 
+/// 令牌桶，每秒补充 `limit` 个令牌，最多积累 `limit` 个，每条命令消耗一个
+#[derive(Debug)]
+struct RateLimiter {
+    tokens: f64,
+    last_refill: Instant,
+}
+
 /// Redis 服务端接收的最大连接数
 const MAX_CONNECTIONS: usize = 255;
 
+/// 接受连接出错后重试的最长间隔（秒）
+const MAX_ACCEPT_BACKOFF: u64 = 64;
+
 /// 运行 mini-redis 服务
 /// 接收 `TcpListener` 里的连接，并生成一个任务处理该连接
 /// 服务将一直运行，直到 `shutdown` 完成，这意味着此时服务可被优雅地关闭
 /// 可使用 `tokio::signal::ctrl_c()` 作为 `shutdown` 的参数，来接收 `SIGINT` 信号
 pub async fn run(listener: TcpListener, shutdown: impl Future) {
+    run_with_config(listener, shutdown, Config::default()).await
+}
+
+/// 与 `run` 相同，但使用指定的配置运行服务
+pub async fn run_with_config(listener: TcpListener, shutdown: impl Future, config: Config) {
+    run_with_listener(listener, shutdown, config).await
+}
+
+/// 与 `run` 相同，但在开始接受连接时通过 `ready_tx` 发送监听的地址
+/// 收到地址后连接服务不会与服务的启动（如恢复快照）竞争；接收端已被丢弃时忽略
+pub async fn run_with_ready(listener: TcpListener, shutdown: impl Future, ready_tx: oneshot::Sender<SocketAddr>) {
+    let addr = match listener.local_addr() {
+        Ok(addr) => addr,
+        Err(err) => {
+            error!(cause = %err, "failed to get local address");
+            return;
+        },
+    };
+
+    serve(listener, shutdown, Config::default(), move || {
+        let _ = ready_tx.send(addr);
+    })
+    .await
+}
+
+/// 与 `run_with_config` 相同，但从任意实现了 `Accept` 的监听器接受连接
+pub async fn run_with_listener(listener: impl Accept, shutdown: impl Future, config: Config) {
+    serve(listener, shutdown, config, || {}).await
+}
+
+/// 运行服务，完成初始化、即将开始接受连接时调用 `ready`
+async fn serve(listener: impl Accept, shutdown: impl Future, config: Config, ready: impl FnOnce()) {
     // 关闭服务时用到的广播发送端和确认连接关闭的 complete 隧道
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel(1);
@@ -67,11 +282,28 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
         db_holder: DbDropGuard::new(),
         listener,
         limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        config: Arc::new(config),
         notify_shutdown,
         shutdown_complete_rx,
         shutdown_complete_tx,
     };
 
+    server.db_holder.db().set_keyspace_events(server.config.notify_keyspace_events);
+    server.db_holder.db().set_message_sequence(server.config.message_sequence);
+    server.db_holder.db().set_proto_max_bulk_len(server.config.proto_max_bulk_len);
+    server.db_holder.db().set_default_ttl(server.config.default_ttl);
+    server.db_holder.db().set_rate_limit(server.config.rate_limit);
+
+    // 从快照文件中恢复数据
+    if let Some(path) = &server.config.save_path {
+        match server.db_holder.db().load(path) {
+            Ok(keys) => info!(keys, path = %path.display(), "loaded snapshot"),
+            Err(err) => error!(cause = %err, path = %path.display(), "failed to load snapshot"),
+        }
+    }
+
+    ready();
+
     tokio::select! {
         res = server.run() => {
             // 若服务异常退出，这里抓一下日志
@@ -84,6 +316,7 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
 
     // 从 server 中得到关闭消息隧道
     let Listener {
+        db_holder,
         mut shutdown_complete_rx,
         shutdown_complete_tx,
         notify_shutdown,
@@ -95,9 +328,12 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     drop(shutdown_complete_tx);
 
     let _ = shutdown_complete_rx.recv().await;
+
+    // 所有连接都已关闭，最后等待后台清理任务退出
+    db_holder.shutdown().await;
 }
 
-impl Listener {
+impl<L: Accept> Listener<L> {
     /// 运行服务
     /// 监听入站连接，并为每个入站连接生成一个任务
     async fn run(&mut self) -> crate::Result<()> {
@@ -117,11 +353,17 @@ impl Listener {
 
             let socket = self.accept().await?;
 
+            // 读取时的长度上限与 `proto-max-bulk-len` 相同，`CONFIG SET` 修改后在 `Handler::run` 中更新
+            let mut connection = Connection::with_limits(socket, self.db_holder.db().proto_max_bulk_len());
+            connection.set_read_timeout(self.config.idle_timeout);
+
             let mut handler = Handler {
                 db: self.db_holder.db(),
-                connection: Connection::new(socket),
+                connection,
                 // subscribe 返回接收端
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+                config: self.config.clone(),
+                rate_limiter: RateLimiter::new(),
                 // 当所有的 self.shutdown_complete_tx 端被丢弃后，接收端会得到通知
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
             };
@@ -138,17 +380,23 @@ impl Listener {
     }
 
     /// 接收一个入站连接
-    /// 若成功则返回一个 `TcpStream` 流，失败后等待并重试
+    /// 若成功则返回一个 `TcpStream` 流，失败后等待并重试，按 `AcceptErrorPolicy` 决定是否放弃
     async fn accept(&mut self) -> crate::Result<TcpStream> {
         let mut backoff = 1;
 
         loop {
             match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+                Ok(socket) => return Ok(socket),
                 Err(err) => {
-                    if backoff > 64 {
-                        return Err(err.into())
+                    if backoff > MAX_ACCEPT_BACKOFF {
+                        match self.config.accept_error {
+                            AcceptErrorPolicy::Fail => return Err(err.into()),
+                            // 间隔不再增长，一直重试
+                            AcceptErrorPolicy::Retry => backoff = MAX_ACCEPT_BACKOFF,
+                        }
                     }
+
+                    warn!(cause = %err, backoff, "failed to accept, retrying");
                 },
             }
 
@@ -161,16 +409,28 @@ impl Listener {
 impl Handler {
     /// 处理单个连接
     /// 从套接字时读取 frames ，处理并写入返回消息
-    /// 接收到关闭信号后直接退出
+    /// 接收到关闭信号后，将尚未发出的数据发送完再退出
     #[instrument(skip(self))]
     async fn run(&mut self) -> crate::Result<()> {
         // 服务没收到关闭信号时
-        while !self.shutdown.is_shutdown() {
+        while !self.shutdown.is_shutdown() && !self.connection.is_closing() {
+            // `CONFIG SET` 可能修改了读取时的长度上限
+            self.connection.set_max_read_len(self.db.proto_max_bulk_len());
+
             let frame = tokio::select! {
                 // 从连接中有可读消息
-                res = self.connection.read_frame() => res?,
-                // 接收到关闭信号，退出
+                res = self.connection.read_frame() => match res {
+                    Ok(frame) => frame,
+                    // 空闲超时，关闭连接
+                    Err(err) if Connection::is_timeout(&err) => {
+                        debug!("idle client timed out");
+                        return Ok(())
+                    },
+                    Err(err) => return Err(err),
+                },
+                // 接收到关闭信号，发送完尚未发出的数据后退出
                 _ = self.shutdown.recv() => {
+                    self.connection.flush().await?;
                     return Ok(())
                 }
             };
@@ -182,12 +442,94 @@ impl Handler {
             };
 
             // 从 `frames` 里解析出命令
-            let cmd = Command::from_frame(frame)?;
+            // 开启慢日志时先保留一份参数，`Bytes` 的复制只是增加引用计数
+            let args = self.config.slowlog_log_slower_than.map(|_| command_args(&frame));
+
+            // 命令有误时回复错误，连接继续处理后续命令
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    let response = Frame::Error(err.to_string());
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                },
+            };
             debug!(?cmd);
 
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown).await?;
+            // `CONFIG SET` 可能在其它连接上修改了限制
+            self.connection.set_max_bulk_len(self.db.proto_max_bulk_len());
+
+            // 超过速率限制的命令不执行，连接继续处理后续命令
+            if let Some(limit) = self.db.rate_limit() {
+                if !self.rate_limiter.try_acquire(limit) {
+                    let response = Frame::Error("ERR rate limit exceeded".to_string());
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                }
+            }
+
+            if matches!(cmd, Command::Debug(_)) && !self.config.enable_debug_command {
+                let response = Frame::Error("ERR DEBUG command not allowed. Enable it with enable-debug-command".to_string());
+                self.connection.write_frame(&response).await?;
+                continue;
+            }
+
+            // 订阅命令会一直执行到取消订阅，不计入慢日志
+            let is_subscribe = matches!(cmd, Command::Subscribe(_) | Command::Psubscribe(_));
+            let start = std::time::Instant::now();
+
+            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown, &self.config).await?;
+
+            if let (Some(threshold), Some(args)) = (self.config.slowlog_log_slower_than, args) {
+                let elapsed = start.elapsed();
+                if !is_subscribe && elapsed >= threshold {
+                    self.db.slowlog_push(args, elapsed, self.connection.peer_addr().ok());
+                }
+            }
         }
 
         Ok(())
     }
 }
+
+impl RateLimiter {
+    /// 新建的令牌桶是满的
+    fn new() -> RateLimiter {
+        RateLimiter {
+            tokens: f64::INFINITY,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 按经过的时间补充令牌，有令牌时消耗一个并返回 `true`
+    /// `limit` 可能被 `CONFIG SET` 修改，每次调用时传入当前的值
+    fn try_acquire(&mut self, limit: u64) -> bool {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last_refill).as_secs_f64() * limit as f64;
+        self.tokens = (self.tokens + refill).min(limit as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 取出命令名及参数，用于记录慢日志
+fn command_args(frame: &Frame) -> Vec<Bytes> {
+    match frame {
+        Frame::Array(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Frame::Bulk(data) => data.clone(),
+                Frame::Simple(s) => Bytes::from(s.clone()),
+                Frame::Integer(num) => Bytes::from(num.to_string()),
+                frame => Bytes::from(frame.to_string()),
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,105 @@
+//! 慢命令日志，记录执行时间超过阈值的命令
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// 最多保留的日志条数，超出后丢弃最早的记录
+const MAX_LEN: usize = 128;
+
+/// 每条记录最多保留的参数个数
+const MAX_ARGS: usize = 32;
+
+/// 每个参数最多保留的字节数
+const MAX_ARG_LEN: usize = 128;
+
+/// 固定容量的环形缓冲区，新记录在前
+#[derive(Debug, Default)]
+pub(crate) struct SlowLog {
+    entries: VecDeque<SlowLogEntry>,
+    next_id: u64,
+}
+
+/// 一条慢命令记录
+#[derive(Debug, Clone)]
+pub(crate) struct SlowLogEntry {
+    /// 自增的唯一标识
+    pub(crate) id: u64,
+    /// 命令执行完毕时的 Unix 时间戳（秒）
+    pub(crate) timestamp: u64,
+    /// 执行耗时
+    pub(crate) duration: Duration,
+    /// 命令名及参数，过多或过长时被截断
+    pub(crate) args: Vec<Bytes>,
+    /// 客户端地址
+    pub(crate) addr: Option<SocketAddr>,
+}
+
+impl SlowLog {
+    /// 添加一条记录
+    pub(crate) fn push(&mut self, args: Vec<Bytes>, duration: Duration, addr: Option<SocketAddr>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+
+        let entry = SlowLogEntry {
+            id: self.next_id,
+            timestamp,
+            duration,
+            args: truncate(args),
+            addr,
+        };
+        self.next_id += 1;
+
+        if self.entries.len() == MAX_LEN {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(entry);
+    }
+
+    /// 返回最近的 `count` 条记录，`None` 时返回全部
+    pub(crate) fn get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        self.entries
+            .iter()
+            .take(count.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// 当前的记录条数
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 清空所有记录
+    pub(crate) fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// 与 Redis 一样截断过多的参数与过长的参数，避免慢日志占用过多内存
+fn truncate(mut args: Vec<Bytes>) -> Vec<Bytes> {
+    if args.len() > MAX_ARGS {
+        let more = args.len() - MAX_ARGS + 1;
+        args.truncate(MAX_ARGS - 1);
+        args.push(Bytes::from(format!("... ({} more arguments)", more)));
+    }
+
+    args.into_iter()
+        .map(|arg| {
+            if arg.len() <= MAX_ARG_LEN {
+                return arg;
+            }
+
+            let suffix = format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN);
+            let mut truncated = BytesMut::with_capacity(MAX_ARG_LEN + suffix.len());
+            truncated.put_slice(&arg[..MAX_ARG_LEN]);
+            truncated.put_slice(suffix.as_bytes());
+            truncated.freeze()
+        })
+        .collect()
+}
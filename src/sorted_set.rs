@@ -0,0 +1,156 @@
+//! 有序集合，每个成员关联一个分值，成员按分值（分值相同时按成员）排序
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    ops::Bound,
+};
+
+use bytes::Bytes;
+
+/// 有序集合
+/// `scores` 用于按成员查找分值，`ordered` 用于按分值排序、范围查找，两者需保持一致
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+/// 可排序的分值，`NaN` 不会被存入有序集合
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl SortedSet {
+    /// 创建一个空的有序集合
+    pub(crate) fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    /// 添加成员或更新已有成员的分值，新添加时返回 `true`
+    pub(crate) fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        // 统一 -0.0 与 0.0
+        let score = score + 0.0;
+
+        match self.scores.insert(member.clone(), score) {
+            Some(prev) => {
+                self.ordered.remove(&(Score(prev), member.clone()));
+                self.ordered.insert((Score(score), member));
+                false
+            },
+            None => {
+                self.ordered.insert((Score(score), member));
+                true
+            },
+        }
+    }
+
+    /// 删除成员，成员存在时返回 `true`
+    pub(crate) fn remove(&mut self, member: &Bytes) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.clone()));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// 为成员的分值加上 `increment`，成员不存在时视其分值为 0，返回新的分值
+    /// 结果为 `NaN`（如 `+inf` 加 `-inf`）时不做修改并返回 `None`
+    pub(crate) fn incr(&mut self, member: Bytes, increment: f64) -> Option<f64> {
+        let score = self.scores.get(&member).copied().unwrap_or(0.0) + increment;
+        if score.is_nan() {
+            return None;
+        }
+
+        self.insert(member, score);
+        Some(score + 0.0)
+    }
+
+    /// 返回成员的分值，成员不存在时返回 `None`
+    pub(crate) fn score(&self, member: &Bytes) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// 返回成员按分值从小到大的排名（从 0 开始），成员不存在时返回 `None`
+    pub(crate) fn rank(&self, member: &Bytes) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+
+        Some(self.ordered.range(..(Score(score), member.clone())).count())
+    }
+
+    /// 成员的数量
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// 按分值从小到大遍历所有成员
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// 有序集合中是否没有成员
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// 按分值从小到大遍历分值在 `min` 与 `max` 之间的成员
+    pub(crate) fn range_by_score(&self, min: Bound<f64>, max: Bound<f64>) -> impl Iterator<Item = (&Bytes, f64)> {
+        // 空的 `Bytes` 是最小的成员，从分值等于下界的第一个成员开始查找
+        let start = match min {
+            Bound::Included(score) | Bound::Excluded(score) => {
+                Bound::Included((Score(score), Bytes::new()))
+            },
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .skip_while(move |(score, _)| matches!(min, Bound::Excluded(min) if score.0 <= min))
+            .take_while(move |(score, _)| match max {
+                Bound::Included(max) => score.0 <= max,
+                Bound::Excluded(max) => score.0 < max,
+                Bound::Unbounded => true,
+            })
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// 估算成员及分值占用的内存
+    pub(crate) fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+
+        self.scores
+            .keys()
+            .map(|member| 2 * (size_of::<Bytes>() + size_of::<f64>()) + member.len())
+            .sum()
+    }
+}
+
+/// 从字符串解析出分值，支持 `inf`/`+inf`/`-inf`，`NaN` 视为非法
+pub(crate) fn parse_score(src: &str) -> Option<f64> {
+    src.parse::<f64>().ok().filter(|score| !score.is_nan())
+}
+
+/// 将分值格式化为返回给客户端的字符串，如 `1`、`1.5`、`inf`
+pub(crate) fn format_score(score: f64) -> Bytes {
+    Bytes::from(score.to_string())
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Score) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
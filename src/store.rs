@@ -0,0 +1,65 @@
+//! 命令使用的键值存储接口
+//!
+//! 服务端使用 `Db` 实现，测试时可以换成记录调用的实现，
+//! 单独检查命令的逻辑而无需启动服务
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::time::Instant;
+
+use crate::{cmd::{SetOptions, SetOutcome}, Db};
+
+/// `GET`/`SET`/`DEL`/`INCR` 等字符串命令及 `TTL`/`PTTL` 使用的存储操作
+pub trait KvStore {
+    /// 查找键对应的字符串，键存储的不是字符串时返回错误
+    fn get(&self, key: &str) -> crate::Result<Option<Bytes>>;
+
+    /// 设置键的值及可选的有效期，覆盖已有的值
+    fn set(&self, key: String, value: Bytes, expire: Option<Duration>);
+
+    /// 按 `options` 设置键的值，条件检查与写入需在一次操作中完成
+    /// 指定了 `GET` 而键存储的不是字符串时返回错误，且不写入
+    fn set_with_options(&self, key: String, value: Bytes, options: &SetOptions) -> crate::Result<SetOutcome>;
+
+    /// 删除键，返回实际删除的数量
+    fn del(&self, keys: &[String]) -> usize;
+
+    /// 将键存储的整数加上 `delta`，返回新的值，键不存在时视其值为 0
+    fn incr_by(&self, key: &str, delta: i64) -> crate::Result<i64>;
+
+    /// 键的过期时刻，外层的 `None` 表示键不存在，内层的 `None` 表示键没有有效期
+    fn ttl(&self, key: &str) -> Option<Option<Instant>>;
+
+    /// 移除键的有效期，键存在且有有效期时返回 `true`
+    fn persist(&self, key: &str) -> bool;
+}
+
+impl KvStore for Db {
+    fn get(&self, key: &str) -> crate::Result<Option<Bytes>> {
+        Db::get(self, key)
+    }
+
+    fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
+        Db::set(self, key, value, expire)
+    }
+
+    fn set_with_options(&self, key: String, value: Bytes, options: &SetOptions) -> crate::Result<SetOutcome> {
+        Db::set_with_options(self, key, value, options)
+    }
+
+    fn del(&self, keys: &[String]) -> usize {
+        Db::del(self, keys)
+    }
+
+    fn incr_by(&self, key: &str, delta: i64) -> crate::Result<i64> {
+        Db::incr_by(self, key, delta)
+    }
+
+    fn ttl(&self, key: &str) -> Option<Option<Instant>> {
+        Db::ttl(self, key)
+    }
+
+    fn persist(&self, key: &str) -> bool {
+        Db::persist(self, key)
+    }
+}
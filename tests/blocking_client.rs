@@ -0,0 +1,121 @@
+use std::{
+    net::SocketAddr,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    net::TcpListener,
+    runtime::Runtime,
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+use mini_redis::{blocking_client, server, Connection, Frame};
+
+/// 服务端重启后，开启断线重连的订阅迭代器可以继续接收消息
+#[test]
+fn subscriber_iterator_reconnects() {
+    let rt = Runtime::new().unwrap();
+
+    let (addr, stop, server) = start_server(&rt, "127.0.0.1:0".parse().unwrap());
+
+    let client = blocking_client::connect(addr).unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).unwrap();
+    subscriber.set_reconnect(true);
+
+    // 在单独的线程里阻塞地迭代消息
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for message in subscriber {
+            if tx.send(message).is_err() {
+                break
+            }
+        }
+    });
+
+    let mut publisher = blocking_client::connect(addr).unwrap();
+    publisher.publish("hello", "before".into()).unwrap();
+
+    let message = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+    assert_eq!(b"before", &message.content[..]);
+
+    // 关闭服务，并在相同的地址上重新启动
+    stop.send(()).unwrap();
+    rt.block_on(server).unwrap();
+    let (_, _stop, _server) = start_server(&rt, addr);
+
+    // 订阅端重连前发布的消息会丢失，因此持续发布直到收到消息
+    let mut publisher = blocking_client::connect(addr).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    let message = loop {
+        assert!(Instant::now() < deadline, "subscriber did not resume");
+
+        publisher.publish("hello", "after".into()).unwrap();
+
+        if let Ok(message) = rx.recv_timeout(Duration::from_millis(50)) {
+            break message.unwrap();
+        }
+    };
+
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"after", &message.content[..]);
+}
+
+/// `scan_iter` 以阻塞的方式遍历所有匹配的键，每个键恰好产生一次
+#[test]
+fn scan_iter_yields_every_key_once() {
+    let rt = Runtime::new().unwrap();
+
+    let (addr, _stop, _server) = start_server(&rt, "127.0.0.1:0".parse().unwrap());
+
+    let mut client = blocking_client::connect(addr).unwrap();
+    for i in 0..100 {
+        client.set(&format!("key:{}", i), "v".into()).unwrap();
+    }
+    client.set("other", "v".into()).unwrap();
+
+    let mut keys: Vec<String> = client.scan_iter(Some("key:*".into())).map(Result::unwrap).collect();
+    keys.sort();
+
+    let mut expected: Vec<String> = (0..100).map(|i| format!("key:{}", i)).collect();
+    expected.sort();
+    assert_eq!(expected, keys);
+}
+
+/// `close` 等待服务端回复 `QUIT` 后关闭连接，服务端读到 EOF
+#[test]
+fn close_reaches_server() {
+    let rt = Runtime::new().unwrap();
+
+    let listener = rt.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = rt.spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = Connection::new(socket);
+
+        conn.read_frame().await.unwrap().unwrap();
+        conn.write_frame(&Frame::ok()).await.unwrap();
+
+        conn.read_frame().await.unwrap()
+    });
+
+    let client = blocking_client::connect(addr).unwrap();
+    client.close().unwrap();
+
+    assert!(rt.block_on(server).unwrap().is_none());
+}
+
+/// 在 `addr` 上启动服务，返回实际的地址、关闭服务的发送端及服务的任务句柄
+fn start_server(rt: &Runtime, addr: SocketAddr) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let listener = rt.block_on(TcpListener::bind(addr)).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let handle = rt.spawn(server::run(listener, rx));
+
+    (addr, tx, handle)
+}
@@ -0,0 +1,57 @@
+use std::process::Command;
+
+/// `--list-commands` 无需连接服务端即可列出支持的命令
+#[test]
+fn list_commands_offline() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-redis-cli"))
+        .arg("--list-commands")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let commands: Vec<&str> = stdout.lines().collect();
+
+    for command in ["ping", "get", "set", "publish", "subscribe"] {
+        assert!(commands.contains(&command), "missing `{}` in {:?}", command, commands);
+    }
+}
+
+/// 服务端按 `--log-format` 与 `--log-level` 初始化日志，无效的级别及格式在启动时报错
+#[test]
+fn server_log_options() {
+    use std::{io::{BufRead, BufReader}, process::Stdio};
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-redis-server"))
+        .args(["--log-level", "loud"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mini-redis-server"))
+        .args(["--log-format", "compact"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_mini-redis-server"))
+        .args(["--port", "0", "--log-format", "json", "--log-level", "debug"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // 日志初始化成功后，服务开始接受连接时会记录一条日志，每行都是一个 JSON 对象
+    let stdout = BufReader::new(server.stdout.take().unwrap());
+    let started = stdout
+        .lines()
+        .map(Result::unwrap)
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str::<serde_json::Value>(&line).unwrap())
+        .any(|log| log["fields"]["message"] == "accepting inbound connections");
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(started);
+}
@@ -1,8 +1,10 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, ops::Bound, time::Duration};
 
-use tokio::net::TcpListener;
+use bytes::Bytes;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, sync::oneshot, task::JoinHandle, time};
+use tokio_stream::StreamExt;
 
-use mini_redis::{client, server};
+use mini_redis::{client, cmd::{GeoUnit, SetOptions}, connection, pipeline::{Pipeline, Reply}, server, Connection, Frame};
 
 /// ping 不附加消息，返回 `PONG`
 #[tokio::test]
@@ -28,6 +30,116 @@ async fn ping_with_hello() {
     assert_eq!("你好".as_bytes(), &response[..]);
 }
 
+/// 服务端正常运行时，`is_alive` 返回 `true`
+#[tokio::test]
+async fn is_alive_with_running_server() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client.is_alive(Duration::from_secs(1)).await);
+}
+
+/// 服务端关闭连接后，`is_alive` 返回 `false`
+#[tokio::test]
+async fn is_alive_with_closed_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // 接受连接后立即关闭
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        drop(socket);
+    });
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(!client.is_alive(Duration::from_secs(1)).await);
+}
+
+/// `is_alive` 超时后迟到的 `PONG` 不会被之后的命令读到，客户端不再可用
+#[tokio::test]
+async fn is_alive_timeout_poisons_client() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // 收到 `PING` 后延迟回复 `PONG`，之后的请求都回复 nil
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = Connection::new(socket);
+
+        conn.read_frame().await.unwrap().unwrap();
+        time::sleep(Duration::from_millis(200)).await;
+        conn.write_frame(&Frame::Simple("PONG".into())).await.unwrap();
+
+        while let Ok(Some(_)) = conn.read_frame().await {
+            conn.write_frame(&Frame::Null).await.unwrap();
+        }
+    });
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(!client.is_alive(Duration::from_millis(50)).await);
+
+    time::sleep(Duration::from_millis(300)).await;
+    assert!(client.get("foo").await.is_err());
+    assert!(!client.is_alive(Duration::from_secs(1)).await);
+}
+
+/// `close` 发送 `QUIT` 后关闭写端，服务端读到正常的 EOF 而不是连接被重置
+#[tokio::test]
+async fn close_sends_quit_and_eof() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut conn = Connection::new(socket);
+
+        let quit = conn.read_frame().await.unwrap().unwrap();
+        assert_eq!(Frame::Array(vec![Frame::Bulk("quit".into())]), quit);
+        conn.write_frame(&Frame::ok()).await.unwrap();
+
+        // 客户端关闭写端后 `read_frame` 返回 `None`，重置时返回错误
+        conn.read_frame().await.unwrap()
+    });
+
+    let client = client::connect(addr).await.unwrap();
+    client.close().await.unwrap();
+
+    assert_eq!(None, server.await.unwrap());
+}
+
+/// 锁被持有时再次获取返回 `None`，释放后可以重新获取，过期后的旧锁不会误删新锁
+#[tokio::test]
+async fn acquire_and_release_lock() {
+    let addr = start_server().await;
+
+    let mut owner = client::connect(addr).await.unwrap();
+    let mut other = client::connect(addr).await.unwrap();
+
+    let lock = owner.acquire_lock("lock", Duration::from_secs(10)).await.unwrap().unwrap();
+    assert_eq!("lock", lock.key());
+    assert_eq!(Some(lock.token().clone()), other.get("lock").await.unwrap());
+
+    assert!(other.acquire_lock("lock", Duration::from_secs(10)).await.unwrap().is_none());
+
+    // token 不同，不能删除别人的锁
+    assert!(!other.delifeq("lock", "not-the-token".into()).await.unwrap());
+
+    assert!(lock.clone().release(&mut owner).await.unwrap());
+    assert_eq!(None, other.get("lock").await.unwrap());
+
+    let relock = other.acquire_lock("lock", Duration::from_secs(10)).await.unwrap().unwrap();
+    assert_ne!(lock.token(), relock.token());
+
+    // 已释放的旧锁再次释放时不会删除新的持有者的锁
+    assert!(!lock.release(&mut owner).await.unwrap());
+    assert_eq!(Some(relock.token().clone()), owner.get("lock").await.unwrap());
+
+    assert!(relock.release(&mut other).await.unwrap());
+}
+
 /// 设置、查询键值
 #[tokio::test]
 async fn key_value_set_get() {
@@ -42,12 +154,589 @@ async fn key_value_set_get() {
     assert_eq!(b"world", &value[..]);
 }
 
+/// 回复超过读取上限时返回错误，调大上限后可以读取
+#[tokio::test]
+async fn get_over_read_limit() {
+    let addr = start_server().await;
+
+    let value = Bytes::from(vec![b'x'; connection::DEFAULT_MAX_FRAME_SIZE + 1]);
+
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("big", value.clone()).await.unwrap();
+    assert!(client.get("big").await.is_err());
+
+    let mut client = client::connect(addr).await.unwrap();
+    client.set_max_read_len(usize::MAX);
+    assert_eq!(Some(value), client.get("big").await.unwrap());
+}
+
+/// `DEL` 可以删除任意类型的键，只统计实际存在的键
+#[tokio::test]
+async fn del_all_types() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("string", "value".into()).await.unwrap();
+    client.set_expires("expiring", "value".into(), Duration::from_secs(60)).await.unwrap();
+    client.sadd("set", vec!["a".into()]).await.unwrap();
+    client.zadd("zset", vec![(1.0, "a".into())]).await.unwrap();
+
+    let keys: Vec<String> = ["string", "expiring", "set", "zset", "missing"]
+        .iter()
+        .map(|key| key.to_string())
+        .collect();
+    assert_eq!(4, client.del(&keys).await.unwrap());
+    assert_eq!(0, client.del(&keys).await.unwrap());
+
+    assert!(client.keys("*").await.unwrap().is_empty());
+
+    // 删除后的键可以重新写入其它类型
+    client.set("set", "value".into()).await.unwrap();
+    assert_eq!(Some("value".into()), client.get("set").await.unwrap());
+}
+
+/// `KEYS` 按模式匹配键
+#[tokio::test]
+async fn keys_with_pattern() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for key in ["hello", "hallo", "hxllo", "hllo", "heeeello"] {
+        client.set(key, "value".into()).await.unwrap();
+    }
+
+    let mut keys = client.keys("h?llo").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["hallo", "hello", "hxllo"], keys);
+
+    let mut keys = client.keys("h*llo").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["hallo", "heeeello", "hello", "hllo", "hxllo"], keys);
+
+    let mut keys = client.keys("h[^e]llo").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["hallo", "hxllo"], keys);
+
+    assert_eq!(vec!["hallo"], client.keys("h[a-b]llo").await.unwrap());
+    assert!(client.keys("nothing*").await.unwrap().is_empty());
+}
+
+/// `KEYS` 按前缀及字符集合匹配键
+#[tokio::test]
+async fn keys_with_prefix_and_set() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for key in ["user:1", "user:2", "user:10", "users", "session:1"] {
+        client.set(key, "value".into()).await.unwrap();
+    }
+
+    let mut keys = client.keys("user:*").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["user:1", "user:10", "user:2"], keys);
+
+    let mut keys = client.keys("user:?").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["user:1", "user:2"], keys);
+
+    let mut keys = client.keys("user[:s]*").await.unwrap();
+    keys.sort();
+    assert_eq!(vec!["user:1", "user:10", "user:2", "users"], keys);
+
+    assert_eq!(5, client.keys("*").await.unwrap().len());
+}
+
+/// 协商 RESP3 后，不存在的键的 RESP3 空值可以正确解析
+#[tokio::test]
+async fn hello_resp3_null() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(2, client.protocol());
+
+    let info = client.hello(Some(3)).await.unwrap();
+    assert_eq!("mini-redis", info.server);
+    assert_eq!(3, info.proto);
+    assert_eq!(3, client.protocol());
+
+    assert_eq!(None, client.get("missing").await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(Some("world".into()), client.get("hello").await.unwrap());
+
+    // RESP3 下的订阅消息同样可以接收
+    let mut subscriber = client.subscribe(vec!["foo".into()]).await.unwrap();
+    let mut publisher = client::connect(addr).await.unwrap();
+    publisher.publish("foo", "bar".into()).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("bar", message.content);
+}
+
+/// 流水线按顺序返回每条命令解码后的回复
+#[tokio::test]
+async fn pipeline_typed_replies() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let replies = Pipeline::new()
+        .set("hello", "world".into())
+        .get("hello")
+        .incr("counter")
+        .incr("counter")
+        .execute(&mut client)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            Reply::Unit,
+            Reply::Bytes(Some("world".into())),
+            Reply::Integer(1),
+            Reply::Integer(2),
+        ],
+        replies
+    );
+
+    // 命令出错时返回错误，但之后的请求仍能正常收到回复
+    let result = Pipeline::new()
+        .incr("hello")
+        .get("missing")
+        .execute(&mut client)
+        .await;
+    assert!(result.is_err());
+
+    assert_eq!(3, client.incr("counter").await.unwrap());
+}
+
+/// 流水线中的 100 条 `SET` 与 100 条 `GET` 按顺序返回对应的回复
+#[tokio::test]
+async fn pipeline_raw_replies() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let mut pipeline = Pipeline::new();
+    for i in 0..100 {
+        pipeline = pipeline.command(&["SET", &format!("key:{}", i), &format!("value:{}", i)]);
+    }
+    for i in 0..100 {
+        pipeline = pipeline.command(&["GET", &format!("key:{}", i)]);
+    }
+    // 出错的命令不影响其它回复
+    pipeline = pipeline.command(&["INCR", "key:0"]);
+
+    let replies = pipeline.execute_raw(&mut client).await.unwrap();
+    assert_eq!(201, replies.len());
+
+    for reply in &replies[..100] {
+        assert_eq!(&Frame::ok(), reply);
+    }
+    for (i, reply) in replies[100..200].iter().enumerate() {
+        assert_eq!(&Frame::Bulk(format!("value:{}", i).into()), reply);
+    }
+    assert!(matches!(replies[200], Frame::Error(_)));
+
+    // 未解码的命令不能由 `execute` 执行，且不会发送任何命令
+    let result = Pipeline::new().set("unsent", "value".into()).command(&["PING"]).execute(&mut client).await;
+    assert!(result.is_err());
+    assert_eq!(None, client.get("unsent").await.unwrap());
+}
+
+/// 流水线的所有命令只 flush 一次，服务端一次读取就收到整批命令
+#[tokio::test]
+async fn pipeline_flushes_once() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    const COMMANDS: usize = 200;
+
+    let mut pipeline = Pipeline::new();
+    let mut request = vec![];
+    for i in 0..COMMANDS {
+        let key = format!("key:{}", i);
+        pipeline = pipeline.command(&["GET", &key]);
+        request.extend_from_slice(format!("*2\r\n$3\r\nGET\r\n${}\r\n{}\r\n", key.len(), key).as_bytes());
+    }
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        // 逐条 flush 时第一次读取只能读到已发出的部分命令
+        let mut buf = vec![0; 64 * 1024];
+        let n = socket.read(&mut buf).await.unwrap();
+        socket.write_all(&b"$-1\r\n".repeat(COMMANDS)).await.unwrap();
+
+        buf.truncate(n);
+        buf
+    });
+
+    let mut client = client::connect(addr).await.unwrap();
+    let replies = pipeline.execute_raw(&mut client).await.unwrap();
+    assert_eq!(vec![Frame::Null; COMMANDS], replies);
+
+    assert_eq!(request, server.await.unwrap());
+}
+
+/// `GETRANGE` 截取一个较大的值的开头部分
+#[tokio::test]
+async fn getrange_prefix_of_large_value() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let value: Vec<u8> = (0..4 * 1024 * 1024).map(|i| b'a' + (i % 26) as u8).collect();
+    client.set("large", value.into()).await.unwrap();
+
+    let prefix = client.getrange("large", 0, 9).await.unwrap();
+    assert_eq!("abcdefghij", prefix);
+}
+
+/// `GETRANGE` 支持负数下标，越界时截断，键不存在时返回空值
+#[tokio::test]
+async fn getrange_bounds() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("key", "Hello World".into()).await.unwrap();
+
+    assert_eq!("Hell", client.getrange("key", 0, 3).await.unwrap());
+    assert_eq!("orld", client.getrange("key", -4, -1).await.unwrap());
+    assert_eq!("Hello World", client.getrange("key", 0, -1).await.unwrap());
+    assert_eq!("World", client.getrange("key", 6, 100).await.unwrap());
+    assert!(client.getrange("key", 20, 30).await.unwrap().is_empty());
+    assert!(client.getrange("key", -1, -5).await.unwrap().is_empty());
+    assert!(client.getrange("missing", 0, -1).await.unwrap().is_empty());
+}
+
+/// `GETRANGE` 的边界情况：负数下标越界时截断到开头，start 在 end 之后时为空，空值的任何范围都为空
+#[tokio::test]
+async fn getrange_edge_cases() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("key", "Hello World".into()).await.unwrap();
+
+    // 负数下标越过开头时截断为 0
+    assert_eq!("Hello World", client.getrange("key", -100, -1).await.unwrap());
+    assert_eq!("Hello", client.getrange("key", -100, 4).await.unwrap());
+    assert_eq!("H", client.getrange("key", -100, -50).await.unwrap());
+    // 最后一个字节
+    assert_eq!("d", client.getrange("key", -1, -1).await.unwrap());
+    assert_eq!("d", client.getrange("key", 10, 10).await.unwrap());
+    // 正数下标越过末尾时截断为最后一个字节
+    assert_eq!("Hello World", client.getrange("key", 0, i64::MAX).await.unwrap());
+    assert!(client.getrange("key", 11, 11).await.unwrap().is_empty());
+    // start 在 end 之后
+    assert!(client.getrange("key", 5, 2).await.unwrap().is_empty());
+    assert!(client.getrange("key", -2, 3).await.unwrap().is_empty());
+
+    client.set("empty", Bytes::new()).await.unwrap();
+    assert!(client.getrange("empty", 0, -1).await.unwrap().is_empty());
+    assert!(client.getrange("empty", 0, 0).await.unwrap().is_empty());
+    assert!(client.getrange("empty", -1, -1).await.unwrap().is_empty());
+}
+
+/// 集合运算结果保存至目标键
+#[tokio::test]
+async fn set_operation_store() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.sadd("a", vec!["1".into(), "2".into()]).await.unwrap();
+    client.sadd("b", vec!["2".into(), "3".into()]).await.unwrap();
+
+    let len = client.sunionstore("dst", &["a".into(), "b".into()]).await.unwrap();
+    assert_eq!(3, len);
+
+    let len = client.sinterstore("dst", &["a".into(), "b".into()]).await.unwrap();
+    assert_eq!(1, len);
+    assert_eq!(vec![Bytes::from("2")], client.smembers("dst").await.unwrap());
+
+    let len = client.sdiffstore("dst", &["a".into(), "b".into()]).await.unwrap();
+    assert_eq!(1, len);
+    assert_eq!(vec![Bytes::from("1")], client.smembers("dst").await.unwrap());
+}
+
+/// 没有交集时，目标键被删除且返回 0
+#[tokio::test]
+async fn sinterstore_empty_result_deletes_destination() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.sadd("a", vec!["1".into()]).await.unwrap();
+    client.sadd("b", vec!["2".into()]).await.unwrap();
+    client.set("dst", "value".into()).await.unwrap();
+
+    let len = client.sinterstore("dst", &["a".into(), "b".into()]).await.unwrap();
+    assert_eq!(0, len);
+
+    assert!(client.get("dst").await.unwrap().is_none());
+}
+
+/// 对集合执行 `GET` 返回 `WRONGTYPE` 错误
+#[tokio::test]
+async fn get_on_set_is_wrongtype() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.sadd("a", vec!["1".into()]).await.unwrap();
+
+    let err = client.get("a").await.unwrap_err();
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+}
+
+/// 按分值范围查找有序集合的成员，区分包含与不包含边界
+#[tokio::test]
+async fn zrangebyscore_inclusive_and_exclusive() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec![
+        (1.0, "a".into()),
+        (2.0, "b".into()),
+        (2.0, "c".into()),
+        (3.0, "d".into()),
+    ];
+    assert_eq!(4, client.zadd("z", members).await.unwrap());
+    // 更新已有成员的分值，不计入新增数量
+    assert_eq!(0, client.zadd("z", vec![(0.5, "d".into())]).await.unwrap());
+
+    let all = client.zrangebyscore("z", Bound::Unbounded, Bound::Unbounded, None).await.unwrap();
+    assert_eq!(vec!["d", "a", "b", "c"], all);
+
+    let inclusive = client.zrangebyscore("z", Bound::Included(1.0), Bound::Included(2.0), None).await.unwrap();
+    assert_eq!(vec!["a", "b", "c"], inclusive);
+
+    let exclusive = client.zrangebyscore("z", Bound::Excluded(1.0), Bound::Excluded(3.0), None).await.unwrap();
+    assert_eq!(vec!["b", "c"], exclusive);
+
+    let empty = client.zrangebyscore("z", Bound::Excluded(2.0), Bound::Excluded(2.0), None).await.unwrap();
+    assert!(empty.is_empty());
+}
+
+/// 使用 `LIMIT` 截取结果，数量为负数时返回偏移量之后的所有成员
+#[tokio::test]
+async fn zrangebyscore_limit() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = (1..=5).map(|i| (i as f64, i.to_string().into())).collect();
+    client.zadd("z", members).await.unwrap();
+
+    let page = client.zrangebyscore("z", Bound::Unbounded, Bound::Unbounded, Some((1, 2))).await.unwrap();
+    assert_eq!(vec!["2", "3"], page);
+
+    let rest = client.zrangebyscore("z", Bound::Included(2.0), Bound::Unbounded, Some((2, -1))).await.unwrap();
+    assert_eq!(vec!["4", "5"], rest);
+
+    let past_end = client.zrangebyscore("z", Bound::Unbounded, Bound::Unbounded, Some((10, 1))).await.unwrap();
+    assert!(past_end.is_empty());
+}
+
+/// `ZINCRBY` 修改分值后，`ZRANK` 的排名随之改变
+#[tokio::test]
+async fn zincrby_updates_zrank() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec![(1.0, "a".into()), (2.0, "b".into()), (3.0, "c".into())];
+    client.zadd("z", members).await.unwrap();
+
+    assert_eq!(Some(0), client.zrank("z", "a".into()).await.unwrap());
+    assert_eq!(None, client.zrank("z", "x".into()).await.unwrap());
+
+    assert_eq!(5.5, client.zincrby("z", 4.5, "a".into()).await.unwrap());
+    assert_eq!(Some(2), client.zrank("z", "a".into()).await.unwrap());
+    assert_eq!(Some(0), client.zrank("z", "b".into()).await.unwrap());
+
+    let all = client.zrangebyscore("z", Bound::Unbounded, Bound::Unbounded, None).await.unwrap();
+    assert_eq!(vec!["b", "c", "a"], all);
+
+    // 不存在的成员视其分值为 0
+    assert_eq!(-1.0, client.zincrby("z", -1.0, "d".into()).await.unwrap());
+    assert_eq!(Some(0), client.zrank("z", "d".into()).await.unwrap());
+}
+
+/// `ZREM` 返回实际删除的数量，删空后键也被删除
+#[tokio::test]
+async fn zrem_removes_members() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.zadd("z", vec![(1.0, "a".into()), (2.0, "b".into())]).await.unwrap();
+
+    assert_eq!(1, client.zrem("z", vec!["a".into(), "x".into()]).await.unwrap());
+    assert_eq!(None, client.zrank("z", "a".into()).await.unwrap());
+    assert_eq!(Some(0), client.zrank("z", "b".into()).await.unwrap());
+
+    assert_eq!(1, client.zrem("z", vec!["b".into()]).await.unwrap());
+
+    // 键已被删除，可以写入其它类型的值
+    client.set("z", "value".into()).await.unwrap();
+    assert_eq!(Some("value".into()), client.get("z").await.unwrap());
+}
+
+/// 快照保存的是绝对的过期时间，重启后键仍在原定的时刻过期
+#[tokio::test]
+async fn save_and_restore_keeps_expiry() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-save-restore.snapshot", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = server::Config {
+        save_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, shutdown, handle) = start_server_with_config(config.clone()).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("forever", "1".into()).await.unwrap();
+    client.set_expires("long", "2".into(), Duration::from_secs(600)).await.unwrap();
+    client.set_expires("short", "3".into(), Duration::from_secs(1)).await.unwrap();
+    let start = time::Instant::now();
+    client.sadd("set", vec!["a".into(), "b".into()]).await.unwrap();
+    client.zadd("zset", vec![(1.5, "a".into()), (-2.0, "b".into())]).await.unwrap();
+    client.save().await.unwrap();
+
+    drop(client);
+    shutdown.send(()).unwrap();
+    handle.await.unwrap();
+
+    // 模拟服务停止了一段时间
+    time::sleep_until(start + Duration::from_millis(500)).await;
+
+    let (addr, shutdown, handle) = start_server_with_config(config).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(Some("1".into()), client.get("forever").await.unwrap());
+    assert_eq!(Some("2".into()), client.get("long").await.unwrap());
+    assert_eq!(Some("3".into()), client.get("short").await.unwrap());
+
+    let mut members = client.smembers("set").await.unwrap();
+    members.sort();
+    assert_eq!(vec!["a", "b"], members);
+    let members = client.zrangebyscore("zset", Bound::Unbounded, Bound::Unbounded, None).await.unwrap();
+    assert_eq!(vec!["b", "a"], members);
+
+    // 若重启时按剩余的 1 秒重新计时，此时 "short" 仍然存在
+    time::sleep_until(start + Duration::from_millis(1300)).await;
+    assert_eq!(None, client.get("short").await.unwrap());
+    assert_eq!(Some("2".into()), client.get("long").await.unwrap());
+
+    drop(client);
+    shutdown.send(()).unwrap();
+    handle.await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// 开启键空间通知后，一次修改的两条通知分别到达各自的订阅者
+#[tokio::test]
+async fn keyspace_notifications() {
+    let config = server::Config {
+        notify_keyspace_events: true,
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _handle) = start_server_with_config(config).await;
+
+    let keyspace = client::connect(addr).await.unwrap();
+    let mut keyspace = keyspace.subscribe(vec!["__keyspace@0__:foo".into()]).await.unwrap();
+
+    let keyevent = client::connect(addr).await.unwrap();
+    let mut keyevent = keyevent.subscribe(vec!["__keyevent@0__:set".into()]).await.unwrap();
+
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+
+    let message = keyspace.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyspace@0__:foo", message.channel);
+    assert_eq!("set", message.content);
+
+    let message = keyevent.next_message().await.unwrap().unwrap();
+    assert_eq!("__keyevent@0__:set", message.channel);
+    assert_eq!("foo", message.content);
+
+    client.del(&["foo".into()]).await.unwrap();
+
+    let message = keyspace.next_message().await.unwrap().unwrap();
+    assert_eq!("del", message.content);
+}
+
+/// 每个修改数据的命令都会发送对应的键事件
+#[tokio::test]
+async fn keyevent_notifications() {
+    let config = server::Config {
+        notify_keyspace_events: true,
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _handle) = start_server_with_config(config).await;
+
+    let events = ["set", "expire", "expired", "del", "incrby", "sadd", "zadd", "zincr", "zrem"];
+    let subscriber = client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber
+        .subscribe(events.iter().map(|event| format!("__keyevent@0__:{}", event)).collect())
+        .await
+        .unwrap();
+
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("a", "1".into()).await.unwrap();
+    client.set_expires("b", "2".into(), Duration::from_millis(50)).await.unwrap();
+    time::sleep(Duration::from_millis(100)).await;
+    client.del(&["a".into(), "missing".into()]).await.unwrap();
+    client.incr("c").await.unwrap();
+    client.sadd("d", vec!["x".into()]).await.unwrap();
+    client.zadd("e", vec![(1.0, "x".into())]).await.unwrap();
+    client.zincrby("e", 1.0, "x".into()).await.unwrap();
+    client.zrem("e", vec!["x".into()]).await.unwrap();
+
+    // 不同频道的消息到达订阅者的顺序不确定，排序后比较
+    let mut expected = [
+        ("set", "a"),
+        ("set", "b"),
+        ("expire", "b"),
+        ("expired", "b"),
+        ("del", "a"),
+        ("incrby", "c"),
+        ("sadd", "d"),
+        ("zadd", "e"),
+        ("zincr", "e"),
+        ("zrem", "e"),
+        ("del", "e"),
+    ];
+
+    expected.sort();
+
+    let mut received = vec![];
+    for _ in 0..expected.len() {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        let event = message.channel.strip_prefix("__keyevent@0__:").unwrap().to_string();
+        received.push((event, String::from_utf8(message.content.to_vec()).unwrap()));
+    }
+    received.sort();
+
+    let expected: Vec<_> = expected.iter().map(|(event, key)| (event.to_string(), key.to_string())).collect();
+    assert_eq!(expected, received);
+}
+
 /// 订阅单个频道并接收消息
 #[tokio::test]
 async fn recieve_message_from_subscribe_channel() {
     let addr = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
 
     // 发送消息
@@ -61,12 +750,81 @@ async fn recieve_message_from_subscribe_channel() {
     assert_eq!(b"world", &message.content[..]);
 }
 
+/// 消费者处理缓慢时，有界消息流缓存的消息不超过上限，且消息不会丢失
+#[tokio::test]
+async fn bounded_stream_slow_consumer() {
+    let addr = start_server().await;
+
+    let client = client::connect(addr).await.unwrap();
+    let subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    let mut stream = subscriber.into_bounded_stream(4);
+
+    let mut publisher = client::connect(addr).await.unwrap();
+    for i in 0..200 {
+        publisher.publish("hello", i.to_string().into()).await.unwrap();
+    }
+
+    // 等待消息到达客户端，期间不消费
+    time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(4, stream.buffered());
+
+    for i in 0..200 {
+        let message = stream.next().await.unwrap().unwrap();
+        assert_eq!(i.to_string(), message.content);
+        assert!(stream.buffered() <= 4);
+    }
+}
+
+/// `publish_wait` 在订阅者读取得慢时等待，而不是丢弃消息
+#[tokio::test]
+async fn publish_wait_backpressure() {
+    let addr = start_server().await;
+
+    // 订阅后暂不读取消息
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let subscribe = Frame::Array(vec![Frame::Bulk("SUBSCRIBE".into()), Frame::Bulk("hello".into())]);
+    subscriber.write_frame(&subscribe).await.unwrap();
+    subscriber.read_frame().await.unwrap().unwrap();
+
+    // 消息总量远超频道缓存与 socket 缓冲区之和
+    const MESSAGES: usize = 4000;
+    let padding = "x".repeat(8 * 1024);
+
+    let mut publisher = client::connect(addr).await.unwrap();
+    let handle = tokio::spawn(async move {
+        for i in 0..MESSAGES {
+            let message = format!("{}:{}", i, padding);
+            assert_eq!(1, publisher.publish_wait("hello", message.into()).await.unwrap());
+        }
+    });
+
+    // 发布者被阻塞，而不是丢弃消息后继续发送
+    time::sleep(Duration::from_millis(500)).await;
+    assert!(!handle.is_finished());
+
+    for i in 0..MESSAGES {
+        match subscriber.read_frame().await.unwrap().unwrap() {
+            Frame::Array(parts) => {
+                let content = match &parts[2] {
+                    Frame::Bulk(content) => content.clone(),
+                    frame => panic!("unexpected frame: {:?}", frame),
+                };
+                let index = std::str::from_utf8(&content).unwrap().split(':').next().unwrap();
+                assert_eq!(i.to_string(), index);
+            },
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+
+    handle.await.unwrap();
+}
+
 /// 订阅多个频道并接收消息
 #[tokio::test]
 async fn recieve_message_from_subscribe_channels() {
     let addr = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client.subscribe(vec!["hello".into(), "foo".into()]).await.unwrap();
 
     // 发送消息至 `hello`、`foo`
@@ -86,16 +844,506 @@ async fn recieve_message_from_subscribe_channels() {
     assert_eq!(b"bar", &message.content[..]);
 }
 
+/// 键与同名的频道互不影响
+#[tokio::test]
+async fn key_and_channel_with_same_name() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    client.set("foo", "value".into()).await.unwrap();
+
+    let subscriber = client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["foo".into()]).await.unwrap();
+
+    let num = client.publish("foo", "message".into()).await.unwrap();
+    assert_eq!(1, num);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("foo", &message.channel);
+    assert_eq!(b"message", &message.content[..]);
+
+    let value = client.get("foo").await.unwrap().unwrap();
+    assert_eq!(b"value", &value[..]);
+}
+
 /// 取消订阅
 #[tokio::test]
 async fn unsubscribe_from_channels() {
     let addr = start_server().await;
 
-    let client = client::connect(addr.clone()).await.unwrap();
+    let client = client::connect(addr).await.unwrap();
     let mut subscriber = client.subscribe(vec!["hello".into(), "foo".into()]).await.unwrap();
 
     subscriber.unsubscribe(&[]).await.unwrap();
     assert_eq!(0, subscriber.get_subscribed().len());
+
+    // 已没有订阅，再次取消全部订阅也能正常收到回复
+    subscriber.unsubscribe(&[]).await.unwrap();
+    assert_eq!(0, subscriber.get_subscribed().len());
+}
+
+/// 取消订阅其中一个频道，其余的订阅保持不变
+#[tokio::test]
+async fn unsubscribe_single_channel() {
+    let addr = start_server().await;
+
+    let client = client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into(), "foo".into()]).await.unwrap();
+
+    subscriber.unsubscribe(&["hello".into()]).await.unwrap();
+    assert_eq!(&["foo".to_string()], subscriber.get_subscribed());
+
+    // 仍然能收到其余频道的消息
+    let mut publisher = client::connect(addr).await.unwrap();
+    assert_eq!(0, publisher.publish("hello", "world".into()).await.unwrap());
+    assert_eq!(1, publisher.publish("foo", "bar".into()).await.unwrap());
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("foo", &message.channel);
+    assert_eq!(b"bar", &message.content[..]);
+}
+
+/// `SET k v NX GET` 返回原先的值，键已存在时不覆盖
+#[tokio::test]
+async fn set_options_nx_get() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("foo", "old".into()).await.unwrap();
+
+    let previous = client.set_options("foo", "new".into(), SetOptions::new().nx().get()).await.unwrap();
+    assert_eq!(Some(Bytes::from("old")), previous);
+    assert_eq!(Some(Bytes::from("old")), client.get("foo").await.unwrap());
+
+    // 键不存在时写入，原先的值为 `None`
+    let previous = client.set_options("bar", "new".into(), SetOptions::new().nx().get()).await.unwrap();
+    assert_eq!(None, previous);
+    assert_eq!(Some(Bytes::from("new")), client.get("bar").await.unwrap());
+
+    // 不指定 `GET` 时，不满足条件返回错误
+    assert!(client.set_options("baz", "new".into(), SetOptions::new().xx()).await.is_err());
+    assert_eq!(None, client.get("baz").await.unwrap());
+
+    // `KEEPTTL` 保留原有的有效期
+    client.set_expires("ttl", "old".into(), Duration::from_millis(100)).await.unwrap();
+    client.set_options("ttl", "new".into(), SetOptions::new().keep_ttl()).await.unwrap();
+    time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(None, client.get("ttl").await.unwrap());
+}
+
+/// 大量带有效期的键被写入及覆盖后，每个键都会按时过期
+/// 若 `expirations` 中的记录发生冲突而被覆盖，对应的键将永远不会过期
+#[tokio::test]
+async fn many_keys_with_ttl_expire() {
+    let addr = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let ttl = Duration::from_secs(1);
+    let mut pipeline = Pipeline::new();
+    for i in 0..1000 {
+        pipeline = pipeline.set_expires(&format!("key:{}", i), "value".into(), ttl);
+    }
+
+    // 覆盖一半的键，旧的过期记录需被替换
+    for i in (0..1000).step_by(2) {
+        pipeline = pipeline.set_expires(&format!("key:{}", i), "new".into(), ttl);
+    }
+    pipeline.execute(&mut client).await.unwrap();
+
+    assert_eq!(1000, client.keys("*").await.unwrap().len());
+
+    time::sleep(ttl * 2).await;
+    assert!(client.keys("*").await.unwrap().is_empty());
+}
+
+/// 服务端重启后，`subscribe_resilient` 的消息流产生重连事件并继续接收消息
+#[tokio::test]
+async fn subscribe_resilient_resumes_after_restart() {
+    let (addr, stop, server) = start_server_with_config(server::Config::default()).await;
+
+    let stream = client::subscribe_resilient(addr, vec!["hello".into()]);
+    tokio::pin!(stream);
+
+    match publish_until_received(addr, &mut stream).await {
+        client::SubscriptionEvent::Message(message) => assert_eq!(b"hi", &message.content[..]),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    // 关闭服务，并在相同的地址上重新启动
+    stop.send(()).unwrap();
+    server.await.unwrap();
+
+    let listener = TcpListener::bind(addr).await.unwrap();
+    let (_stop, rx) = oneshot::channel::<()>();
+    tokio::spawn(async move { server::run(listener, rx).await });
+
+    let event = time::timeout(Duration::from_secs(5), stream.next()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(event, client::SubscriptionEvent::Reconnected));
+
+    match publish_until_received(addr, &mut stream).await {
+        client::SubscriptionEvent::Message(message) => {
+            assert_eq!("hello", &message.channel);
+            assert_eq!(b"hi", &message.content[..]);
+        },
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+/// 添加 10000 个不同的元素后，`PFCOUNT` 估算的基数误差在几个百分点以内
+#[tokio::test]
+async fn pfcount_estimates_distinct_elements() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for chunk in (0..10_000).collect::<Vec<_>>().chunks(1000) {
+        let elements = chunk.iter().map(|i| Bytes::from(format!("element:{}", i))).collect();
+        assert!(client.pfadd("hll", elements).await.unwrap());
+    }
+
+    // 重复添加已有的元素，估算的基数不变
+    assert!(!client.pfadd("hll", vec!["element:0".into()]).await.unwrap());
+
+    let count = client.pfcount(&["hll".into()]).await.unwrap();
+    assert!((9_700..=10_300).contains(&count), "estimated {}", count);
+
+    // 合并两个有一半重叠的 HyperLogLog
+    let elements = (5_000..15_000).map(|i| Bytes::from(format!("element:{}", i))).collect();
+    client.pfadd("other", elements).await.unwrap();
+
+    let count = client.pfcount(&["hll".into(), "other".into(), "missing".into()]).await.unwrap();
+    assert!((14_500..=15_500).contains(&count), "estimated {}", count);
+}
+
+/// `GEODIST` 返回两座城市之间的距离，与实际距离的误差很小
+#[tokio::test]
+async fn geodist_between_cities() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let members = vec![
+        (13.361389, 38.115556, Bytes::from("Palermo")),
+        (15.087269, 37.502669, Bytes::from("Catania")),
+    ];
+    assert_eq!(2, client.geoadd("Sicily", members).await.unwrap());
+
+    // 两地之间的大圆距离约为 166.274 公里
+    let distance = client
+        .geodist("Sicily", "Palermo".into(), "Catania".into(), GeoUnit::Km)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!((distance - 166.274).abs() < 0.1, "distance {}", distance);
+
+    let distance = client
+        .geodist("Sicily", "Palermo".into(), "Catania".into(), GeoUnit::M)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!((distance - 166_274.15).abs() < 100.0, "distance {}", distance);
+
+    // 成员不存在时返回 `None`
+    let distance = client
+        .geodist("Sicily", "Palermo".into(), "Rome".into(), GeoUnit::M)
+        .await
+        .unwrap();
+    assert!(distance.is_none());
+}
+
+/// `SAVE` 成功后 `LASTSAVE` 返回的时间前进
+#[tokio::test]
+async fn lastsave_advances_after_save() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-lastsave.snapshot", std::process::id()));
+
+    let config = server::Config {
+        save_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _shutdown, _handle) = start_server_with_config(config).await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let before = client.lastsave().await.unwrap();
+
+    // `LASTSAVE` 以秒为单位
+    time::sleep(Duration::from_millis(1100)).await;
+    client.save().await.unwrap();
+
+    let after = client.lastsave().await.unwrap();
+    assert!(after > before, "before {}, after {}", before, after);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+/// `COPY` 复制键的值，目标已存在时需指定 `REPLACE`
+/// 只有一个逻辑数据库，`DB 0` 可用，其它编号返回错误
+#[tokio::test]
+async fn copy_within_only_database() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.sadd("src", vec!["a".into(), "b".into()]).await.unwrap();
+    assert!(client.copy("src", "dst", false).await.unwrap());
+
+    let mut members = client.smembers("dst").await.unwrap();
+    members.sort();
+    assert_eq!(vec!["a", "b"], members);
+
+    // 复制出的是独立的值
+    client.sadd("src", vec!["c".into()]).await.unwrap();
+    assert_eq!(2, client.smembers("dst").await.unwrap().len());
+
+    client.set("other", "value".into()).await.unwrap();
+    assert!(!client.copy("other", "dst", false).await.unwrap());
+    assert!(client.copy("other", "dst", true).await.unwrap());
+    assert_eq!(Some("value".into()), client.get("dst").await.unwrap());
+
+    assert!(!client.copy("missing", "dst", true).await.unwrap());
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    for (db, expected) in [
+        ("0", Frame::Integer(1)),
+        ("1", Frame::Error("ERR DB index is out of range".into())),
+    ] {
+        let copy = Frame::Array(
+            ["COPY", "other", "dst", "DB", db, "REPLACE"].iter().map(|arg| Frame::Bulk(Bytes::from(*arg))).collect(),
+        );
+        conn.write_frame(&copy).await.unwrap();
+        assert_eq!(expected, conn.read_frame().await.unwrap().unwrap());
+    }
+}
+
+/// `execute` 原样返回嵌套数组的回复，由辅助函数逐层解码
+#[tokio::test]
+async fn execute_decodes_nested_reply() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let reply = client.execute(&["COMMAND", "DOCS", "getrange"]).await.unwrap();
+
+    let mut docs = client::frame_to_pairs(reply).unwrap();
+    assert_eq!(1, docs.len());
+    let (name, doc) = docs.remove(0);
+    assert_eq!("getrange", name);
+
+    let (field, arguments) = client::frame_to_pairs(doc).unwrap().remove(0);
+    assert_eq!("arguments", field);
+
+    let arguments: Vec<Vec<String>> = match arguments {
+        Frame::Array(arguments) => arguments.into_iter().map(|arg| client::frame_to_string_vec(arg).unwrap()).collect(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+    assert_eq!(
+        vec![
+            vec!["name", "key", "type", "key"],
+            vec!["name", "start", "type", "integer"],
+            vec!["name", "end", "type", "integer"],
+        ],
+        arguments
+    );
+
+    // 错误回复转为 `Err`
+    assert!(client.execute(&["COMMAND", "NOPE"]).await.is_err());
+}
+
+/// 开启消息序号后，同一频道的消息序号逐条递增，没有订阅者时发布的消息同样占用序号
+#[tokio::test]
+async fn message_sequence_increases_per_channel() {
+    let config = server::Config {
+        message_sequence: true,
+        ..server::Config::default()
+    };
+    let (addr, _shutdown, _handle) = start_server_with_config(config).await;
+
+    let mut publisher = client::connect(addr).await.unwrap();
+    assert_eq!(0, publisher.publish("news", "missed".into()).await.unwrap());
+
+    let subscriber = client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["news".into()]).await.unwrap();
+
+    for content in ["a", "b", "c"] {
+        publisher.publish("news", content.into()).await.unwrap();
+        // 其它频道的序号互不影响
+        publisher.publish("other", content.into()).await.unwrap();
+    }
+
+    let mut seqs = vec![];
+    for content in ["a", "b", "c"] {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(content.as_bytes(), &message.content[..]);
+        seqs.push(message.seq.unwrap());
+    }
+    assert_eq!(vec![2, 3, 4], seqs);
+}
+
+/// 并发的 `INCR`/`DECR` 不会相互覆盖，最终的值等于所有增减之和
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_incr_decr() {
+    let addr = start_server().await;
+
+    let tasks: Vec<_> = (0..16)
+        .map(|i| {
+            tokio::spawn(async move {
+                let mut client = client::connect(addr).await.unwrap();
+                for _ in 0..100 {
+                    // 一半的任务加一，另一半的任务先加二再减一
+                    if i % 2 == 0 {
+                        client.incr("total").await.unwrap();
+                    } else {
+                        client.incr("total").await.unwrap();
+                        client.incr("total").await.unwrap();
+                        client.decr("total").await.unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut client = client::connect(addr).await.unwrap();
+    assert_eq!(1599, client.decr("total").await.unwrap());
+
+    // 键不存在时视其值为 0，不是整数的值返回错误
+    assert_eq!(-1, client.decr("missing").await.unwrap());
+    client.set("text", "abc".into()).await.unwrap();
+    let err = client.decr("text").await.unwrap_err();
+    assert_eq!("ERR value is not an integer or out of range", err.to_string());
+}
+
+/// `TTL`/`PTTL` 返回剩余的有效期，键不存在或没有有效期时返回 -2 或 -1
+#[tokio::test]
+async fn ttl_and_pttl() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set_expires("foo", "bar".into(), Duration::from_secs(60)).await.unwrap();
+    client.set("forever", "bar".into()).await.unwrap();
+
+    assert_eq!(60, client.ttl("foo").await.unwrap());
+    let pttl = client.pttl("foo").await.unwrap();
+    assert!((59_000..=60_000).contains(&pttl), "{}", pttl);
+
+    assert_eq!(-1, client.ttl("forever").await.unwrap());
+    assert_eq!(-2, client.pttl("missing").await.unwrap());
+}
+
+/// `mset` 之后所有的键都可以读到，奇数个参数的 `MSET` 返回错误
+#[tokio::test]
+async fn mset_sets_all_keys() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("a", "old".into()).await.unwrap();
+
+    let pairs = vec![("a".to_string(), Bytes::from("1")), ("b".to_string(), Bytes::from("2")), ("c".to_string(), Bytes::from("3"))];
+    client.mset(pairs).await.unwrap();
+
+    assert_eq!(Some(Bytes::from("1")), client.get("a").await.unwrap());
+    assert_eq!(Some(Bytes::from("2")), client.get("b").await.unwrap());
+    assert_eq!(Some(Bytes::from("3")), client.get("c").await.unwrap());
+
+    assert!(client.execute(&["MSET", "a", "1", "b"]).await.is_err());
+    assert_eq!(Some(Bytes::from("1")), client.get("a").await.unwrap());
+}
+
+/// `scan_iter` 自动使用返回的游标继续遍历，100 个键每个恰好产生一次
+#[tokio::test]
+async fn scan_iter_yields_every_key_once() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    for i in 0..100 {
+        client.set(&format!("key:{}", i), "v".into()).await.unwrap();
+    }
+
+    let mut keys: Vec<String> = client.scan_iter(None).map(Result::unwrap).collect().await;
+    keys.sort();
+
+    let mut expected: Vec<String> = (0..100).map(|i| format!("key:{}", i)).collect();
+    expected.sort();
+    assert_eq!(expected, keys);
+
+    // 一次 `SCAN` 只返回一部分键，游标不为 0
+    let (cursor, keys) = client.scan(0, None, Some(5)).await.unwrap();
+    assert_ne!(0, cursor);
+    assert!(keys.len() < 100, "{}", keys.len());
+}
+
+/// 第二次 `SETNX` 返回 0，原有的值不变
+#[tokio::test]
+async fn set_nx_only_when_absent() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert!(client.set_nx("foo", "first".into()).await.unwrap());
+    assert!(!client.set_nx("foo", "second".into()).await.unwrap());
+
+    assert_eq!(Some(Bytes::from("first")), client.get("foo").await.unwrap());
+}
+
+/// `getset` 返回原先的值，新值被保存，原有的有效期被清除
+#[tokio::test]
+async fn getset_returns_previous_value() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.getset("foo", "first".into()).await.unwrap());
+    assert_eq!(Some(Bytes::from("first")), client.get("foo").await.unwrap());
+
+    client.set_expires("foo", "second".into(), Duration::from_secs(60)).await.unwrap();
+    assert_eq!(Some(Bytes::from("second")), client.getset("foo", "third".into()).await.unwrap());
+    assert_eq!(Some(Bytes::from("third")), client.get("foo").await.unwrap());
+    assert_eq!(-1, client.ttl("foo").await.unwrap());
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+}
+
+/// `set_json` 保存的值可以由 `get_json` 原样读回，键不存在时返回 `None`
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn json_round_trip() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    let user = User { name: "张三".into(), age: 18 };
+    client.set_json("user", &user).await.unwrap();
+
+    assert_eq!(Some(user), client.get_json::<User>("user").await.unwrap());
+    assert_eq!(None, client.get_json::<User>("nobody").await.unwrap());
+}
+
+/// 保存的值不是合法的 JSON 时，`get_json` 返回解析错误，连接仍可继续使用
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn get_json_malformed_value() {
+    let addr = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("user", "not json".into()).await.unwrap();
+
+    let err = client.get_json::<User>("user").await.unwrap_err();
+    assert!(err.downcast_ref::<serde_json::Error>().is_some(), "{err}");
+
+    assert_eq!(Some(Bytes::from("not json")), client.get("user").await.unwrap());
 }
 
 /// 启动服务
@@ -107,3 +1355,30 @@ async fn start_server() -> SocketAddr {
 
     addr
 }
+
+/// 使用指定的配置启动服务，返回地址、关闭服务的发送端及服务的任务
+async fn start_server_with_config(config: server::Config) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let handle = tokio::spawn(async move { server::run_with_config(listener, rx, config).await });
+
+    (addr, tx, handle)
+}
+
+/// 持续发布消息直到订阅端的消息流产生事件，避免订阅尚未完成时消息丢失
+async fn publish_until_received(
+    addr: SocketAddr,
+    stream: &mut (impl tokio_stream::Stream<Item = mini_redis::Result<client::SubscriptionEvent>> + Unpin),
+) -> client::SubscriptionEvent {
+    let mut publisher = client::connect(addr).await.unwrap();
+
+    loop {
+        publisher.publish("hello", "hi".into()).await.unwrap();
+
+        if let Ok(event) = time::timeout(Duration::from_millis(50), stream.next()).await {
+            return event.unwrap().unwrap()
+        }
+    }
+}
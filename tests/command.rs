@@ -0,0 +1,63 @@
+use mini_redis::{cmd, Command, Frame};
+
+/// 由命令名及参数构建客户端发送的 frame 并解析出命令
+fn parse(args: &[&str]) -> Command {
+    let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(arg.to_string().into())).collect());
+
+    Command::from_frame(frame).unwrap()
+}
+
+/// `SET`/`DEL` 会修改数据，`GET` 不会
+#[test]
+fn write_commands() {
+    assert!(parse(&["SET", "foo", "bar"]).is_write());
+    assert!(parse(&["DEL", "foo"]).is_write());
+    assert!(!parse(&["GET", "foo"]).is_write());
+    assert!(!parse(&["FOO"]).is_write());
+}
+
+/// `PING`/`AUTH` 无需认证，其它命令及未知命令都需要
+#[test]
+fn auth_commands() {
+    assert!(!parse(&["PING"]).requires_auth());
+    assert!(!parse(&["AUTH", "secret"]).requires_auth());
+    assert!(parse(&["GET", "foo"]).requires_auth());
+    assert!(parse(&["FOO"]).requires_auth());
+}
+
+/// 按命令名查找元数据不区分大小写
+#[test]
+fn lookup_command_info() {
+    let info = cmd::lookup("GETRANGE").unwrap();
+    assert_eq!("getrange", info.name);
+    assert_eq!(4, info.arity);
+
+    assert_eq!(Some(info), parse(&["getrange", "foo", "0", "1"]).info());
+    assert!(cmd::lookup("foo").is_none());
+}
+
+/// 解析时命令名不区分大小写，未知命令也能解析
+#[test]
+fn mixed_case_command_name() {
+    assert_eq!("set", parse(&["SeT", "foo", "bar"]).info().unwrap().name);
+    assert_eq!("getrange", parse(&["GETRANGE", "foo", "0", "1"]).info().unwrap().name);
+    assert!(parse(&["FoO"]).info().is_none());
+}
+
+/// 元数据表中记录了命令的参数描述
+#[test]
+fn command_args() {
+    let args = cmd::lookup("set").unwrap().args;
+    assert_eq!("key", args[0].name);
+    assert_eq!(cmd::ArgType::Key, args[0].kind);
+    let expiration = args.iter().find(|arg| arg.name == "expiration").unwrap();
+    assert!(expiration.optional);
+
+    match expiration.kind {
+        cmd::ArgType::OneOf(options) => {
+            let tokens: Vec<_> = options.iter().map(|option| option.token.unwrap()).collect();
+            assert_eq!(vec!["EX", "PX", "EXAT", "PXAT", "KEEPTTL"], tokens);
+        },
+        kind => panic!("unexpected kind: {:?}", kind),
+    }
+}
@@ -0,0 +1,278 @@
+use std::{io, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use mini_redis::{Connection, Frame};
+
+/// 读取超时不会丢弃已收到的部分数据
+#[tokio::test]
+async fn read_frame_timeout_keeps_partial_data() {
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::new(server);
+
+    // 没有任何数据，超时返回 `TimedOut`
+    let err = connection
+        .read_frame_timeout(Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    let err = err.downcast::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+
+    // 只发送半条 frame，仍然超时
+    client.write_all(b"*2\r\n$3\r\nGET\r\n").await.unwrap();
+    connection
+        .read_frame_timeout(Duration::from_millis(50))
+        .await
+        .unwrap_err();
+
+    // 发送剩余部分后，可以读到完整的 frame
+    client.write_all(b"$5\r\nhello\r\n").await.unwrap();
+    let frame = connection
+        .read_frame_timeout(Duration::from_secs(1))
+        .await
+        .unwrap()
+        .unwrap();
+
+    match frame {
+        Frame::Array(parts) => {
+            assert_eq!(2, parts.len());
+            assert!(parts[0] == "GET");
+            assert!(parts[1] == "hello");
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 有符号整数的边界值写入后可以原样读回
+#[tokio::test]
+async fn write_signed_integers() {
+    let (client, server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    let mut reader = Connection::new(server);
+
+    for value in [i64::MIN, -1, 0, i64::MAX] {
+        writer.write_frame(&Frame::Integer(value)).await.unwrap();
+        assert_eq!(Frame::Integer(value), reader.read_frame().await.unwrap().unwrap());
+    }
+}
+
+/// 空的 `Bulk` 编码为 `$0\r\n\r\n`，并可以读回
+#[tokio::test]
+async fn write_empty_bulk() {
+    let (client, mut server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+
+    writer.write_frame(&Frame::Bulk(Bytes::new())).await.unwrap();
+    writer.write_frame(&Frame::Null).await.unwrap();
+
+    let mut response = [0; 11];
+    server.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$0\r\n\r\n$-1\r\n", &response);
+
+    let (client, server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    let mut reader = Connection::new(server);
+
+    writer.write_frame(&Frame::Bulk(Bytes::new())).await.unwrap();
+    writer.write_frame(&Frame::Null).await.unwrap();
+    assert_eq!(Frame::Bulk(Bytes::new()), reader.read_frame().await.unwrap().unwrap());
+    assert_eq!(Frame::Null, reader.read_frame().await.unwrap().unwrap());
+}
+
+/// 嵌套的数组递归编码，写入后可以原样读回
+#[tokio::test]
+async fn write_nested_array() {
+    let (client, server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    let mut reader = Connection::new(server);
+
+    let frame = Frame::Array(vec![
+        Frame::Array(vec![Frame::Bulk("get".into()), Frame::Integer(2)]),
+        Frame::Bulk("tail".into()),
+    ]);
+
+    writer.write_frame(&frame).await.unwrap();
+    assert_eq!(frame, reader.read_frame().await.unwrap().unwrap());
+
+    writer.write_value(&frame).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(frame, reader.read_frame().await.unwrap().unwrap());
+}
+
+/// 只包含数组的数组，多层嵌套同样可以原样读回
+#[tokio::test]
+async fn write_deeply_nested_array() {
+    let (client, server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    let mut reader = Connection::new(server);
+
+    let frame = Frame::Array(vec![Frame::Array(vec![
+        Frame::Array(vec![Frame::Bulk("channel".into()), Frame::Integer(1)]),
+        Frame::Array(vec![]),
+    ])]);
+
+    writer.write_value(&frame).await.unwrap();
+    writer.flush().await.unwrap();
+    assert_eq!(frame, reader.read_frame().await.unwrap().unwrap());
+}
+
+/// `Frame::encode` 与 `write_value` 写出的字节完全相同
+#[tokio::test]
+async fn encode_matches_write_value() {
+    let (client, mut server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+
+    let frames = vec![
+        Frame::Simple("OK".into()),
+        Frame::Error("ERR oops".into()),
+        Frame::Integer(i64::MIN),
+        Frame::Integer(42),
+        Frame::Null,
+        Frame::Bulk(Bytes::new()),
+        Frame::Bulk("hello".into()),
+        Frame::Array(vec![]),
+        Frame::Array(vec![
+            Frame::Bulk("a".into()),
+            Frame::Array(vec![Frame::Integer(1), Frame::Null]),
+        ]),
+        Frame::Push(vec![Frame::Bulk("message".into()), Frame::Simple("b".into())]),
+    ];
+
+    for frame in frames {
+        let mut encoded = BytesMut::new();
+        frame.encode(&mut encoded);
+
+        writer.write_value(&frame).await.unwrap();
+        writer.flush().await.unwrap();
+
+        let mut written = vec![0; encoded.len()];
+        server.read_exact(&mut written).await.unwrap();
+        assert_eq!(&encoded[..], &written[..], "{:?}", frame);
+
+        // `write_frame` 同样使用 `encode` 的结果
+        writer.write_frame(&frame).await.unwrap();
+        server.read_exact(&mut written).await.unwrap();
+        assert_eq!(&encoded[..], &written[..], "{:?}", frame);
+    }
+}
+
+/// 超过 `max_bulk_len` 的 bulk 写入失败，且不会写入任何数据
+#[tokio::test]
+async fn write_over_limit_bulk() {
+    let (client, server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    let mut reader = Connection::new(server);
+
+    writer.set_max_bulk_len(5);
+
+    let err = writer.write_frame(&Frame::Bulk("too long".into())).await.unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+    // 数组中的 bulk 同样受限制
+    let array = Frame::Array(vec![Frame::Bulk("ok".into()), Frame::Bulk("too long".into())]);
+    writer.write_frame(&array).await.unwrap_err();
+    writer.write_value(&array).await.unwrap_err();
+
+    // 之后的写入不受影响，对端只收到这一条
+    writer.write_frame(&Frame::Bulk("hello".into())).await.unwrap();
+    assert_eq!(Frame::Bulk("hello".into()), reader.read_frame().await.unwrap().unwrap());
+
+    drop(writer);
+    assert!(reader.read_frame().await.unwrap().is_none());
+}
+
+/// 协商 RESP3 后，`write_value` 与 `write_frame` 都将 `Null` 写为 `_\r\n`
+#[tokio::test]
+async fn write_resp3_null() {
+    let (client, mut server) = socket_pair().await;
+    let mut writer = Connection::new(client);
+    writer.set_protocol(3);
+
+    let frame = Frame::Array(vec![Frame::Null, Frame::Integer(1)]);
+    let expected = b"*2\r\n_\r\n:1\r\n";
+
+    writer.write_value(&frame).await.unwrap();
+    writer.flush().await.unwrap();
+
+    let mut written = [0; 11];
+    server.read_exact(&mut written).await.unwrap();
+    assert_eq!(expected, &written);
+
+    writer.write_frame(&frame).await.unwrap();
+    server.read_exact(&mut written).await.unwrap();
+    assert_eq!(expected, &written);
+}
+
+/// 服务端的连接返回客户端的地址
+#[tokio::test]
+async fn peer_addr() {
+    let (client, server) = socket_pair().await;
+    let client_addr = client.local_addr().unwrap();
+
+    let connection = Connection::new(server);
+    assert_eq!(client_addr, connection.peer_addr().unwrap());
+}
+
+/// 建立一对互相连接的 `TcpStream`，返回 (客户端, 服务端)
+async fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = TcpStream::connect(addr).await.unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+
+    (client, server)
+}
+
+/// 声明的长度超过上限时立即返回错误，不会等待并缓存数据
+#[tokio::test]
+async fn oversized_length_rejected() {
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::with_limits(server, 1024);
+
+    // 只发送长度，若等待数据则会超时
+    client.write_all(b"*1\r\n$1000000000000\r\n").await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(5), connection.read_frame())
+        .await
+        .expect("waited for the oversized bulk")
+        .unwrap_err();
+    assert!(err.to_string().contains("exceeds the limit"), "{}", err);
+
+    // `new` 默认同样有上限
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::new(server);
+
+    client.write_all(b"$1000000000000\r\n").await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(5), connection.read_frame())
+        .await
+        .expect("waited for the oversized bulk")
+        .unwrap_err();
+    assert!(err.to_string().contains("exceeds the limit"), "{}", err);
+
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::with_limits(server, 1024);
+
+    client.write_all(b"*100000000\r\n").await.unwrap();
+    connection.read_frame().await.unwrap_err();
+
+    // 无法解析为整数的长度同样返回错误
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::with_limits(server, 1024);
+
+    client.write_all(b"$99999999999999999999999\r\n").await.unwrap();
+    connection.read_frame().await.unwrap_err();
+
+    // 上限以内的数据正常读取
+    let (mut client, server) = socket_pair().await;
+    let mut connection = Connection::with_limits(server, 3);
+
+    client.write_all(b"*1\r\n$3\r\nGET\r\n").await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![Frame::Bulk("GET".into())]),
+        connection.read_frame().await.unwrap().unwrap()
+    );
+}
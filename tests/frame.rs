@@ -0,0 +1,137 @@
+use std::io::Cursor;
+
+use bytes::{Bytes, BytesMut};
+use mini_redis::Frame;
+
+/// `Frame::ok()` 与手动构建的 `+OK` 相同
+#[test]
+fn ok_frame() {
+    assert_eq!(Frame::Simple("OK".into()), Frame::ok());
+    assert_eq!(Frame::Simple("PONG".into()), Frame::from_static_simple("PONG"));
+    assert!(Frame::ok() == "OK");
+}
+
+/// 空的 `Bulk` 只消耗 `$0\r\n\r\n`，且与 `Null` 不同
+#[test]
+fn parse_empty_bulk() {
+    let src = b"$0\r\n\r\n+OK\r\n";
+    let mut cursor = Cursor::new(&src[..]);
+
+    let frame = Frame::parse(&mut cursor).unwrap();
+    assert_eq!(Frame::Bulk(Bytes::new()), frame);
+    assert_ne!(Frame::Null, frame);
+    assert_eq!(6, cursor.position());
+
+    assert_eq!(Frame::ok(), Frame::parse(&mut cursor).unwrap());
+}
+
+/// `encoded_len` 与 `encode` 实际写入的字节数相同，RESP3 下 `Null` 为 `_\r\n`
+#[test]
+fn encoded_len_matches_encode() {
+    let frames = vec![
+        Frame::Simple("OK".into()),
+        Frame::Simple(String::new()),
+        Frame::Error("ERR oops".into()),
+        Frame::Integer(0),
+        Frame::Integer(9),
+        Frame::Integer(10),
+        Frame::Integer(-1),
+        Frame::Integer(i64::MIN),
+        Frame::Integer(i64::MAX),
+        Frame::Null,
+        Frame::Bulk(Bytes::new()),
+        Frame::Bulk(Bytes::from(vec![b'x'; 1000])),
+        Frame::Array(vec![]),
+        Frame::Array(vec![
+            Frame::Bulk("a".into()),
+            Frame::Null,
+            Frame::Array(vec![Frame::Integer(-42), Frame::Array(vec![Frame::Null])]),
+        ]),
+        Frame::Push(vec![Frame::Bulk("message".into()), Frame::Simple("b".into())]),
+    ];
+
+    for frame in frames {
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf);
+        assert_eq!(buf.len(), frame.encoded_len(), "{:?}", frame);
+
+        for resp3 in [false, true] {
+            let mut buf = BytesMut::new();
+            frame.encode_with(&mut buf, resp3);
+            assert_eq!(buf.len(), frame.encoded_len_with(resp3), "{:?} resp3={}", frame, resp3);
+        }
+    }
+
+    assert_eq!(3, Frame::Null.encoded_len_with(true));
+}
+
+/// RESP3 的空值 `_\r\n` 解析为 `Null`
+#[test]
+fn parse_resp3_null() {
+    let src = b"_\r\n*2\r\n_\r\n:1\r\n";
+    let mut cursor = Cursor::new(&src[..]);
+
+    assert_eq!(Frame::Null, Frame::parse(&mut cursor).unwrap());
+    assert_eq!(
+        Frame::Array(vec![Frame::Null, Frame::Integer(1)]),
+        Frame::parse(&mut cursor).unwrap()
+    );
+}
+
+/// 数组的每个元素都被显示，以一个空格分隔
+#[test]
+fn display_array() {
+    let frame = Frame::Array(vec![Frame::Bulk("a".into()), Frame::Bulk("b".into()), Frame::Bulk("c".into())]);
+    assert_eq!("a b c", frame.to_string());
+
+    assert_eq!("", Frame::Array(vec![]).to_string());
+}
+
+/// 行内单独的 `\r` 不是行尾，以 `\r` 结尾的数据需要等待更多数据
+#[test]
+fn parse_line_with_bare_cr() {
+    let src = b"+a\rb\r\n:1\r\n";
+    let mut cursor = Cursor::new(&src[..]);
+
+    assert_eq!(Frame::Simple("a\rb".into()), Frame::parse(&mut cursor).unwrap());
+    assert_eq!(6, cursor.position());
+    assert_eq!(Frame::Integer(1), Frame::parse(&mut cursor).unwrap());
+
+    for src in [&b"+abc"[..], b"+abc\r", b"+a\rb\r"] {
+        let mut cursor = Cursor::new(src);
+        assert!(matches!(Frame::parse(&mut cursor), Err(mini_redis::frame::Error::Incomplete)), "{:?}", src);
+    }
+}
+
+/// 很长的一行也能完整解析，不会被其中的 `\r` 截断
+#[test]
+fn parse_long_line() {
+    let line: String = (0..1024 * 1024).map(|i| if i % 1000 == 999 { '\r' } else { 'x' }).collect();
+    let src = format!("+{}\r\n", line);
+    let mut cursor = Cursor::new(src.as_bytes());
+
+    assert_eq!(Frame::Simple(line), Frame::parse(&mut cursor).unwrap());
+    assert_eq!(src.len() as u64, cursor.position());
+}
+
+/// 溢出与格式错误的整数返回不同的错误，负数只能出现在 `Integer` 中
+#[test]
+fn parse_decimal_errors() {
+    let error = |src: &[u8]| Frame::parse(&mut Cursor::new(src)).unwrap_err().to_string();
+
+    assert_eq!(
+        "protocol error: integer '99999999999999999999999' out of range",
+        error(b"$99999999999999999999999\r\n")
+    );
+    assert_eq!(
+        "protocol error: integer '-99999999999999999999999' out of range",
+        error(b":-99999999999999999999999\r\n")
+    );
+    assert_eq!("protocol error: unexpected negative integer '-3'", error(b"*-3\r\n"));
+    assert_eq!("protocol error: invalid integer '12abc'", error(b"*12abc\r\n"));
+    assert_eq!("protocol error: invalid integer ''", error(b":\r\n"));
+    assert_eq!("protocol error: invalid integer '-'", error(b":-\r\n"));
+
+    assert_eq!(Frame::Integer(-42), Frame::parse(&mut Cursor::new(&b":-42\r\n"[..])).unwrap());
+    assert_eq!(Frame::Integer(i64::MIN), Frame::parse(&mut Cursor::new(format!(":{}\r\n", i64::MIN).as_bytes())).unwrap());
+}
@@ -1,10 +1,17 @@
-use std::net::SocketAddr;
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
 
-use mini_redis::server;
+use mini_redis::{cmd, server, Connection, Frame};
 
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
+    sync::oneshot,
     time::{self, Duration},
 };
 
@@ -323,6 +330,165 @@ async fn manage_subscription() {
                &response);
 }
 
+/// 同一条 `SUBSCRIBE` 中重复的频道只订阅一次
+#[tokio::test]
+async fn subscribe_duplicate_channels() {
+    let addr = start_server().await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*3\r\n\
+                     $9\r\nSUBSCRIBE\r\n\
+                     $3\r\nfoo\r\n\
+                     $3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 32];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n\
+               $9\r\nsubscribe\r\n\
+               $3\r\nfoo\r\n\
+               :1\r\n",
+               &response);
+
+    publisher.write_all(b"*3\r\n\
+                        $7\r\nPUBLISH\r\n\
+                        $3\r\nfoo\r\n\
+                        $3\r\nbar\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    let mut response = [0; 35];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n\
+               $7\r\nmessage\r\n\
+               $3\r\nfoo\r\n\
+               $3\r\nbar\r\n",
+               &response);
+
+    // 没有第二条确认或消息
+    time::timeout(Duration::from_millis(100), sub.read(&mut response))
+        .await
+        .unwrap_err();
+}
+
+/// 没有任何订阅时取消全部订阅，回复频道名为 `nil` 的消息
+#[tokio::test]
+async fn unsubscribe_without_subscriptions() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$11\r\nUNSUBSCRIBE\r\n").await.unwrap();
+
+    let mut response = [0; 31];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n\
+               $11\r\nunsubscribe\r\n\
+               $-1\r\n\
+               :0\r\n",
+               &response);
+
+    // 连接仍可继续使用
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+}
+
+/// 协商 RESP3 后，`GET` 不存在的键回复 RESP3 的空值 `_\r\n`
+#[tokio::test]
+async fn resp3_get_missing_null() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // `HELLO` 的回复长度不固定，以 `PING` 的回复作为结束标志
+    stream.write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n\
+                     *2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n\
+                     *1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+
+    let mut response = vec![];
+    while !response.ends_with(b"+PONG\r\n") {
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(n > 0, "{:?}", String::from_utf8_lossy(&response));
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    assert!(response.ends_with(b"_\r\n+PONG\r\n"), "{:?}", String::from_utf8_lossy(&response));
+}
+
+/// 协商 RESP3 后，订阅消息以 push 类型 `>` 发送
+#[tokio::test]
+async fn pub_sub_resp3_push() {
+    let addr = start_server().await;
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+
+    // 切换至 RESP3
+    sub.write_all(b"*2\r\n\
+                  $5\r\nHELLO\r\n\
+                  $1\r\n3\r\n")
+        .await
+        .unwrap();
+
+    let mut sub = Connection::new(sub);
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Array(info) => {
+            let proto = info.chunks(2).find(|pair| pair[0] == "proto").unwrap();
+            assert!(matches!(proto[1], Frame::Integer(3)));
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    let subscribe = Frame::Array(vec![
+        Frame::Bulk("SUBSCRIBE".into()),
+        Frame::Bulk("hello".into()),
+    ]);
+    sub.write_frame(&subscribe).await.unwrap();
+
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Push(parts) => {
+            assert!(parts[0] == "subscribe");
+            assert!(parts[1] == "hello");
+            assert!(matches!(parts[2], Frame::Integer(1)));
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    // RESP2 的发布者仍然收到普通的整数回复
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    publisher.write_all(b"*3\r\n\
+                        $7\r\nPUBLISH\r\n\
+                        $5\r\nhello\r\n\
+                        $5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Push(parts) => {
+            assert!(parts[0] == "message");
+            assert!(parts[1] == "hello");
+            assert!(parts[2] == "world");
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
 /// 错误命令格式测试
 #[tokio::test]
 async fn send_error_unknown_command() {
@@ -371,9 +537,10 @@ async fn send_error_get_set_after_subscribe() {
         .await
         .unwrap();
 
-    let mut response = [0; 29];
+    let expected = subscribe_context_error("set");
+    let mut response = vec![0; expected.len()];
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-Err: unknown command \'set\'\r\n", &response);
+    assert_eq!(expected, response);
 
     stream.write_all(b"*2\r\n\
                      $3\r\nGET\r\n\
@@ -381,36 +548,1801 @@ async fn send_error_get_set_after_subscribe() {
         .await
         .unwrap();
 
-    let mut response = [0; 29];
+    let expected = subscribe_context_error("get");
+    let mut response = vec![0; expected.len()];
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-Err: unknown command \'get\'\r\n", &response);
+    assert_eq!(expected, response);
 }
 
-async fn get_ok(stream: &mut TcpStream) {
-    let mut response = [0; 5];
-    stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"+OK\r\n", &response);
+/// 订阅模式下 `RESET` 退出订阅模式，之后可以执行普通命令
+#[tokio::test]
+async fn reset_in_subscribe_mode() {
+    let addr = start_server().await;
+
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SUBSCRIBE", "hello"])).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap();
+
+    conn.write_frame(&command(&["RESET"])).await.unwrap();
+    assert_eq!(Frame::from_static_simple("RESET"), conn.read_frame().await.unwrap().unwrap());
+
+    // 已不再订阅任何频道
+    publisher.write_frame(&command(&["PUBLISH", "hello", "world"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), publisher.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "hello"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
 }
 
-async fn get_null(stream: &mut TcpStream) {
-    let mut response = [0; 5];
-    stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"$-1\r\n", &response);
+/// 订阅模式下的 `PING` 以数组形式回复 `["pong", message]`，没有 message 时为空字符串
+#[tokio::test]
+async fn ping_in_subscribe_mode() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SUBSCRIBE", "hello"])).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap();
+
+    conn.write_frame(&command(&["PING", "hello"])).await.unwrap();
+    assert_eq!(command(&["pong", "hello"]), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["PING"])).await.unwrap();
+    assert_eq!(command(&["pong", ""]), conn.read_frame().await.unwrap().unwrap());
+
+    // 退出订阅模式后恢复普通的回复
+    conn.write_frame(&command(&["RESET"])).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap();
+
+    conn.write_frame(&command(&["PING", "hello"])).await.unwrap();
+    assert_eq!(Frame::Bulk("hello".into()), conn.read_frame().await.unwrap().unwrap());
 }
 
-async fn get_world(stream: &mut TcpStream) {
-    let mut response = [0; 11];
+/// `MSET` 一次写入大量键值，之后都能读到
+#[tokio::test]
+async fn mset_many_pairs() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    const PAIRS: usize = 10_000;
+
+    let mut mset = vec![Frame::Bulk("MSET".into())];
+    for i in 0..PAIRS {
+        mset.push(Frame::Bulk(format!("key:{}", i).into()));
+        mset.push(Frame::Bulk(format!("value:{}", i).into()));
+    }
+    conn.write_frame(&Frame::Array(mset)).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    // 每批先发送若干 `GET`，再依次读取回复，未读取的回复不会多到填满 socket 的缓冲区
+    const BATCH: usize = 100;
+    for batch in (0..PAIRS).step_by(BATCH) {
+        for i in batch..batch + BATCH {
+            let get = Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk(format!("key:{}", i).into())]);
+            conn.write_frame(&get).await.unwrap();
+        }
+        for i in batch..batch + BATCH {
+            assert_eq!(Frame::Bulk(format!("value:{}", i).into()), conn.read_frame().await.unwrap().unwrap());
+        }
+    }
+}
+
+/// `QUIT` 回复 `OK` 后服务端关闭连接，订阅模式下同样如此
+#[tokio::test]
+async fn quit_closes_connection() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.write_frame(&command(&["QUIT"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+    assert!(conn.read_frame().await.unwrap().is_none());
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.write_frame(&command(&["SUBSCRIBE", "hello"])).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap();
+    conn.write_frame(&command(&["QUIT"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+    assert!(conn.read_frame().await.unwrap().is_none());
+}
+
+/// MEMORY STATS 统计的字节数不少于所有值的长度之和
+#[tokio::test]
+async fn memory_stats_reports_dataset_bytes() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // 设置三个键值，值的总长度为 5 + 3 + 10
+    stream.write_all(b"*3\r\n\
+                     $3\r\nSET\r\n\
+                     $5\r\nhello\r\n\
+                     $5\r\nworld\r\n")
+        .await
+        .unwrap();
+    get_ok(&mut stream).await;
+
+    stream.write_all(b"*3\r\n\
+                     $3\r\nSET\r\n\
+                     $3\r\nfoo\r\n\
+                     $3\r\nbar\r\n")
+        .await
+        .unwrap();
+    get_ok(&mut stream).await;
+
+    stream.write_all(b"*3\r\n\
+                     $3\r\nSET\r\n\
+                     $3\r\nnum\r\n\
+                     $10\r\n0123456789\r\n")
+        .await
+        .unwrap();
+    get_ok(&mut stream).await;
+
+    stream.write_all(b"*2\r\n\
+                     $6\r\nMEMORY\r\n\
+                     $5\r\nSTATS\r\n")
+        .await
+        .unwrap();
+
+    let mut connection = Connection::new(stream);
+    let stats = match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Array(stats) => stats,
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    // 格式为 [名称, 数值, 名称, 数值, ..]
+    let field = |name: &str| {
+        stats
+            .chunks(2)
+            .find(|pair| pair[0] == name)
+            .map(|pair| pair[1].clone())
+    };
+
+    assert!(matches!(field("keys.count"), Some(Frame::Integer(3))));
+    match field("dataset.bytes") {
+        Some(Frame::Integer(bytes)) => assert!(bytes >= 5 + 3 + 10),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 默认配置下不允许执行 `DEBUG`
+#[tokio::test]
+async fn debug_command_disabled_by_default() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let debug = Frame::Array(vec![
+        Frame::Bulk("DEBUG".into()),
+        Frame::Bulk("SLEEP".into()),
+        Frame::Bulk("0".into()),
+    ]);
+    conn.write_frame(&debug).await.unwrap();
+
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Error(msg) => assert!(msg.starts_with("ERR DEBUG command not allowed")),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 多线程运行时上，`DEBUG SLEEP-BLOCKING` 只阻塞一个工作线程，其它连接仍可响应
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn debug_sleep_blocking_multi_thread() {
+    let addr = start_debug_server().await;
+
+    let mut blocked = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let ping = Frame::Array(vec![Frame::Bulk("PING".into())]);
+
+    // 确保两个连接都已被服务端接受
+    for conn in [&mut blocked, &mut other] {
+        conn.write_frame(&ping).await.unwrap();
+        assert_eq!(Frame::from_static_simple("PONG"), conn.read_frame().await.unwrap().unwrap());
+    }
+
+    let sleep = Frame::Array(vec![
+        Frame::Bulk("DEBUG".into()),
+        Frame::Bulk("SLEEP-BLOCKING".into()),
+        Frame::Bulk("1".into()),
+    ]);
+    blocked.write_frame(&sleep).await.unwrap();
+
+    // 等待服务端开始执行阻塞的 sleep
+    time::sleep(Duration::from_millis(100)).await;
+
+    other.write_frame(&ping).await.unwrap();
+    let pong = time::timeout(Duration::from_millis(500), other.read_frame())
+        .await
+        .expect("second connection stalled")
+        .unwrap()
+        .unwrap();
+    assert_eq!(Frame::from_static_simple("PONG"), pong);
+
+    assert_eq!(Frame::ok(), blocked.read_frame().await.unwrap().unwrap());
+}
+
+/// `DEBUG OBJECT-STATS` 按内部编码统计键的数量
+#[tokio::test]
+async fn debug_object_stats_counts_encodings() {
+    let addr = start_debug_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SET", "number", "12345"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SET", "text", "a string that is too long to be stored as an integer"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["DEBUG", "OBJECT-STATS"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![
+            Frame::Bulk("int".into()),
+            Frame::Integer(1),
+            Frame::Bulk("raw".into()),
+            Frame::Integer(1),
+        ]),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+}
+
+/// 订阅者的连接不再从频道取消息时，`DEBUG CHANNELS` 报告频道缓存中积压的消息
+#[tokio::test]
+async fn debug_channels_reports_buffered_messages() {
+    let config = server::Config {
+        enable_debug_command: true,
+        pubsub_output_buffer_limit: server::OutputBufferLimit {
+            hard: 0,
+            soft: 0,
+            soft_duration: Duration::from_secs(3600),
+        },
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut sub = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    sub.write_frame(&command(&["SUBSCRIBE", "hello"])).await.unwrap();
+    sub.read_frame().await.unwrap().unwrap();
+
+    conn.write_frame(&command(&["DEBUG", "CHANNELS"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![Frame::Array(vec![Frame::Bulk("hello".into()), Frame::Integer(1), Frame::Integer(0)])]),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+
+    // 订阅者不读取数据，但每次发布后发送 `PING`
+    // socket 的缓冲区满后，回复 `PING` 时连接被阻塞，不再从频道取消息，消息开始在频道缓存中积压
+    let message = Bytes::from(vec![b'x'; 64 * 1024]);
+    let mut buffered = 0;
+    for _ in 0..512 {
+        sub.write_frame(&command(&["PING"])).await.unwrap();
+
+        let publish = Frame::Array(vec![
+            Frame::Bulk("PUBLISH".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Bulk(message.clone()),
+        ]);
+        conn.write_frame(&publish).await.unwrap();
+        assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+        conn.write_frame(&command(&["DEBUG", "CHANNELS"])).await.unwrap();
+        buffered = match conn.read_frame().await.unwrap().unwrap() {
+            Frame::Array(mut channels) => match channels.pop() {
+                Some(Frame::Array(stats)) => match stats[..] {
+                    [_, Frame::Integer(1), Frame::Integer(buffered)] => buffered,
+                    ref stats => panic!("unexpected stats: {:?}", stats),
+                },
+                channel => panic!("unexpected channel: {:?}", channel),
+            },
+            frame => panic!("unexpected frame: {:?}", frame),
+        };
+
+        if buffered > 0 {
+            break;
+        }
+    }
+
+    assert!(buffered > 0);
+}
+
+/// `DEBUG EXPIRES` 统计有有效期的键，并返回最近的过期时刻
+#[tokio::test]
+async fn debug_expires_reports_nearest_deadline() {
+    let addr = start_debug_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["DEBUG", "EXPIRES"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![Frame::Bulk("expires".into()), Frame::Integer(0), Frame::Bulk("nearest-ms".into()), Frame::Null]),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+
+    for args in [&["SET", "later", "v", "PX", "50000"][..], &["SET", "sooner", "v", "PX", "20000"], &["SET", "forever", "v"]] {
+        conn.write_frame(&command(args)).await.unwrap();
+        assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+    }
+
+    conn.write_frame(&command(&["DEBUG", "EXPIRES"])).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(parts) => match &parts[..] {
+            [_, Frame::Integer(2), _, Frame::Integer(nearest)] => assert!((10_000..=20_000).contains(nearest), "{}", nearest),
+            parts => panic!("unexpected reply: {:?}", parts),
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 宽松模式下，未知命令没有任何回复，连接继续处理后续命令
+#[tokio::test]
+async fn unknown_command_lenient() {
+    let config = server::Config {
+        unknown_command: server::UnknownCommandMode::Lenient,
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let unknown = Frame::Array(vec![Frame::Bulk("FOO".into()), Frame::Bulk("bar".into())]);
+    let ping = Frame::Array(vec![Frame::Bulk("PING".into())]);
+
+    conn.write_frame(&unknown).await.unwrap();
+    conn.write_frame(&ping).await.unwrap();
+
+    // 第一条回复就是 `PING` 的
+    assert_eq!(Frame::from_static_simple("PONG"), conn.read_frame().await.unwrap().unwrap());
+
+    // 订阅模式下同样忽略
+    let subscribe = Frame::Array(vec![Frame::Bulk("SUBSCRIBE".into()), Frame::Bulk("hello".into())]);
+    conn.write_frame(&subscribe).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(parts) => assert!(parts[0] == "subscribe"),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    let unsubscribe = Frame::Array(vec![Frame::Bulk("UNSUBSCRIBE".into())]);
+    conn.write_frame(&unknown).await.unwrap();
+    conn.write_frame(&unsubscribe).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(parts) => assert!(parts[0] == "unsubscribe"),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 匹配大量键的 `KEYS` 执行期间，其它连接的 `GET` 不会被阻塞
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn keys_does_not_block_get() {
+    let addr = start_server().await;
+
+    // 一次性发送大量的 `SET`，再读取所有回复
+    const KEYS: usize = 200_000;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut request = vec![];
+    for i in 0..KEYS {
+        let key = format!("key:{:06}", i);
+        request.extend_from_slice(format!("*3\r\n$3\r\nSET\r\n${}\r\n{}\r\n$1\r\nv\r\n", key.len(), key).as_bytes());
+    }
+    let (mut reader, mut writer) = stream.split();
+    let write = async { writer.write_all(&request).await.unwrap() };
+    let read = async {
+        let mut response = vec![0; KEYS * 5];
+        reader.read_exact(&mut response).await.unwrap();
+    };
+    tokio::join!(write, read);
+
+    let mut keys_conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut get_conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let keys = Frame::Array(vec![Frame::Bulk("KEYS".into()), Frame::Bulk("*a*b*c*d*e*f*".into())]);
+    let get = Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk("key:000001".into())]);
+
+    // 确保两个连接都已被服务端接受
+    let ping = Frame::Array(vec![Frame::Bulk("PING".into())]);
+    for conn in [&mut keys_conn, &mut get_conn] {
+        conn.write_frame(&ping).await.unwrap();
+        conn.read_frame().await.unwrap().unwrap();
+    }
+
+    let start = time::Instant::now();
+    keys_conn.write_frame(&keys).await.unwrap();
+    let keys_task = tokio::spawn(async move {
+        let response = keys_conn.read_frame().await.unwrap().unwrap();
+        (response, start.elapsed())
+    });
+
+    time::sleep(Duration::from_millis(20)).await;
+    get_conn.write_frame(&get).await.unwrap();
+    let value = get_conn.read_frame().await.unwrap().unwrap();
+    let get_elapsed = start.elapsed();
+    assert_eq!(Frame::Bulk("v".into()), value);
+
+    let (response, keys_elapsed) = keys_task.await.unwrap();
+    assert_eq!(Frame::Array(vec![]), response);
+    assert!(get_elapsed < keys_elapsed);
+}
+
+/// 键与值都可以是空字符串，`GET` 返回空值而不是 `Null`
+#[tokio::test]
+async fn set_get_empty_value() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*3\r\n\
+                     $3\r\nSET\r\n\
+                     $0\r\n\r\n\
+                     $0\r\n\r\n")
+        .await
+        .unwrap();
+
+    get_ok(&mut stream).await;
+
+    stream.write_all(b"*2\r\n\
+                     $3\r\nGET\r\n\
+                     $0\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 6];
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"$5\r\nworld\r\n", &response);
+    assert_eq!(b"$0\r\n\r\n", &response);
 }
 
-/// 启动 mini_redis 服务
-async fn start_server() -> SocketAddr {
-    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+/// 执行时间超过阈值的命令出现在 `SLOWLOG GET` 中
+#[tokio::test]
+async fn slowlog_records_slow_command() {
+    let addr = start_debug_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["PING"])).await.unwrap();
+    conn.read_frame().await.unwrap().unwrap();
+
+    conn.write_frame(&command(&["DEBUG", "SLEEP", "0.05"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SLOWLOG", "LEN"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SLOWLOG", "GET"])).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(entries) => {
+            assert_eq!(1, entries.len());
+            match &entries[0] {
+                Frame::Array(entry) => {
+                    match entry[2] {
+                        Frame::Integer(micros) => assert!(micros >= 50_000),
+                        ref frame => panic!("unexpected frame: {:?}", frame),
+                    }
+                    assert_eq!(command(&["DEBUG", "SLEEP", "0.05"]), entry[3]);
+                },
+                frame => panic!("unexpected frame: {:?}", frame),
+            }
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    conn.write_frame(&command(&["SLOWLOG", "RESET"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SLOWLOG", "LEN"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), conn.read_frame().await.unwrap().unwrap());
+}
 
-    let addr = listener.local_addr().unwrap();
+/// `CONFIG SET proto-max-bulk-len` 限制回复中 bulk 的大小，对所有连接生效
+#[tokio::test]
+async fn config_proto_max_bulk_len() {
+    let addr = start_server().await;
 
-    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
 
-    addr
+    conn.write_frame(&command(&["SET", "foo", "longer than the twenty byte limit"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["CONFIG", "SET", "proto-max-bulk-len", "abc"])).await.unwrap();
+    assert!(matches!(conn.read_frame().await.unwrap().unwrap(), Frame::Error(_)));
+
+    conn.write_frame(&command(&["CONFIG", "SET", "proto-max-bulk-len", "20"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["CONFIG", "GET", "proto-*"])).await.unwrap();
+    assert_eq!(command(&["proto-max-bulk-len", "20"]), conn.read_frame().await.unwrap().unwrap());
+
+    // 另一个连接读取超过限制的值，服务端不会发出不完整的回复，而是断开连接
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+    other.write_frame(&command(&["GET", "foo"])).await.unwrap();
+    assert!(matches!(other.read_frame().await, Ok(None) | Err(_)));
+
+    // 未超过限制的回复不受影响
+    conn.write_frame(&command(&["GET", "bar"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 超过 `rate-limit` 的命令被拒绝，连接保持可用，令牌随时间补充
+#[tokio::test]
+async fn rate_limit_rejects_excess_commands() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["CONFIG", "SET", "rate-limit", "5"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    for _ in 0..20 {
+        conn.write_frame(&command(&["PING"])).await.unwrap();
+    }
+
+    let mut rejected = 0;
+    for _ in 0..20 {
+        match conn.read_frame().await.unwrap().unwrap() {
+            Frame::Simple(pong) => assert_eq!("PONG", pong),
+            Frame::Error(err) => {
+                assert_eq!("ERR rate limit exceeded", err);
+                rejected += 1;
+            },
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+    assert!(rejected > 0 && rejected < 20, "rejected {} commands", rejected);
+
+    time::sleep(Duration::from_millis(500)).await;
+
+    conn.write_frame(&command(&["PING"])).await.unwrap();
+    assert_eq!(Frame::Simple("PONG".into()), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 命令名不区分大小写
+#[tokio::test]
+async fn mixed_case_command() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*3\r\n\
+                     $3\r\nSeT\r\n\
+                     $5\r\nhello\r\n\
+                     $5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    get_ok(&mut stream).await;
+
+    stream.write_all(b"*2\r\n\
+                     $3\r\ngEt\r\n\
+                     $5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    get_world(&mut stream).await;
+}
+
+/// 服务关闭时，订阅者在连接断开前收到关闭通知
+#[tokio::test]
+async fn subscriber_notified_on_shutdown() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move { server::run(listener, rx).await });
+
+    let mut sub = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let subscribe = Frame::Array(vec![
+        Frame::Bulk("SUBSCRIBE".into()),
+        Frame::Bulk("hello".into()),
+    ]);
+    sub.write_frame(&subscribe).await.unwrap();
+
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Array(parts) => assert!(parts[0] == "subscribe"),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    tx.send(()).unwrap();
+
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Error(msg) => assert_eq!("ERR server shutting down", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    // 之后连接被关闭
+    assert!(sub.read_frame().await.unwrap().is_none());
+
+    server.await.unwrap();
+}
+
+/// 服务关闭时，订阅者积压在服务端尚未发出的消息在连接断开前全部送达
+#[tokio::test]
+async fn shutdown_flushes_pending_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move { server::run(listener, rx).await });
+
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut sub = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let subscribe = Frame::Array(vec![Frame::Bulk("SUBSCRIBE".into()), Frame::Bulk("hello".into())]);
+    sub.write_frame(&subscribe).await.unwrap();
+    sub.read_frame().await.unwrap().unwrap();
+
+    // 订阅者暂不读取，消息总量超过 socket 的缓冲区，剩余部分积压在服务端的发送队列中
+    let message = Bytes::from(vec![b'x'; 64 * 1024]);
+    for _ in 0..64 {
+        let publish = Frame::Array(vec![
+            Frame::Bulk("PUBLISH".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Bulk(message.clone()),
+        ]);
+        publisher.write_frame(&publish).await.unwrap();
+        assert_eq!(Frame::Integer(1), publisher.read_frame().await.unwrap().unwrap());
+    }
+
+    // 等待订阅者的连接将消息放入发送队列
+    time::sleep(Duration::from_millis(100)).await;
+    tx.send(()).unwrap();
+
+    for _ in 0..64 {
+        match sub.read_frame().await.unwrap().unwrap() {
+            Frame::Array(parts) => {
+                assert!(parts[0] == "message");
+                assert_eq!(Frame::Bulk(message.clone()), parts[2]);
+            },
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+
+    match sub.read_frame().await.unwrap().unwrap() {
+        Frame::Error(msg) => assert_eq!("ERR server shutting down", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+    assert!(sub.read_frame().await.unwrap().is_none());
+
+    server.await.unwrap();
+}
+
+/// 追加数据后，`int` 编码的值转为 `raw` 编码
+#[tokio::test]
+async fn append_int_becomes_raw() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SET", "counter", "100"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "counter"])).await.unwrap();
+    assert_eq!(Frame::Bulk("int".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["APPEND", "counter", "x"])).await.unwrap();
+    assert_eq!(Frame::Integer(4), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "counter"])).await.unwrap();
+    assert_eq!(Frame::Bulk("raw".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "counter"])).await.unwrap();
+    assert_eq!(Frame::Bulk("100x".into()), conn.read_frame().await.unwrap().unwrap());
+
+    // 不存在的键
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "missing"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 不读取消息的订阅者积压的数据超过硬上限后被断开
+/// 超过软上限后仍继续接收消息，硬上限才能生效
+#[tokio::test]
+async fn slow_subscriber_disconnected_over_output_limit() {
+    let config = server::Config {
+        pubsub_output_buffer_limit: server::OutputBufferLimit {
+            hard: 1024 * 1024,
+            soft: 256 * 1024,
+            soft_duration: Duration::from_secs(3600),
+        },
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    // 订阅后不再读取任何数据
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    subscriber.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nflood\r\n").await.unwrap();
+    let mut response = [0; 34];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n$9\r\nsubscribe\r\n$5\r\nflood\r\n:1\r\n", &response);
+
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let message = Frame::Bulk(vec![b'x'; 256 * 1024].into());
+
+    // 内核的 socket 缓存也会接收一部分数据，持续发布直到订阅者被断开
+    for _ in 0..1000 {
+        publisher
+            .write_frame(&Frame::Array(vec![Frame::Bulk("PUBLISH".into()), Frame::Bulk("flood".into()), message.clone()]))
+            .await
+            .unwrap();
+
+        if Frame::Integer(0) == publisher.read_frame().await.unwrap().unwrap() {
+            break;
+        }
+    }
+
+    // 服务端关闭了连接，订阅者读完内核缓存中的数据后读到 EOF
+    // 积压的消息不再发送，最后一条消息可能不完整，因此直接读取 socket
+    let eof = time::timeout(Duration::from_secs(10), async {
+        let mut buf = vec![0; 64 * 1024];
+        while subscriber.read(&mut buf).await.unwrap() > 0 {}
+    });
+    eof.await.expect("subscriber was not disconnected");
+}
+
+/// 积压超过软上限的订阅者读走积压的数据后，立即继续收到新消息
+#[tokio::test]
+async fn subscriber_resumes_after_catching_up() {
+    let config = server::Config {
+        pubsub_output_buffer_limit: server::OutputBufferLimit {
+            hard: 0,
+            soft: 64 * 1024,
+            soft_duration: Duration::from_secs(3600),
+        },
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    subscriber.write_frame(&command(&["SUBSCRIBE", "hello"])).await.unwrap();
+    subscriber.read_frame().await.unwrap().unwrap();
+
+    // 订阅者暂不读取，积压远超软上限
+    const MESSAGES: usize = 64;
+    let message = Bytes::from(vec![b'x'; 64 * 1024]);
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let publish = |message: Bytes| Frame::Array(vec![Frame::Bulk("PUBLISH".into()), Frame::Bulk("hello".into()), Frame::Bulk(message)]);
+    for _ in 0..MESSAGES {
+        publisher.write_frame(&publish(message.clone())).await.unwrap();
+        assert_eq!(Frame::Integer(1), publisher.read_frame().await.unwrap().unwrap());
+    }
+
+    // 远早于 `soft_duration` 收到全部积压的消息，以及之后发布的消息
+    let catch_up = time::timeout(Duration::from_secs(10), async {
+        for _ in 0..MESSAGES {
+            subscriber.read_frame().await.unwrap().unwrap();
+        }
+
+        publisher.write_frame(&publish("last".into())).await.unwrap();
+        assert_eq!(Frame::Integer(1), publisher.read_frame().await.unwrap().unwrap());
+
+        match subscriber.read_frame().await.unwrap().unwrap() {
+            Frame::Array(parts) => assert!(parts[2] == "last"),
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    });
+    catch_up.await.expect("subscriber stalled after catching up");
+}
+
+/// `run` 返回前后台清理任务已经退出
+#[tokio::test]
+async fn purge_task_stopped_before_run_returns() {
+    let (logs, _guard) = Logs::capture();
+
+    // 在当前任务中运行并立即关闭服务，`run` 返回前其它任务没有机会再执行
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    server::run(listener, async {}).await;
+
+    let logs = logs.contents();
+    assert!(logs.contains("Purge background task shutdown"), "{}", logs);
+}
+
+/// `COMMAND DOCS` 返回命令的参数描述
+#[tokio::test]
+async fn command_docs() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let bulk = |value: &str| Frame::Bulk(value.to_string().into());
+
+    conn.write_frame(&command(&["COMMAND", "DOCS", "get"])).await.unwrap();
+    let expected = Frame::Array(vec![
+        bulk("get"),
+        Frame::Array(vec![
+            bulk("arguments"),
+            Frame::Array(vec![Frame::Array(vec![bulk("name"), bulk("key"), bulk("type"), bulk("key")])]),
+        ]),
+    ]);
+    assert_eq!(expected, conn.read_frame().await.unwrap().unwrap());
+
+    // 可选参数带有 `flags`，不存在的命令被忽略
+    conn.write_frame(&command(&["COMMAND", "DOCS", "ping", "foo"])).await.unwrap();
+    let expected = Frame::Array(vec![
+        bulk("ping"),
+        Frame::Array(vec![
+            bulk("arguments"),
+            Frame::Array(vec![Frame::Array(vec![
+                bulk("name"),
+                bulk("message"),
+                bulk("type"),
+                bulk("string"),
+                bulk("flags"),
+                Frame::Array(vec![bulk("optional")]),
+            ])]),
+        ]),
+    ]);
+    assert_eq!(expected, conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["COMMAND", "COUNT"])).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Integer(count) => assert!(count > 30),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 命令表按名称排序，其中每条命令都能以大写的命令名查找到
+#[tokio::test]
+async fn command_table_sorted() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["COMMAND", "DOCS"])).await.unwrap();
+    let names: Vec<String> = match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(docs) => docs
+            .into_iter()
+            .step_by(2)
+            .map(|name| match name {
+                Frame::Bulk(name) => String::from_utf8(name.to_vec()).unwrap(),
+                frame => panic!("unexpected frame: {:?}", frame),
+            })
+            .collect(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", names);
+    for name in &names {
+        assert_eq!(Some(&name[..]), cmd::lookup(&name.to_uppercase()).map(|info| info.name));
+    }
+}
+
+/// 不指定频道的 `SUBSCRIBE` 返回参数个数错误，连接仍可继续使用
+#[tokio::test]
+async fn subscribe_without_channels() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$9\r\nSUBSCRIBE\r\n").await.unwrap();
+
+    let expected = b"-ERR wrong number of arguments for 'subscribe' command\r\n";
+    let mut response = [0; 56];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+}
+
+/// `GETSET` 与 `SET ... GET` 返回相同的原值，但 `GETSET` 总是清除有效期
+#[tokio::test]
+async fn getset_matches_set_get() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for key in ["a", "b"] {
+        conn.write_frame(&command(&["SET", key, "old", "PX", "100"])).await.unwrap();
+        assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+    }
+
+    conn.write_frame(&command(&["GETSET", "a", "new"])).await.unwrap();
+    assert_eq!(Frame::Bulk("old".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SET", "b", "new", "GET", "KEEPTTL"])).await.unwrap();
+    assert_eq!(Frame::Bulk("old".into()), conn.read_frame().await.unwrap().unwrap());
+
+    // 不存在的键返回 `nil`
+    conn.write_frame(&command(&["GETSET", "c", "new"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    time::sleep(Duration::from_millis(300)).await;
+
+    conn.write_frame(&command(&["GET", "a"])).await.unwrap();
+    assert_eq!(Frame::Bulk("new".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "b"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 配置了默认有效期时，未指定有效期的 `SET` 使用默认值，显式指定的有效期优先
+#[tokio::test]
+async fn default_ttl_applies_to_plain_set() {
+    let config = server::Config {
+        default_ttl: Some(Duration::from_millis(200)),
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SET", "plain", "value"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SET", "explicit", "value", "EX", "100"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "plain"])).await.unwrap();
+    assert_eq!(Frame::Bulk("value".into()), conn.read_frame().await.unwrap().unwrap());
+
+    time::sleep(Duration::from_millis(500)).await;
+
+    conn.write_frame(&command(&["GET", "plain"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "explicit"])).await.unwrap();
+    assert_eq!(Frame::Bulk("value".into()), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 同一个连接同时使用 `SUBSCRIBE` 与 `PSUBSCRIBE`，分别收到 `message` 与 `pmessage`
+#[tokio::test]
+async fn subscribe_and_psubscribe_on_one_connection() {
+    let addr = start_server().await;
+
+    let bulk = |s: &str| Frame::Bulk(s.to_string().into());
+
+    let mut sub = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    sub.write_frame(&command(&["SUBSCRIBE", "news.tech"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![bulk("subscribe"), bulk("news.tech"), Frame::Integer(1)]),
+        sub.read_frame().await.unwrap().unwrap()
+    );
+
+    // 订阅数量包括频道与模式
+    sub.write_frame(&command(&["PSUBSCRIBE", "news.*"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![bulk("psubscribe"), bulk("news.*"), Frame::Integer(2)]),
+        sub.read_frame().await.unwrap().unwrap()
+    );
+
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // 频道与模式的订阅者各收到一条
+    publisher.write_frame(&command(&["PUBLISH", "news.tech", "rust"])).await.unwrap();
+    assert_eq!(Frame::Integer(2), publisher.read_frame().await.unwrap().unwrap());
+
+    let message = Frame::Array(vec![bulk("message"), bulk("news.tech"), bulk("rust")]);
+    let pmessage = Frame::Array(vec![bulk("pmessage"), bulk("news.*"), bulk("news.tech"), bulk("rust")]);
+
+    // 两类消息来自不同的流，到达的顺序不确定
+    let first = sub.read_frame().await.unwrap().unwrap();
+    let second = sub.read_frame().await.unwrap().unwrap();
+    assert!(
+        (first == message && second == pmessage) || (first == pmessage && second == message),
+        "unexpected frames {:?} {:?}", first, second
+    );
+
+    // 只匹配模式的频道只收到 `pmessage`
+    publisher.write_frame(&command(&["PUBLISH", "news.sport", "ball"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), publisher.read_frame().await.unwrap().unwrap());
+    assert_eq!(
+        Frame::Array(vec![bulk("pmessage"), bulk("news.*"), bulk("news.sport"), bulk("ball")]),
+        sub.read_frame().await.unwrap().unwrap()
+    );
+
+    // 取消模式订阅后只剩频道订阅
+    sub.write_frame(&command(&["PUNSUBSCRIBE"])).await.unwrap();
+    assert_eq!(
+        Frame::Array(vec![bulk("punsubscribe"), bulk("news.*"), Frame::Integer(1)]),
+        sub.read_frame().await.unwrap().unwrap()
+    );
+
+    publisher.write_frame(&command(&["PUBLISH", "news.tech", "again"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), publisher.read_frame().await.unwrap().unwrap());
+    assert_eq!(
+        Frame::Array(vec![bulk("message"), bulk("news.tech"), bulk("again")]),
+        sub.read_frame().await.unwrap().unwrap()
+    );
+}
+
+/// 不带参数的 `PING` 回复 `PONG`，带消息时原样返回该消息
+#[tokio::test]
+async fn ping_raw() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    stream.write_all(b"*2\r\n\
+                     $4\r\nPING\r\n\
+                     $5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nhello\r\n", &response);
+}
+
+/// 键空间分片后，持有一个分片的锁执行较慢的命令时，其它分片中的键仍可读写
+/// 单一的锁下，每个连接至多有一次 `SET`/`GET` 在 `SUNIONSTORE` 执行期间完成
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn slow_command_does_not_block_other_shards() {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let addr = start_server().await;
+
+    // 构造一个很大的集合，对其做集合运算需要较长的时间
+    const MEMBERS: usize = 200_000;
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    for chunk in (0..MEMBERS).collect::<Vec<_>>().chunks(1000) {
+        let mut args = vec!["SADD".to_string(), "big".to_string()];
+        args.extend(chunk.iter().map(|i| format!("member:{}", i)));
+        conn.write_frame(&command(&args)).await.unwrap();
+    }
+    for _ in 0..MEMBERS / 1000 {
+        conn.read_frame().await.unwrap().unwrap();
+    }
+
+    // 探测用的键分布在不同的分片中，每个连接不停地写入并读回自己的键
+    const PROBES: usize = 8;
+    let running = Arc::new(AtomicBool::new(true));
+    let completed = Arc::new(AtomicUsize::new(0));
+    let mut probes = vec![];
+    for i in 0..PROBES {
+        let mut probe = Connection::new(TcpStream::connect(addr).await.unwrap());
+        let key = format!("probe:{}", i);
+        probe.write_frame(&command(&["SET", &key, "v"])).await.unwrap();
+        probe.read_frame().await.unwrap().unwrap();
+
+        let (running, completed) = (running.clone(), completed.clone());
+        probes.push(tokio::spawn(async move {
+            let mut n = 0;
+            while running.load(Ordering::SeqCst) {
+                n += 1;
+                let value = n.to_string();
+                probe.write_frame(&command(&["SET", &key, &value])).await.unwrap();
+                assert_eq!(Frame::ok(), probe.read_frame().await.unwrap().unwrap());
+
+                probe.write_frame(&command(&["GET", &key])).await.unwrap();
+                assert_eq!(Frame::Bulk(value.into()), probe.read_frame().await.unwrap().unwrap());
+                completed.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+    }
+
+    // 先测量没有其它命令时 `SET`/`GET` 的吞吐量，作为比较的基准
+    time::sleep(Duration::from_millis(20)).await;
+    let start = time::Instant::now();
+    let before = completed.load(Ordering::SeqCst);
+    time::sleep(Duration::from_millis(100)).await;
+    let baseline = (completed.load(Ordering::SeqCst) - before) as f64 / start.elapsed().as_secs_f64();
+
+    let start = time::Instant::now();
+    let before = completed.load(Ordering::SeqCst);
+    conn.write_frame(&command(&["SUNIONSTORE", "dest", "big", "big"])).await.unwrap();
+    let response = conn.read_frame().await.unwrap().unwrap();
+    let during = (completed.load(Ordering::SeqCst) - before) as f64 / start.elapsed().as_secs_f64();
+    assert_eq!(Frame::Integer(MEMBERS as i64), response);
+
+    running.store(false, Ordering::SeqCst);
+    for probe in probes {
+        probe.await.unwrap();
+    }
+
+    assert!(during > baseline / 4.0, "baseline {:.0}/s, during SUNIONSTORE {:.0}/s", baseline, during);
+}
+
+/// 向大量订阅者发布消息时，`GET` 不会被阻塞
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn get_not_blocked_by_large_fanout_publish() {
+    let addr = start_server().await;
+
+    const SUBSCRIBERS: usize = 200;
+    let mut subscribers = vec![];
+    for _ in 0..SUBSCRIBERS {
+        let mut sub = Connection::new(TcpStream::connect(addr).await.unwrap());
+        sub.write_frame(&command(&["SUBSCRIBE", "fanout"])).await.unwrap();
+        sub.read_frame().await.unwrap().unwrap();
+        subscribers.push(sub);
+    }
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.write_frame(&command(&["SET", "foo", "bar"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    // 不停地发布消息，直到 `GET` 全部完成
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let publishing = tokio::spawn(async move {
+        for _ in 0..200 {
+            publisher.write_frame(&command(&["PUBLISH", "fanout", "message"])).await.unwrap();
+            assert_eq!(Frame::Integer(SUBSCRIBERS as i64), publisher.read_frame().await.unwrap().unwrap());
+        }
+    });
+
+    for _ in 0..200 {
+        conn.write_frame(&command(&["GET", "foo"])).await.unwrap();
+        let response = time::timeout(Duration::from_secs(1), conn.read_frame())
+            .await
+            .expect("GET blocked by PUBLISH")
+            .unwrap()
+            .unwrap();
+        assert_eq!(Frame::Bulk("bar".into()), response);
+    }
+
+    publishing.await.unwrap();
+
+    // 所有订阅者都收到了消息
+    for sub in &mut subscribers {
+        match sub.read_frame().await.unwrap().unwrap() {
+            Frame::Array(parts) => assert!(parts[2] == "message"),
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+    }
+}
+
+/// `SUBSTR` 是 `GETRANGE` 的别名，键不存在时返回空的 bulk 而不是 nil
+#[tokio::test]
+async fn substr_alias_of_getrange() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SET", "key", "Hello World"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    for name in ["GETRANGE", "SUBSTR"] {
+        conn.write_frame(&command(&[name, "key", "-5", "-1"])).await.unwrap();
+        assert_eq!(Frame::Bulk("World".into()), conn.read_frame().await.unwrap().unwrap());
+
+        conn.write_frame(&command(&[name, "missing", "0", "-1"])).await.unwrap();
+        assert_eq!(Frame::Bulk("".into()), conn.read_frame().await.unwrap().unwrap());
+    }
+
+    // 参数数量与 `GETRANGE` 相同
+    conn.write_frame(&command(&["SUBSTR", "key", "0"])).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Error(msg) => assert_eq!("ERR wrong number of arguments for 'substr' command", msg),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+/// 先返回 `errors` 次错误再正常接受连接的监听器，模拟暂时用尽文件描述符
+struct FlakyListener {
+    listener: TcpListener,
+    errors: usize,
+}
+
+impl server::Accept for FlakyListener {
+    async fn accept(&mut self) -> std::io::Result<TcpStream> {
+        if self.errors > 0 {
+            self.errors -= 1;
+            return Err(std::io::Error::other("Too many open files"));
+        }
+
+        let (socket, _) = self.listener.accept().await?;
+        Ok(socket)
+    }
+}
+
+/// 接受连接反复出错时，`Retry` 策略下服务继续运行，默认的 `Fail` 策略下服务停止
+#[tokio::test(start_paused = true)]
+async fn accept_error_policy() {
+    // 重试间隔依次为 1、2、4 .. 64 秒，20 次错误已超过 `Fail` 策略的上限
+    const ERRORS: usize = 20;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = server::Config {
+        accept_error: server::AcceptErrorPolicy::Retry,
+        ..server::Config::default()
+    };
+    let listener = FlakyListener { listener, errors: ERRORS };
+    let server = tokio::spawn(server::run_with_listener(listener, std::future::pending::<()>(), config));
+
+    // 所有重试的间隔之和不到一小时
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.write_frame(&Frame::Array(vec![Frame::Bulk("PING".into())])).await.unwrap();
+    let pong = time::timeout(Duration::from_secs(3600), conn.read_frame())
+        .await
+        .expect("server stopped accepting connections")
+        .unwrap()
+        .unwrap();
+    assert_eq!(Frame::from_static_simple("PONG"), pong);
+    assert!(!server.is_finished());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let listener = FlakyListener { listener, errors: ERRORS };
+    let server = tokio::spawn(server::run_with_listener(listener, std::future::pending::<()>(), server::Config::default()));
+
+    // 没有收到关闭信号，服务也会因接受连接失败而退出
+    server.await.unwrap();
+}
+
+/// 不支持脚本，`EVAL`/`EVALSHA`/`SCRIPT` 返回明确的错误，而不是未知命令
+#[tokio::test]
+async fn scripting_is_unsupported() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for args in [
+        &["EVAL", "return 1", "0"][..],
+        &["EVALSHA", "e0e1f9fabfc9d4800c877a703b823ac0578ff8db", "1", "key", "arg"],
+        &["SCRIPT", "LOAD", "return 1"],
+    ] {
+        conn.write_frame(&command(args)).await.unwrap();
+        assert_eq!(
+            Frame::Error("ERR this build does not support scripting".into()),
+            conn.read_frame().await.unwrap().unwrap()
+        );
+    }
+
+    // 连接仍可正常使用
+    conn.write_frame(&command(&["PING"])).await.unwrap();
+    assert_eq!(Frame::from_static_simple("PONG"), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// `EXPIRE`/`PEXPIRE` 为没有有效期的键设置有效期，键不存在时返回 0
+#[tokio::test]
+async fn expire_existing_key() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["SET", "hello", "world"])).await.unwrap();
+    assert_eq!(Frame::from_static_simple("OK"), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["PEXPIRE", "hello", "100"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["EXPIRE", "missing", "10"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), conn.read_frame().await.unwrap().unwrap());
+
+    // 有效期内键仍然存在
+    conn.write_frame(&command(&["GET", "hello"])).await.unwrap();
+    assert_eq!(Frame::Bulk("world".into()), conn.read_frame().await.unwrap().unwrap());
+
+    time::sleep(Duration::from_millis(300)).await;
+
+    conn.write_frame(&command(&["GET", "hello"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    // 不为正数的有效期直接删除键
+    conn.write_frame(&command(&["SET", "hello", "world"])).await.unwrap();
+    assert_eq!(Frame::from_static_simple("OK"), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["EXPIRE", "hello", "0"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "hello"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 整数参数无法解析时回复 Redis 的整数错误，缺少参数时仍回复协议错误
+#[tokio::test]
+async fn integer_argument_errors() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["ZRANGEBYSCORE", "key", "0", "1", "LIMIT", "abc", "1"])).await.unwrap();
+    assert_eq!(
+        Frame::Error("ERR value is not an integer or out of range".into()),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+
+    conn.write_frame(&command(&["GETRANGE", "key", "0", "x1"])).await.unwrap();
+    assert_eq!(
+        Frame::Error("ERR value is not an integer or out of range".into()),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+
+    conn.write_frame(&command(&["SCAN", "abc"])).await.unwrap();
+    assert_eq!(Frame::Error("ERR invalid cursor".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["ZRANGEBYSCORE", "key", "0", "1", "LIMIT", "0"])).await.unwrap();
+    assert_eq!(
+        Frame::Error("protocol error: unnexpected end of stream.".into()),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+}
+
+/// `run_with_ready` 开始接受连接时发送监听的地址，收到后即可连接
+#[tokio::test]
+async fn run_with_ready_reports_address() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bound = listener.local_addr().unwrap();
+
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(server::run_with_ready(listener, stop_rx, ready_tx));
+
+    let addr = ready_rx.await.unwrap();
+    assert_eq!(bound, addr);
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.write_frame(&Frame::Array(vec![Frame::Bulk("PING".into())])).await.unwrap();
+    assert_eq!(Frame::from_static_simple("PONG"), conn.read_frame().await.unwrap().unwrap());
+
+    drop(conn);
+    stop_tx.send(()).unwrap();
+    server.await.unwrap();
+}
+
+/// 空闲超过 `idle_timeout` 的连接被服务端关闭，仍在发送命令的连接不受影响
+#[tokio::test]
+async fn idle_connection_closed_after_timeout() {
+    let config = server::Config {
+        idle_timeout: Some(Duration::from_millis(200)),
+        ..server::Config::default()
+    };
+    let addr = start_server_with_config(config).await;
+
+    let mut idle = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut active = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // 活跃的连接每次等待都不超过超时时间
+    for _ in 0..5 {
+        time::sleep(Duration::from_millis(100)).await;
+        active.write_frame(&Frame::Array(vec![Frame::Bulk("PING".into())])).await.unwrap();
+        assert_eq!(Frame::from_static_simple("PONG"), active.read_frame().await.unwrap().unwrap());
+    }
+
+    // 什么都没有发送的连接已被关闭
+    let closed = time::timeout(Duration::from_secs(1), idle.read_frame())
+        .await
+        .expect("idle connection was not closed");
+    assert!(matches!(closed, Ok(None) | Err(_)));
+}
+
+/// `LPOP`/`RPOP` 指定 `count` 时以数组返回弹出的元素，不指定时返回单个 bulk
+#[tokio::test]
+async fn pop_with_count() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let bulk = |value: &'static str| Frame::Bulk(value.into());
+
+    conn.write_frame(&command(&["RPUSH", "list", "a", "b", "c"])).await.unwrap();
+    assert_eq!(Frame::Integer(3), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["LPOP", "list", "2"])).await.unwrap();
+    assert_eq!(Frame::Array(vec![bulk("a"), bulk("b")]), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["LPUSH", "list", "y", "x"])).await.unwrap();
+    assert_eq!(Frame::Integer(3), conn.read_frame().await.unwrap().unwrap());
+
+    // 从尾部弹出时按弹出的顺序返回
+    conn.write_frame(&command(&["RPOP", "list", "2"])).await.unwrap();
+    assert_eq!(Frame::Array(vec![bulk("c"), bulk("y")]), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["LPOP", "list"])).await.unwrap();
+    assert_eq!(bulk("x"), conn.read_frame().await.unwrap().unwrap());
+
+    // 列表弹空后键被删除
+    conn.write_frame(&command(&["LPOP", "list"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["RPOP", "list", "2"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    // 列表不为空时 `count` 为 0 返回空数组
+    conn.write_frame(&command(&["RPUSH", "list", "a"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["LPOP", "list", "0"])).await.unwrap();
+    assert_eq!(Frame::Array(vec![]), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SET", "string", "value"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["LPOP", "string", "1"])).await.unwrap();
+    assert!(matches!(conn.read_frame().await.unwrap().unwrap(), Frame::Error(msg) if msg.starts_with("WRONGTYPE")));
+}
+
+/// 分片使用读写锁，只读取的命令可以同时执行
+/// `SDIFF` 持有 `big` 所在分片的读锁期间，读取同一个键的命令仍能完成
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn reads_of_one_shard_run_concurrently() {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let addr = start_server().await;
+
+    // `SDIFF big big` 需要逐个检查成员，耗时较长而回复为空
+    const MEMBERS: usize = 200_000;
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    for chunk in (0..MEMBERS).collect::<Vec<_>>().chunks(1000) {
+        let mut args = vec!["SADD".to_string(), "big".to_string()];
+        args.extend(chunk.iter().map(|i| format!("member:{}", i)));
+        conn.write_frame(&command(&args)).await.unwrap();
+    }
+    for _ in 0..MEMBERS / 1000 {
+        conn.read_frame().await.unwrap().unwrap();
+    }
+
+    // 每个连接不停地读取同一个键
+    const PROBES: usize = 4;
+    let running = Arc::new(AtomicBool::new(true));
+    let completed = Arc::new(AtomicUsize::new(0));
+    let mut probes = vec![];
+    for _ in 0..PROBES {
+        let mut probe = Connection::new(TcpStream::connect(addr).await.unwrap());
+        let (running, completed) = (running.clone(), completed.clone());
+        probes.push(tokio::spawn(async move {
+            while running.load(Ordering::SeqCst) {
+                probe.write_frame(&command(&["OBJECT", "ENCODING", "big"])).await.unwrap();
+                assert_eq!(Frame::Bulk("hashtable".into()), probe.read_frame().await.unwrap().unwrap());
+                completed.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+    }
+
+    time::sleep(Duration::from_millis(20)).await;
+    let start = time::Instant::now();
+    let before = completed.load(Ordering::SeqCst);
+    time::sleep(Duration::from_millis(100)).await;
+    let baseline = (completed.load(Ordering::SeqCst) - before) as f64 / start.elapsed().as_secs_f64();
+
+    let start = time::Instant::now();
+    let before = completed.load(Ordering::SeqCst);
+    conn.write_frame(&command(&["SDIFF", "big", "big"])).await.unwrap();
+    let response = conn.read_frame().await.unwrap().unwrap();
+    let during = (completed.load(Ordering::SeqCst) - before) as f64 / start.elapsed().as_secs_f64();
+    assert_eq!(Frame::Array(vec![]), response);
+
+    running.store(false, Ordering::SeqCst);
+    for probe in probes {
+        probe.await.unwrap();
+    }
+
+    assert!(during > baseline / 4.0, "baseline {:.0}/s, during SDIFF {:.0}/s", baseline, during);
+}
+
+/// `SET EX`、`PERSIST`、`RENAME` 之后，`DEBUG INVARIANTS` 确认有效期的记录保持一致
+#[tokio::test]
+async fn debug_invariants_after_ttl_changes() {
+    let addr = start_debug_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // 使用多个键，重命名时跨越不同的分片
+    for i in 0..32 {
+        let key = format!("key:{}", i);
+        let renamed = format!("renamed:{}", i);
+
+        conn.write_frame(&command(&["SET", &key, "v", "EX", "100"])).await.unwrap();
+        assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+        if i % 2 == 0 {
+            conn.write_frame(&command(&["PERSIST", &key])).await.unwrap();
+            assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+        }
+
+        conn.write_frame(&command(&["RENAME", &key, &renamed])).await.unwrap();
+        assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+        // 有效期随键一起转移
+        conn.write_frame(&command(&["TTL", &renamed])).await.unwrap();
+        let expected = if i % 2 == 0 { Frame::Integer(-1) } else { Frame::Integer(100) };
+        assert_eq!(expected, conn.read_frame().await.unwrap().unwrap());
+
+        conn.write_frame(&command(&["DEBUG", "INVARIANTS"])).await.unwrap();
+        assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+    }
+
+    // 重命名覆盖有有效期的键
+    conn.write_frame(&command(&["RENAME", "renamed:0", "renamed:1"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["TTL", "renamed:1"])).await.unwrap();
+    assert_eq!(Frame::Integer(-1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["DEBUG", "INVARIANTS"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["RENAME", "missing", "other"])).await.unwrap();
+    assert_eq!(Frame::Error("ERR no such key".into()), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// `TYPE` 返回键存储的值的类型，键不存在时返回 `none`
+#[tokio::test]
+async fn type_of_keys() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for args in [&["SET", "string", "value"][..], &["SET", "int", "42"], &["SADD", "set", "a"], &["RPUSH", "list", "a"]] {
+        conn.write_frame(&command(args)).await.unwrap();
+        conn.read_frame().await.unwrap().unwrap();
+    }
+
+    for (key, expected) in [("string", "string"), ("int", "string"), ("set", "set"), ("list", "list"), ("missing", "none")] {
+        conn.write_frame(&command(&["TYPE", key])).await.unwrap();
+        assert_eq!(Frame::Simple(expected.into()), conn.read_frame().await.unwrap().unwrap(), "{}", key);
+    }
+}
+
+/// 求交集时只遍历最小的集合，`SINTERCARD` 的 `LIMIT` 达到后即停止
+#[tokio::test]
+async fn intersection_iterates_smallest_set() {
+    let addr = start_debug_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // 两个大集合都包含 a 与 b，只有小集合包含 c
+    const MEMBERS: usize = 10_000;
+    for key in ["huge1", "huge2"] {
+        for chunk in (0..MEMBERS).collect::<Vec<_>>().chunks(1000) {
+            let mut sadd = vec!["SADD".to_string(), key.to_string(), "a".to_string(), "b".to_string()];
+            sadd.extend(chunk.iter().map(|i| format!("member:{}", i)));
+            conn.write_frame(&command(&sadd)).await.unwrap();
+            conn.read_frame().await.unwrap().unwrap();
+        }
+    }
+    conn.write_frame(&command(&["SADD", "tiny", "a", "b", "c"])).await.unwrap();
+    assert_eq!(Frame::Integer(3), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SINTER", "huge1", "tiny", "huge2"])).await.unwrap();
+    match conn.read_frame().await.unwrap().unwrap() {
+        Frame::Array(members) => {
+            let mut members: Vec<_> = members.into_iter().map(|member| format!("{}", member)).collect();
+            members.sort();
+            assert_eq!(vec!["a", "b"], members);
+        },
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    conn.write_frame(&command(&["SINTERCARD", "3", "huge1", "tiny", "huge2"])).await.unwrap();
+    assert_eq!(Frame::Integer(2), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SINTERCARD", "3", "huge1", "tiny", "huge2", "LIMIT", "1"])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    // 不存在的键使交集为空
+    conn.write_frame(&command(&["SINTERCARD", "2", "huge1", "missing"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SINTERCARD", "3", "huge1", "tiny"])).await.unwrap();
+    assert_eq!(
+        Frame::Error("ERR Number of keys can't be greater than number of args".into()),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+
+    // 求交集时遍历的成员数量恰好是小集合的大小，不论小集合排在第几个
+    for keys in [["huge1", "tiny", "huge2"], ["tiny", "huge1", "huge2"], ["huge1", "huge2", "tiny"]] {
+        let mut debug = vec!["DEBUG", "INTERSECTION"];
+        debug.extend(keys);
+        conn.write_frame(&command(&debug)).await.unwrap();
+        assert_eq!(Frame::Integer(3), conn.read_frame().await.unwrap().unwrap(), "{:?}", keys);
+    }
+
+    // 有键不存在时无需遍历
+    conn.write_frame(&command(&["DEBUG", "INTERSECTION", "huge1", "missing"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 字段较少的哈希以 `listpack` 保存，字段数量超过阈值后转为 `hashtable`
+#[tokio::test]
+async fn hash_encoding_grows_to_hashtable() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    conn.write_frame(&command(&["HSET", "hash", "a", "1", "b", "2"])).await.unwrap();
+    assert_eq!(Frame::Integer(2), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "hash"])).await.unwrap();
+    assert_eq!(Frame::Bulk("listpack".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["TYPE", "hash"])).await.unwrap();
+    assert_eq!(Frame::Simple("hash".into()), conn.read_frame().await.unwrap().unwrap());
+
+    // 共 129 个字段，超过 128 的阈值
+    let mut hset = vec!["HSET".to_string(), "hash".to_string()];
+    for i in 0..127 {
+        hset.push(format!("field:{}", i));
+        hset.push(i.to_string());
+    }
+    conn.write_frame(&command(&hset)).await.unwrap();
+    assert_eq!(Frame::Integer(127), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "hash"])).await.unwrap();
+    assert_eq!(Frame::Bulk("hashtable".into()), conn.read_frame().await.unwrap().unwrap());
+
+    // 转换后已有的字段仍然可读，覆盖已有字段不计入新添加的数量
+    conn.write_frame(&command(&["HGET", "hash", "a"])).await.unwrap();
+    assert_eq!(Frame::Bulk("1".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["HSET", "hash", "field:0", "zero"])).await.unwrap();
+    assert_eq!(Frame::Integer(0), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["HGET", "hash", "field:0"])).await.unwrap();
+    assert_eq!(Frame::Bulk("zero".into()), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["HGET", "hash", "missing"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    // 值过长时同样转为 `hashtable`
+    let long = "x".repeat(65);
+    conn.write_frame(&command(&["HSET", "long", "f", &long])).await.unwrap();
+    assert_eq!(Frame::Integer(1), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["OBJECT", "ENCODING", "long"])).await.unwrap();
+    assert_eq!(Frame::Bulk("hashtable".into()), conn.read_frame().await.unwrap().unwrap());
+
+    // 字段与值不成对
+    conn.write_frame(&command(&["HSET", "hash", "a", "1", "b"])).await.unwrap();
+    assert_eq!(
+        Frame::Error("ERR wrong number of arguments for 'hset' command".into()),
+        conn.read_frame().await.unwrap().unwrap()
+    );
+}
+
+/// 有效期超出范围的 `SET` 返回错误，不会在持有分片的锁时 panic，之后同一分片的命令仍能执行
+#[tokio::test]
+async fn set_overflowing_expire() {
+    let addr = start_server().await;
+
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // 以秒为单位时溢出，以毫秒为单位的相同数值仍在范围内
+    for option in ["EX", "EXAT"] {
+        conn.write_frame(&command(&["SET", "key", "value", option, "18446744073709551615"])).await.unwrap();
+        assert_eq!(
+            Frame::Error("ERR invalid expire time in 'set' command".into()),
+            conn.read_frame().await.unwrap().unwrap(),
+            "{}",
+            option
+        );
+    }
+
+    conn.write_frame(&command(&["GET", "key"])).await.unwrap();
+    assert_eq!(Frame::Null, conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["SET", "key", "value", "EX", "100"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "key"])).await.unwrap();
+    assert_eq!(Frame::Bulk("value".into()), conn.read_frame().await.unwrap().unwrap());
+}
+
+/// 读取请求时的长度上限与 `proto-max-bulk-len` 相同，默认可以写入、读取大于 8MB 的值
+#[tokio::test]
+async fn read_limit_follows_proto_max_bulk_len() {
+    let addr = start_server().await;
+
+    // 客户端读取回复时默认同样有上限，需调大才能读取 `GET` 的回复
+    let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+    conn.set_max_read_len(usize::MAX);
+
+    let value = Bytes::from(vec![b'x'; 9 * 1024 * 1024]);
+    let set = Frame::Array(vec![Frame::Bulk("SET".into()), Frame::Bulk("big".into()), Frame::Bulk(value.clone())]);
+    conn.write_frame(&set).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["GET", "big"])).await.unwrap();
+    assert_eq!(Frame::Bulk(value), conn.read_frame().await.unwrap().unwrap());
+
+    conn.write_frame(&command(&["CONFIG", "SET", "proto-max-bulk-len", "1024"])).await.unwrap();
+    assert_eq!(Frame::ok(), conn.read_frame().await.unwrap().unwrap());
+
+    // 修改后的上限对已建立的连接同样生效，声明的长度超过上限时断开连接
+    let set = Frame::Array(vec![Frame::Bulk("SET".into()), Frame::Bulk("foo".into()), Frame::Bulk(vec![b'x'; 2048].into())]);
+    conn.write_frame(&set).await.unwrap();
+    assert!(matches!(conn.read_frame().await, Ok(None) | Err(_)));
+}
+
+async fn get_ok(stream: &mut TcpStream) {
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+}
+
+async fn get_null(stream: &mut TcpStream) {
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+async fn get_world(stream: &mut TcpStream) {
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+}
+
+/// 订阅模式下执行其它命令时的错误回复
+fn subscribe_context_error(command: &str) -> Vec<u8> {
+    format!(
+        "-ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context\r\n",
+        command
+    )
+    .into_bytes()
+}
+
+/// 启动 mini_redis 服务
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
+
+/// 以指定的配置启动 mini_redis 服务
+async fn start_server_with_config(config: server::Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run_with_config(listener, tokio::signal::ctrl_c(), config).await });
+
+    addr
+}
+
+/// 启动开启了 `DEBUG` 命令的 mini_redis 服务
+async fn start_debug_server() -> SocketAddr {
+    let config = server::Config {
+        enable_debug_command: true,
+        ..server::Config::default()
+    };
+
+    start_server_with_config(config).await
+}
+
+/// 由命令名及参数构造请求的 frame
+fn command<S: AsRef<str>>(args: &[S]) -> Frame {
+    Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_ref().as_bytes()))).collect())
+}
+
+/// 记录日志的共享 buffer，用于检查服务端在执行命令时记录的日志
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    /// 此后当前线程的日志都写入返回的 `Logs`，直到 guard 被 drop
+    /// 测试使用单线程运行时，服务端所有任务的日志都由此记录
+    fn capture() -> (Logs, tracing::subscriber::DefaultGuard) {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    /// 已记录的日志
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,229 @@
+use std::{sync::Mutex, time::Duration};
+
+use bytes::Bytes;
+use mini_redis::{
+    cmd::{Del, Get, Incr, Set, SetOptions, Persist, SetOutcome, Ttl},
+    Db, Frame, KvStore,
+};
+use tokio::time::{self, Instant};
+
+/// 记录每次调用的存储，只实现命令逻辑需要的部分
+#[derive(Default)]
+struct RecordingStore {
+    calls: Mutex<Vec<Call>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Call {
+    Get(String),
+    Set(String, Bytes, Option<Duration>),
+    SetWithOptions(String, Bytes, SetOptions),
+    Del(Vec<String>),
+    IncrBy(String, i64),
+    Ttl(String),
+    Persist(String),
+}
+
+impl RecordingStore {
+    fn calls(&self) -> Vec<Call> {
+        std::mem::take(&mut self.calls.lock().unwrap())
+    }
+}
+
+impl KvStore for RecordingStore {
+    fn get(&self, key: &str) -> mini_redis::Result<Option<Bytes>> {
+        self.calls.lock().unwrap().push(Call::Get(key.to_string()));
+        Ok(Some("value".into()))
+    }
+
+    fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
+        self.calls.lock().unwrap().push(Call::Set(key, value, expire));
+    }
+
+    fn set_with_options(&self, key: String, value: Bytes, options: &SetOptions) -> mini_redis::Result<SetOutcome> {
+        self.calls.lock().unwrap().push(Call::SetWithOptions(key, value, *options));
+        Ok(SetOutcome { written: false, previous: Some("old".into()) })
+    }
+
+    fn del(&self, keys: &[String]) -> usize {
+        self.calls.lock().unwrap().push(Call::Del(keys.to_vec()));
+        keys.len()
+    }
+
+    fn incr_by(&self, key: &str, delta: i64) -> mini_redis::Result<i64> {
+        self.calls.lock().unwrap().push(Call::IncrBy(key.to_string(), delta));
+        Err("ERR value is not an integer or out of range".into())
+    }
+
+    fn ttl(&self, key: &str) -> Option<Option<Instant>> {
+        self.calls.lock().unwrap().push(Call::Ttl(key.to_string()));
+        Some(None)
+    }
+
+    fn persist(&self, key: &str) -> bool {
+        self.calls.lock().unwrap().push(Call::Persist(key.to_string()));
+        false
+    }
+}
+
+/// `SET` 将键、值及有效期原样交给存储
+#[test]
+fn set_calls_store() {
+    let store = RecordingStore::default();
+
+    let response = Set::new("foo", "bar".into(), Some(Duration::from_secs(10))).execute(&store);
+
+    assert_eq!(Frame::ok(), response);
+    assert_eq!(
+        vec![Call::Set("foo".into(), "bar".into(), Some(Duration::from_secs(10)))],
+        store.calls()
+    );
+}
+
+/// 带条件或 `GET` 的 `SET` 使用 `set_with_options`，按结果回复
+#[test]
+fn set_with_options_calls_store() {
+    let store = RecordingStore::default();
+
+    let options = SetOptions::new().nx().get();
+    assert_eq!(Frame::Bulk("old".into()), Set::with_options("foo", "bar".into(), options).execute(&store));
+    assert_eq!(Frame::Null, Set::with_options("foo", "bar".into(), SetOptions::new().xx()).execute(&store));
+
+    assert_eq!(
+        vec![
+            Call::SetWithOptions("foo".into(), "bar".into(), options),
+            Call::SetWithOptions("foo".into(), "bar".into(), SetOptions::new().xx()),
+        ],
+        store.calls()
+    );
+}
+
+/// 其它命令按存储的返回值构建回复
+#[test]
+fn commands_use_store_results() {
+    let store = RecordingStore::default();
+
+    assert_eq!(Frame::Bulk("value".into()), Get::new("foo").execute(&store));
+    assert_eq!(Frame::Integer(2), Del::new(&["a".into(), "b".into()]).execute(&store));
+    assert!(matches!(Incr::new("foo").execute(&store), Frame::Error(_)));
+    assert_eq!(Frame::Integer(-1), Ttl::new("foo").execute(&store));
+    assert_eq!(Frame::Integer(0), Persist::new("foo").execute(&store));
+
+    assert_eq!(
+        vec![
+            Call::Get("foo".into()),
+            Call::Del(vec!["a".into(), "b".into()]),
+            Call::IncrBy("foo".into(), 1),
+            Call::Ttl("foo".into()),
+            Call::Persist("foo".into()),
+        ],
+        store.calls()
+    );
+}
+
+/// `Db` 的后台任务在有效期到达后删除键，无需经过 TCP 连接
+#[tokio::test(start_paused = true)]
+async fn db_purges_expired_key() {
+    let db = Db::new();
+
+    assert_eq!(Frame::ok(), Set::new("foo", "bar".into(), Some(Duration::from_millis(100))).execute(&db));
+
+    time::advance(Duration::from_millis(50)).await;
+    assert_eq!(Some(Bytes::from("bar")), db.get("foo").unwrap());
+
+    // `Db::get` 不检查有效期，键消失说明已被后台任务清理
+    time::advance(Duration::from_millis(60)).await;
+    tokio::task::yield_now().await;
+    assert_eq!(None, db.get("foo").unwrap());
+}
+
+/// `KEEPTTL` 保留原有的过期时刻，而不是从写入时重新计算有效期
+#[tokio::test(start_paused = true)]
+async fn keep_ttl_preserves_deadline() {
+    let db = Db::new();
+
+    db.set("foo".into(), "old".into(), Some(Duration::from_millis(100)));
+    time::advance(Duration::from_millis(60)).await;
+
+    let outcome = db.set_with_options("foo".into(), "new".into(), &SetOptions::new().get().keep_ttl()).unwrap();
+    assert_eq!(SetOutcome { written: true, previous: Some("old".into()) }, outcome);
+
+    // 原有的过期时刻之前仍然存在
+    time::advance(Duration::from_millis(39)).await;
+    tokio::task::yield_now().await;
+    assert_eq!(Some(Bytes::from("new")), db.get("foo").unwrap());
+
+    time::advance(Duration::from_millis(2)).await;
+    tokio::task::yield_now().await;
+    assert_eq!(None, db.get("foo").unwrap());
+}
+
+/// `TTL`/`PTTL` 返回的剩余有效期随时间减少，键不存在时为 -2，没有有效期时为 -1
+#[tokio::test(start_paused = true)]
+async fn ttl_counts_down() {
+    let db = Db::new();
+
+    assert_eq!(Frame::Integer(-2), Ttl::new("foo").execute(&db));
+    db.set("forever".into(), "v".into(), None);
+    assert_eq!(Frame::Integer(-1), Ttl::pttl("forever").execute(&db));
+
+    db.set("foo".into(), "bar".into(), Some(Duration::from_secs(10)));
+    assert_eq!(Frame::Integer(10_000), Ttl::pttl("foo").execute(&db));
+    assert_eq!(Frame::Integer(10), Ttl::new("foo").execute(&db));
+
+    // 秒数四舍五入
+    time::advance(Duration::from_millis(2_600)).await;
+    assert_eq!(Frame::Integer(7_400), Ttl::pttl("foo").execute(&db));
+    assert_eq!(Frame::Integer(7), Ttl::new("foo").execute(&db));
+
+    time::advance(Duration::from_millis(7_399)).await;
+    assert_eq!(Frame::Integer(1), Ttl::pttl("foo").execute(&db));
+    assert_eq!(Frame::Integer(0), Ttl::new("foo").execute(&db));
+
+    // 过期后被清理，键不再存在
+    time::advance(Duration::from_millis(2)).await;
+    tokio::task::yield_now().await;
+    assert_eq!(Frame::Integer(-2), Ttl::pttl("foo").execute(&db));
+}
+
+/// `PERSIST` 移除有效期后，后台任务不再清理该键
+#[tokio::test(start_paused = true)]
+async fn persist_removes_expiry() {
+    let db = Db::new();
+
+    assert_eq!(Frame::Integer(0), Persist::new("foo").execute(&db));
+
+    db.set("forever".into(), "v".into(), None);
+    assert_eq!(Frame::Integer(0), Persist::new("forever").execute(&db));
+
+    db.set("foo".into(), "bar".into(), Some(Duration::from_millis(100)));
+    assert_eq!(Frame::Integer(1), Persist::new("foo").execute(&db));
+    assert_eq!(Frame::Integer(-1), Ttl::pttl("foo").execute(&db));
+
+    time::advance(Duration::from_millis(150)).await;
+    tokio::task::yield_now().await;
+    assert_eq!(Some(Bytes::from("bar")), db.get("foo").unwrap());
+
+    // 有效期已被移除，再次执行返回 0
+    assert_eq!(Frame::Integer(0), Persist::new("foo").execute(&db));
+}
+
+/// 最后一个 `Db` 句柄被 drop 后，后台清理任务退出
+#[tokio::test]
+async fn purge_task_stops_with_last_handle() {
+    let metrics = tokio::runtime::Handle::current().metrics();
+
+    let db = Db::new();
+    let clone = db.clone();
+    tokio::task::yield_now().await;
+    assert_eq!(1, metrics.num_alive_tasks());
+
+    // 仍有句柄时后台任务继续运行
+    drop(db);
+    tokio::task::yield_now().await;
+    assert_eq!(1, metrics.num_alive_tasks());
+
+    drop(clone);
+    tokio::task::yield_now().await;
+    assert_eq!(0, metrics.num_alive_tasks());
+}